@@ -33,6 +33,7 @@ fn main() {
                 let action = if is_pressed { "pressed" } else { "released" };
                 println!("Key {:?} {} on device {}", key_id, action, device_id);
             }
+            other => println!("Unhandled event: {:?}", other),
         }
     }
 }
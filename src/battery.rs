@@ -0,0 +1,489 @@
+//! Watching the battery level of wireless devices.
+//!
+//! The SDK reports battery levels only when asked.  A [`BatteryMonitor`]
+//! polls [`PropertyId::BatteryLevel`] on every device that can read it and
+//! turns the readings into [`BatteryEvent`]s: level changes, crossing the
+//! low and critical thresholds, and charging completing.  The SDK does not
+//! say whether a device is charging, so charging is inferred from the level
+//! rising.
+//!
+//! A [`BatteryIndicator`] optionally shows the level in color, e.g. on the
+//! logo of a headset or on one key of the keyboard.
+//!
+//! ```no_run
+//! # use std::ops::ControlFlow;
+//! # use std::time::Duration;
+//! # use cue_sdk::battery::{BatteryEvent, BatteryIndicator, BatteryMonitor};
+//! # use cue_sdk::zone::Zone;
+//! # let session = cue_sdk::connect().unwrap();
+//! let mut monitor = BatteryMonitor::new()
+//!     .with_indicator(BatteryIndicator::new(Zone::builtin("logo").unwrap()));
+//! monitor
+//!     .run(&session, Duration::from_secs(60), |event| {
+//!         if let BatteryEvent::Low { device_id, level } = event {
+//!             eprintln!("{device_id} is at {level}%");
+//!         }
+//!         ControlFlow::Continue(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::device::{DeviceId, DeviceType};
+use crate::error::{Result, SdkError};
+use crate::led::{LedColor, Rgba};
+use crate::property::{PropertyFlags, PropertyId, PropertyInfo, PropertyValue};
+use crate::session::Session;
+use crate::trace;
+use crate::zone::Zone;
+
+// ---------------------------------------------------------------------------
+// BatteryThresholds
+// ---------------------------------------------------------------------------
+
+/// Battery levels, in percent, at which events are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryThresholds {
+    /// Report [`BatteryEvent::Low`] when the level drops to this or below.
+    pub low: i32,
+    /// Report [`BatteryEvent::Critical`] when the level drops to this or
+    /// below.
+    pub critical: i32,
+    /// Report [`BatteryEvent::ChargingComplete`] when a rising level reaches
+    /// this.
+    pub full: i32,
+}
+
+impl Default for BatteryThresholds {
+    /// Low at 20 %, critical at 5 %, full at 100 %.
+    fn default() -> Self {
+        Self {
+            low: 20,
+            critical: 5,
+            full: 100,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BatteryEvent
+// ---------------------------------------------------------------------------
+
+/// A change in the battery state of a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatteryEvent {
+    /// The level changed.  `previous` is `None` for the first reading.
+    Changed {
+        device_id: DeviceId,
+        previous: Option<i32>,
+        level: i32,
+    },
+    /// The level dropped to the low threshold.  Reported again only after
+    /// the level has risen above it.
+    Low { device_id: DeviceId, level: i32 },
+    /// The level dropped to the critical threshold.  Reported again only
+    /// after the level has risen above it.
+    Critical { device_id: DeviceId, level: i32 },
+    /// The level rose to the full threshold.
+    ChargingComplete { device_id: DeviceId },
+    /// The device disconnected.
+    Lost { device_id: DeviceId },
+}
+
+impl BatteryEvent {
+    /// The device the event is about.
+    pub fn device_id(&self) -> &DeviceId {
+        match self {
+            Self::Changed { device_id, .. }
+            | Self::Low { device_id, .. }
+            | Self::Critical { device_id, .. }
+            | Self::ChargingComplete { device_id }
+            | Self::Lost { device_id } => device_id,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BatteryIndicator
+// ---------------------------------------------------------------------------
+
+/// Shows a battery level as a color on some LEDs.
+///
+/// The color fades from `empty` at 0 % to `full` at 100 %.  By default every
+/// monitored device shows its own level on the LEDs of `zone`; use
+/// [`source`](Self::source) and [`target`](Self::target) to show one
+/// device's level on another device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatteryIndicator {
+    zone: Zone,
+    source: Option<DeviceId>,
+    target: Option<DeviceId>,
+    empty: Rgba,
+    full: Rgba,
+}
+
+impl BatteryIndicator {
+    /// Show the level on the LEDs of `zone`, from red to green.
+    pub fn new(zone: Zone) -> Self {
+        Self {
+            zone,
+            source: None,
+            target: None,
+            empty: Rgba::rgb(255, 0, 0),
+            full: Rgba::rgb(0, 255, 0),
+        }
+    }
+
+    /// Only show the level of this device.
+    pub fn source(mut self, device_id: DeviceId) -> Self {
+        self.source = Some(device_id);
+        self
+    }
+
+    /// Show the level on this device instead of the battery device.
+    pub fn target(mut self, device_id: DeviceId) -> Self {
+        self.target = Some(device_id);
+        self
+    }
+
+    /// Use these colors for 0 % and 100 %.
+    pub fn colors(mut self, empty: Rgba, full: Rgba) -> Self {
+        self.empty = empty;
+        self.full = full;
+        self
+    }
+
+    /// The color shown for `level`.
+    pub fn color(&self, level: i32) -> Rgba {
+        let t = level.clamp(0, 100) as f32 / 100.0;
+        let mix = |a: u8, b: u8| (f32::from(a) + (f32::from(b) - f32::from(a)) * t).round() as u8;
+        Rgba::new(
+            mix(self.empty.r, self.full.r),
+            mix(self.empty.g, self.full.g),
+            mix(self.empty.b, self.full.b),
+            mix(self.empty.a, self.full.a),
+        )
+    }
+
+    fn show(&self, session: &Session, device_id: &DeviceId, level: i32) -> Result<()> {
+        if self.source.is_some_and(|source| source != *device_id) {
+            return Ok(());
+        }
+        let target = self.target.as_ref().unwrap_or(device_id);
+        let positions = session.get_led_positions(target)?;
+        let color = self.color(level);
+        let colors: Vec<LedColor> = self
+            .zone
+            .resolve(&positions)
+            .into_iter()
+            .map(|luid| LedColor::with_color(luid, color))
+            .collect();
+        if colors.is_empty() {
+            return Ok(());
+        }
+        session.set_led_colors(target, &colors)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BatteryMonitor
+// ---------------------------------------------------------------------------
+
+/// What the monitor remembers about one device.
+#[derive(Debug, Clone, Copy)]
+struct Battery {
+    level: i32,
+    low: bool,
+    critical: bool,
+}
+
+/// Polls battery levels and reports changes.
+#[derive(Debug, Default)]
+pub struct BatteryMonitor {
+    thresholds: BatteryThresholds,
+    batteries: HashMap<DeviceId, Battery>,
+    /// Devices without a readable battery level property.
+    unsupported: HashSet<DeviceId>,
+    indicators: Vec<BatteryIndicator>,
+    listeners: Vec<mpsc::Sender<BatteryEvent>>,
+}
+
+impl BatteryMonitor {
+    /// Create a monitor with the default thresholds and no indicators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use these thresholds.
+    pub fn with_thresholds(mut self, thresholds: BatteryThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Show levels with this indicator.
+    pub fn with_indicator(mut self, indicator: BatteryIndicator) -> Self {
+        self.indicators.push(indicator);
+        self
+    }
+
+    /// Receive every future [`BatteryEvent`] on a channel.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<BatteryEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.listeners.push(tx);
+        rx
+    }
+
+    /// The last level read from a device.
+    pub fn level(&self, device_id: &DeviceId) -> Option<i32> {
+        self.batteries.get(device_id).map(|b| b.level)
+    }
+
+    /// Iterate over the last level of every monitored device.
+    pub fn levels(&self) -> impl Iterator<Item = (&DeviceId, i32)> + '_ {
+        self.batteries.iter().map(|(id, b)| (id, b.level))
+    }
+
+    /// Read the battery level of every connected device that has one.
+    ///
+    /// Indicators are updated for devices whose level changed.  A device
+    /// whose level cannot be read this time, e.g. because it is asleep,
+    /// keeps its last level and threshold state and is read again on the
+    /// next poll; only devices that disconnect are reported as
+    /// [`BatteryEvent::Lost`].
+    pub fn poll(&mut self, session: &Session) -> Result<Vec<BatteryEvent>> {
+        let devices = session.get_devices(DeviceType::ALL)?;
+        let present: Vec<DeviceId> = devices.iter().map(|info| info.id).collect();
+        self.unsupported.retain(|id| present.contains(id));
+
+        let mut events = Vec::new();
+        for info in &devices {
+            if self.unsupported.contains(&info.id) {
+                continue;
+            }
+            if !self.batteries.contains_key(&info.id) {
+                let info_result =
+                    session.get_device_property_info(&info.id, PropertyId::BatteryLevel, 0);
+                match readable(info_result) {
+                    Some(true) => {}
+                    Some(false) => {
+                        self.unsupported.insert(info.id);
+                        continue;
+                    }
+                    // Try again on the next poll.
+                    None => continue,
+                }
+            }
+            let reading = session.read_device_property(&info.id, PropertyId::BatteryLevel, 0);
+            let changed = self.apply_reading(&info.id, reading);
+            if let Some(&BatteryEvent::Changed { level, .. }) = changed.first() {
+                self.show(session, &info.id, level);
+            }
+            events.extend(changed);
+        }
+
+        let gone: Vec<DeviceId> = self
+            .batteries
+            .keys()
+            .filter(|id| !present.contains(id))
+            .copied()
+            .collect();
+        for id in gone {
+            events.extend(self.lose(&id));
+        }
+        Ok(events)
+    }
+
+    /// Call [`poll`](Self::poll) every `interval`, passing each event to
+    /// `on_event`, until it returns [`ControlFlow::Break`] or an SDK call
+    /// fails.
+    pub fn run(
+        &mut self,
+        session: &Session,
+        interval: Duration,
+        mut on_event: impl FnMut(&BatteryEvent) -> ControlFlow<()>,
+    ) -> Result<()> {
+        loop {
+            for event in self.poll(session)? {
+                if on_event(&event).is_break() {
+                    return Ok(());
+                }
+            }
+            std::thread::sleep(interval);
+        }
+    }
+
+    /// Record a level read by other means and return the resulting events.
+    pub fn record(&mut self, device_id: &DeviceId, level: i32) -> Vec<BatteryEvent> {
+        let thresholds = self.thresholds;
+        let previous = self.batteries.get(device_id).copied();
+        if previous.is_some_and(|b| b.level == level) {
+            return Vec::new();
+        }
+        let mut battery = previous.unwrap_or(Battery {
+            level,
+            low: false,
+            critical: false,
+        });
+        battery.level = level;
+
+        let mut events = vec![BatteryEvent::Changed {
+            device_id: *device_id,
+            previous: previous.map(|b| b.level),
+            level,
+        }];
+        if level <= thresholds.critical {
+            if !battery.critical {
+                events.push(BatteryEvent::Critical {
+                    device_id: *device_id,
+                    level,
+                });
+            }
+            battery.critical = true;
+            battery.low = true;
+        } else if level <= thresholds.low {
+            if !battery.low {
+                events.push(BatteryEvent::Low {
+                    device_id: *device_id,
+                    level,
+                });
+            }
+            battery.critical = false;
+            battery.low = true;
+        } else {
+            battery.critical = false;
+            battery.low = false;
+        }
+        if previous.is_some_and(|b| b.level < thresholds.full) && level >= thresholds.full {
+            events.push(BatteryEvent::ChargingComplete {
+                device_id: *device_id,
+            });
+        }
+
+        self.batteries.insert(*device_id, battery);
+        trace::event!(debug, device = %device_id, level, "battery level changed");
+        self.emit(events)
+    }
+
+    /// Record the result of reading the level of a device.
+    ///
+    /// A failed read changes nothing, so a device that misses a poll does not
+    /// report its thresholds again when it comes back.
+    fn apply_reading(
+        &mut self,
+        device_id: &DeviceId,
+        reading: Result<PropertyValue>,
+    ) -> Vec<BatteryEvent> {
+        match reading {
+            Ok(PropertyValue::Int32(level)) => self.record(device_id, level),
+            Ok(_value) => {
+                trace::event!(debug, device = %device_id, value = ?_value, "unexpected battery level type");
+                Vec::new()
+            }
+            Err(_e) => {
+                trace::event!(debug, device = %device_id, error = %_e, "failed to read battery level");
+                Vec::new()
+            }
+        }
+    }
+
+    fn lose(&mut self, device_id: &DeviceId) -> Option<BatteryEvent> {
+        self.batteries.remove(device_id)?;
+        let mut events = self.emit(vec![BatteryEvent::Lost {
+            device_id: *device_id,
+        }]);
+        events.pop()
+    }
+
+    fn show(&self, session: &Session, device_id: &DeviceId, level: i32) {
+        for indicator in &self.indicators {
+            if let Err(_e) = indicator.show(session, device_id, level) {
+                trace::event!(debug, device = %device_id, error = %_e, "failed to show battery level");
+            }
+        }
+    }
+
+    fn emit(&mut self, events: Vec<BatteryEvent>) -> Vec<BatteryEvent> {
+        self.listeners
+            .retain(|tx| events.iter().all(|event| tx.send(event.clone()).is_ok()));
+        events
+    }
+}
+
+/// Whether the battery level property described by `info` can be read, or
+/// `None` if the query failed for a reason that may pass, such as iCUE not
+/// responding.
+///
+/// Any other error means the device has no such property, which is how the
+/// SDK answers for wired devices.
+fn readable(info: Result<PropertyInfo>) -> Option<bool> {
+    match info {
+        Ok(info) => Some(info.flags.contains(PropertyFlags::CAN_READ)),
+        Err(e) if matches!(e.kind(), SdkError::NotConnected | SdkError::Timeout) => None,
+        Err(_) => Some(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::{ErrorContext, Operation};
+    use crate::property::DataType;
+
+    fn error(kind: SdkError) -> crate::Error {
+        ErrorContext::new(Operation::ReadDeviceProperty)
+            .property(PropertyId::BatteryLevel)
+            .error(kind)
+    }
+
+    fn timeout() -> crate::Error {
+        error(SdkError::Timeout)
+    }
+
+    #[test]
+    fn failed_reads_keep_the_level_and_thresholds() {
+        let device_id: DeviceId = "{headset}".parse().unwrap();
+        let mut monitor = BatteryMonitor::new();
+        let low = monitor.apply_reading(&device_id, Ok(PropertyValue::Int32(15)));
+        assert_eq!(low.len(), 2);
+
+        assert!(monitor.apply_reading(&device_id, Err(timeout())).is_empty());
+        assert!(monitor
+            .apply_reading(&device_id, Ok(PropertyValue::Boolean(true)))
+            .is_empty());
+        assert_eq!(monitor.level(&device_id), Some(15));
+
+        // Still low, so only the change is reported.
+        assert_eq!(
+            monitor.apply_reading(&device_id, Ok(PropertyValue::Int32(14))),
+            [BatteryEvent::Changed {
+                device_id,
+                previous: Some(15),
+                level: 14
+            }]
+        );
+    }
+
+    #[test]
+    fn only_transient_errors_leave_support_undecided() {
+        let info = |flags| {
+            Ok(PropertyInfo {
+                data_type: DataType::Int32,
+                flags,
+            })
+        };
+        assert_eq!(readable(info(PropertyFlags::CAN_READ)), Some(true));
+        assert_eq!(readable(info(PropertyFlags::empty())), Some(false));
+        assert_eq!(readable(Err(timeout())), None);
+        assert_eq!(readable(Err(error(SdkError::NotConnected))), None);
+        assert_eq!(readable(Err(error(SdkError::NotAllowed))), Some(false));
+        assert_eq!(
+            readable(Err(error(SdkError::InvalidArguments))),
+            Some(false)
+        );
+    }
+}
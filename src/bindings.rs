@@ -0,0 +1,545 @@
+//! Binding macro key gestures to actions from a config file.
+//!
+//! [`Bindings`] is a list of rules read from a line-based config.  Each rule
+//! names the devices it applies to, a [`Gesture`] and an [`Action`]:
+//!
+//! ```text
+//! # selector  gesture        = action
+//! keyboard    tap(G1)        = exec notify-send "G1 pressed"
+//! keyboard    long_press(G2) = profile gaming
+//! headset     tap(G1)        = toggle mic_enabled
+//! *           chord(G1+G2)   = emit panic
+//! keyboard    sequence(G1,G1,G3) = emit konami
+//! ```
+//!
+//! The selector is a [`Selector`] without spaces (`keyboard`, `mouse:*`,
+//! `*`).  Gestures are `tap`, `double_tap`, `long_press` and `hold_repeat`
+//! of one key, `chord` of keys joined with `+`, and `sequence` of keys
+//! separated by `,`.  Keys are written `G1` to `G20`.  Actions are
+//!
+//! - `exec <command>`: run a shell command without waiting for it;
+//! - `profile <name>`: restore a [`LightingSnapshot`] registered with
+//!   [`Dispatcher::add_profile`];
+//! - `toggle <property>`: flip a boolean property of the device the gesture
+//!   was made on, named as by [`PropertyId::name`];
+//! - `emit <name>`: call the callbacks registered with
+//!   [`Dispatcher::on_emit`].
+//!
+//! A [`Dispatcher`] runs the actions.  It intercepts every bound key so
+//! iCUE does not run its own action for it, and can swap in new bindings at
+//! runtime with [`reload`](Dispatcher::reload).
+//!
+//! ```no_run
+//! # use cue_sdk::bindings::{Bindings, Dispatcher};
+//! # let session = cue_sdk::connect().unwrap();
+//! let bindings = Bindings::parse("keyboard tap(G1) = emit hello").unwrap();
+//! let mut dispatcher = Dispatcher::new(&session, bindings).unwrap();
+//! dispatcher.on_emit(|name, device_id| println!("{name} from {device_id}"));
+//!
+//! let events = session.subscribe_for_events().unwrap();
+//! while let Some(event) = events.recv() {
+//!     dispatcher.handle_event(&event).unwrap();
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::device::{DeviceId, DeviceInfo, DeviceType};
+use crate::error::{Error, ErrorContext, Operation, SdkError};
+use crate::event::{Event, MacroKeyId};
+use crate::gesture::{Gesture, GestureConfig, GestureEvent, GestureRecognizer};
+use crate::guard::KeyInterceptGuard;
+use crate::property::{PropertyId, PropertyValue};
+use crate::session::Session;
+use crate::snapshot::LightingSnapshot;
+use crate::trace;
+use crate::zone::{Selector, ZoneError};
+
+// ---------------------------------------------------------------------------
+// BindingError
+// ---------------------------------------------------------------------------
+
+/// Errors produced while loading bindings or running their actions.
+#[derive(Debug, thiserror::Error)]
+pub enum BindingError {
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+    #[error("line {line}: {source}")]
+    Selector { line: usize, source: ZoneError },
+    #[error("failed to read bindings: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unknown profile `{0}`")]
+    UnknownProfile(String),
+    #[error("failed to run `{command}`: {source}")]
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Sdk(#[from] Error),
+}
+
+// ---------------------------------------------------------------------------
+// Action
+// ---------------------------------------------------------------------------
+
+/// What to do when a bound gesture is made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Run a shell command.
+    Exec(String),
+    /// Restore a named lighting profile.
+    Profile(String),
+    /// Flip a boolean device property.
+    Toggle(PropertyId),
+    /// Call the registered callbacks with this name.
+    Emit(String),
+}
+
+impl Action {
+    fn parse(s: &str) -> Result<Self, String> {
+        let (verb, arg) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let arg = arg.trim();
+        if arg.is_empty() {
+            return Err(format!("missing argument for `{verb}`"));
+        }
+        match verb {
+            "exec" => Ok(Self::Exec(arg.to_owned())),
+            "profile" => Ok(Self::Profile(arg.to_owned())),
+            "toggle" => PropertyId::from_name(arg)
+                .map(Self::Toggle)
+                .ok_or_else(|| format!("unknown property `{arg}`")),
+            "emit" => Ok(Self::Emit(arg.to_owned())),
+            _ => Err(format!("unknown action `{verb}`")),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Binding
+// ---------------------------------------------------------------------------
+
+/// One rule: devices, gesture and action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    /// The devices the rule applies to.
+    pub selector: Selector,
+    /// The gesture that triggers the action.  The `count` of
+    /// [`Gesture::HoldRepeat`] is ignored when matching.
+    pub gesture: Gesture,
+    /// What to do.
+    pub action: Action,
+}
+
+impl Binding {
+    /// Whether a gesture made on `device` triggers this binding.
+    pub fn matches(&self, device: &DeviceInfo, gesture: &Gesture) -> bool {
+        let same = match (&self.gesture, gesture) {
+            (Gesture::HoldRepeat { key: a, .. }, Gesture::HoldRepeat { key: b, .. }) => a == b,
+            (a, b) => a == b,
+        };
+        same && self.selector.matches(device)
+    }
+
+    /// The keys used by the gesture.
+    pub fn keys(&self) -> &[MacroKeyId] {
+        match &self.gesture {
+            Gesture::Tap(key)
+            | Gesture::DoubleTap(key)
+            | Gesture::LongPress(key)
+            | Gesture::HoldRepeat { key, .. } => std::slice::from_ref(key),
+            Gesture::Chord(keys) | Gesture::Sequence(keys) => keys,
+        }
+    }
+}
+
+fn parse_gesture(s: &str) -> Result<Gesture, String> {
+    let (kind, keys) = s
+        .strip_suffix(')')
+        .and_then(|s| s.split_once('('))
+        .ok_or_else(|| format!("expected `gesture(keys)`, got `{s}`"))?;
+    let separator = match kind.trim() {
+        "chord" => '+',
+        "sequence" => ',',
+        _ => ' ',
+    };
+    let keys = keys
+        .split(separator)
+        .map(|key| parse_key(key.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match (kind.trim(), keys.as_slice()) {
+        ("tap", &[key]) => Ok(Gesture::Tap(key)),
+        ("double_tap", &[key]) => Ok(Gesture::DoubleTap(key)),
+        ("long_press", &[key]) => Ok(Gesture::LongPress(key)),
+        ("hold_repeat", &[key]) => Ok(Gesture::HoldRepeat { key, count: 0 }),
+        ("chord", _) if keys.len() >= 2 => Ok(Gesture::Chord(keys)),
+        ("sequence", _) if keys.len() >= 2 => Ok(Gesture::Sequence(keys)),
+        ("tap" | "double_tap" | "long_press" | "hold_repeat", _) => {
+            Err(format!("`{kind}` takes exactly one key"))
+        }
+        ("chord" | "sequence", _) => Err(format!("`{kind}` needs at least two keys")),
+        _ => Err(format!("unknown gesture `{kind}`")),
+    }
+}
+
+fn parse_key(s: &str) -> Result<MacroKeyId, String> {
+    s.strip_prefix('G')
+        .and_then(|n| n.parse::<usize>().ok())
+        .and_then(|n| MacroKeyId::ALL.get(n.checked_sub(1)?).copied())
+        .ok_or_else(|| format!("unknown macro key `{s}`"))
+}
+
+// ---------------------------------------------------------------------------
+// Bindings
+// ---------------------------------------------------------------------------
+
+/// A list of bindings, in config order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bindings {
+    bindings: Vec<Binding>,
+}
+
+impl Bindings {
+    /// Parse bindings from a config string (see the module docs).
+    ///
+    /// Text after `#` is a comment, except inside `exec` commands.
+    pub fn parse(config: &str) -> Result<Self, BindingError> {
+        let mut bindings = Vec::new();
+        for (index, line) in config.lines().enumerate() {
+            let line_no = index + 1;
+            let syntax = |message: String| BindingError::Syntax {
+                line: line_no,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (trigger, action) = line
+                .split_once('=')
+                .ok_or_else(|| syntax("expected `selector gesture = action`".into()))?;
+            let action = action.trim();
+            let action = if action.starts_with("exec") {
+                action
+            } else {
+                action.split('#').next().unwrap_or("").trim()
+            };
+            let (selector, gesture) = trigger
+                .trim()
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| syntax("expected `selector gesture`".into()))?;
+            let selector = selector.parse().map_err(|source| BindingError::Selector {
+                line: line_no,
+                source,
+            })?;
+            bindings.push(Binding {
+                selector,
+                gesture: parse_gesture(gesture.trim()).map_err(syntax)?,
+                action: Action::parse(action).map_err(syntax)?,
+            });
+        }
+        Ok(Self { bindings })
+    }
+
+    /// Read and parse a bindings file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BindingError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Add a binding after the existing ones.
+    pub fn push(&mut self, binding: Binding) {
+        self.bindings.push(binding);
+    }
+
+    /// Iterate over the bindings in config order.
+    pub fn iter(&self) -> impl Iterator<Item = &Binding> + '_ {
+        self.bindings.iter()
+    }
+
+    /// Number of bindings.
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Whether there are no bindings.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// The actions triggered by a gesture made on `device`, in config order.
+    pub fn actions<'a>(
+        &'a self,
+        device: &'a DeviceInfo,
+        gesture: &'a Gesture,
+    ) -> impl Iterator<Item = &'a Action> + 'a {
+        self.iter()
+            .filter(move |b| b.matches(device, gesture))
+            .map(|b| &b.action)
+    }
+
+    /// Every key bound on `device`, without duplicates.
+    pub fn keys(&self, device: &DeviceInfo) -> Vec<MacroKeyId> {
+        let mut keys: Vec<MacroKeyId> = Vec::new();
+        for binding in self.iter().filter(|b| b.selector.matches(device)) {
+            for key in binding.keys() {
+                if !keys.contains(key) {
+                    keys.push(*key);
+                }
+            }
+        }
+        keys
+    }
+
+    /// `base` extended with the chords and sequences bound on `device`.
+    pub fn gesture_config(&self, device: &DeviceInfo, base: &GestureConfig) -> GestureConfig {
+        self.iter()
+            .filter(|b| b.selector.matches(device))
+            .fold(base.clone(), |config, binding| match &binding.gesture {
+                Gesture::Chord(keys) => config.chord(keys),
+                Gesture::Sequence(keys) => config.sequence(keys),
+                _ => config,
+            })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Dispatcher
+// ---------------------------------------------------------------------------
+
+type EmitCallback<'s> = Box<dyn FnMut(&str, &DeviceId) + 's>;
+
+/// Runs the actions of a set of [`Bindings`] in response to SDK events.
+pub struct Dispatcher<'s> {
+    session: &'s Session,
+    bindings: Bindings,
+    base_config: GestureConfig,
+    gestures: GestureRecognizer,
+    devices: HashMap<DeviceId, DeviceInfo>,
+    intercepts: Vec<KeyInterceptGuard<'s>>,
+    profiles: HashMap<String, LightingSnapshot>,
+    callbacks: Vec<EmitCallback<'s>>,
+}
+
+impl<'s> Dispatcher<'s> {
+    /// Create a dispatcher and intercept every bound key on the connected
+    /// devices.
+    ///
+    /// Hold-repeat gestures fire 500 ms after a key is pressed and then every
+    /// 100 ms; use [`set_gesture_config`](Self::set_gesture_config) to change
+    /// this or other thresholds.
+    pub fn new(session: &'s Session, bindings: Bindings) -> Result<Self, BindingError> {
+        let mut dispatcher = Self {
+            session,
+            bindings: Bindings::default(),
+            base_config: GestureConfig::default()
+                .hold_repeat(Duration::from_millis(500), Duration::from_millis(100)),
+            gestures: GestureRecognizer::new(),
+            devices: HashMap::new(),
+            intercepts: Vec::new(),
+            profiles: HashMap::new(),
+            callbacks: Vec::new(),
+        };
+        for info in session.get_devices(DeviceType::ALL)? {
+            dispatcher.devices.insert(info.id, info);
+        }
+        dispatcher.reload(bindings)?;
+        Ok(dispatcher)
+    }
+
+    /// Replace the bindings.
+    ///
+    /// Keys bound by the new bindings are intercepted before keys that are
+    /// no longer bound are released.  On error the previous bindings stay in
+    /// effect.
+    pub fn reload(&mut self, bindings: Bindings) -> Result<(), BindingError> {
+        let mut intercepts = Vec::new();
+        for info in self.devices.values() {
+            for key in bindings.keys(info) {
+                intercepts.push(self.session.intercept_key(&info.id, key)?);
+            }
+        }
+        trace::event!(
+            info,
+            bindings = bindings.len(),
+            keys = intercepts.len(),
+            "bindings loaded"
+        );
+        self.intercepts = intercepts;
+        self.bindings = bindings;
+        self.configure_gestures();
+        Ok(())
+    }
+
+    /// The bindings in effect.
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// Change the gesture thresholds.  Chords and sequences are added from
+    /// the bindings.
+    pub fn set_gesture_config(&mut self, config: GestureConfig) {
+        self.base_config = config;
+        self.configure_gestures();
+    }
+
+    /// Register a lighting profile for `profile` actions.
+    pub fn add_profile(&mut self, name: impl Into<String>, profile: LightingSnapshot) {
+        self.profiles.insert(name.into(), profile);
+    }
+
+    /// Call `callback` with the name and device of every `emit` action.
+    pub fn on_emit(&mut self, callback: impl FnMut(&str, &DeviceId) + 's) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Feed an SDK event to the dispatcher and run the actions it triggers.
+    ///
+    /// Newly connected devices get their bound keys intercepted.  Every
+    /// triggered action is run even if an earlier one fails; the first error
+    /// is returned.
+    pub fn handle_event(&mut self, event: &Event) -> Result<(), BindingError> {
+        let mut result = Ok(());
+        if let Event::DeviceConnectionChanged {
+            device_id,
+            is_connected,
+        } = event
+        {
+            result = self.device_changed(device_id, *is_connected);
+        }
+        let gestures = self.gestures.handle_event(event);
+        let ran = self.run(gestures);
+        result.and(ran)
+    }
+
+    /// Run the actions of gestures that became due because time passed,
+    /// such as long presses.  Call this regularly, or at
+    /// [`next_deadline`](Self::next_deadline).
+    pub fn poll(&mut self) -> Result<(), BindingError> {
+        let gestures = self.gestures.poll();
+        self.run(gestures)
+    }
+
+    /// When [`poll`](Self::poll) next has work to do.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.gestures.next_deadline()
+    }
+
+    fn device_changed(
+        &mut self,
+        device_id: &DeviceId,
+        connected: bool,
+    ) -> Result<(), BindingError> {
+        if !connected {
+            // iCUE forgets interception when the device goes away.
+            self.intercepts.retain(|g| g.device_id() != device_id);
+            self.devices.remove(device_id);
+            return Ok(());
+        }
+        let info = self.session.get_device_info(device_id)?;
+        for key in self.bindings.keys(&info) {
+            self.intercepts
+                .push(self.session.intercept_key(device_id, key)?);
+        }
+        self.gestures.set_config(
+            device_id,
+            self.bindings.gesture_config(&info, &self.base_config),
+        );
+        self.devices.insert(info.id, info);
+        Ok(())
+    }
+
+    fn configure_gestures(&mut self) {
+        self.gestures.set_default_config(self.base_config.clone());
+        for info in self.devices.values() {
+            let config = self.bindings.gesture_config(info, &self.base_config);
+            self.gestures.set_config(&info.id, config);
+        }
+    }
+
+    fn run(&mut self, gestures: Vec<GestureEvent>) -> Result<(), BindingError> {
+        let mut result = Ok(());
+        for GestureEvent { device_id, gesture } in gestures {
+            let Some(info) = self.devices.get(&device_id) else {
+                continue;
+            };
+            let actions: Vec<Action> = self.bindings.actions(info, &gesture).cloned().collect();
+            for action in actions {
+                trace::event!(debug, device = %device_id, ?gesture, ?action, "running binding");
+                let ran = self.run_action(&device_id, &action);
+                if result.is_ok() {
+                    result = ran;
+                }
+            }
+        }
+        result
+    }
+
+    fn run_action(&mut self, device_id: &DeviceId, action: &Action) -> Result<(), BindingError> {
+        match action {
+            Action::Exec(command) => spawn_shell(command),
+            Action::Profile(name) => {
+                let profile = self
+                    .profiles
+                    .get(name)
+                    .ok_or_else(|| BindingError::UnknownProfile(name.clone()))?;
+                Ok(profile.restore(self.session)?)
+            }
+            Action::Toggle(property) => {
+                match self.session.read_device_property(device_id, *property, 0)? {
+                    PropertyValue::Boolean(value) => Ok(self
+                        .session
+                        .write_device_property_bool(device_id, *property, 0, !value)?),
+                    _ => Err(ErrorContext::new(Operation::WriteDeviceProperty)
+                        .device(device_id)
+                        .property(*property)
+                        .error(SdkError::InvalidOperation)
+                        .into()),
+                }
+            }
+            Action::Emit(name) => {
+                for callback in &mut self.callbacks {
+                    callback(name, device_id);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Dispatcher<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("bindings", &self.bindings)
+            .field("devices", &self.devices.len())
+            .field("intercepts", &self.intercepts.len())
+            .field("profiles", &self.profiles.keys().collect::<Vec<_>>())
+            .field("callbacks", &self.callbacks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Start `command` in the platform shell without waiting for it.
+fn spawn_shell(command: &str) -> Result<(), BindingError> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .spawn()
+        .map_err(|source| BindingError::Spawn {
+            command: command.to_owned(),
+            source,
+        })?;
+    // Reap the child so it does not linger as a zombie.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Connecting with options.
+//!
+//! [`SessionBuilder`] collects the settings an application would otherwise
+//! apply by hand after [`connect()`](crate::connect), then connects, waits
+//! for the connection and applies them in one call.  The session is only
+//! returned once every step has succeeded; if one fails, the session is
+//! dropped, which disconnects and so releases anything already set up.
+//!
+//! The steps run in this order, and a failure reports the
+//! [`Operation`](crate::Operation) of the step that failed:
+//!
+//! 1. `connect` and `wait_for_connection`, repeated according to the
+//!    [`reconnect`](SessionBuilder::reconnect) policy.
+//! 2. The server version check against
+//!    [`min_server_version`](SessionBuilder::min_server_version).
+//! 3. `set_layer_priority`.
+//! 4. `get_devices` and `request_control` for each
+//!    [`request_control`](SessionBuilder::request_control) filter.
+//! 5. `subscribe_for_events`.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use cue_sdk::{AccessLevel, DeviceType, Session};
+//! # use cue_sdk::retry::RetryPolicy;
+//! let session = Session::builder()
+//!     .timeout(Duration::from_secs(2))
+//!     .reconnect(RetryPolicy::default().max_attempts(5))
+//!     .layer_priority(200)
+//!     .request_control(DeviceType::KEYBOARD, AccessLevel::ExclusiveLightingControl)
+//!     .subscribe_for_events(true)
+//!     .connect()?;
+//! let events = session.take_event_subscription().unwrap();
+//! # Ok::<(), cue_sdk::Error>(())
+//! ```
+
+use std::time::Duration;
+
+use crate::device::DeviceType;
+use crate::error::Result;
+use crate::retry::RetryPolicy;
+use crate::session::{AccessLevel, Session, SessionDetails, Version};
+use crate::trace;
+
+/// Options for connecting a [`Session`].  Created by [`Session::builder`].
+#[derive(Debug, Clone)]
+pub struct SessionBuilder {
+    timeout: Duration,
+    min_server_version: Version,
+    reconnect: RetryPolicy,
+    retry_policy: Option<RetryPolicy>,
+    layer_priority: Option<u32>,
+    control: Vec<(DeviceType, AccessLevel)>,
+    subscribe: bool,
+}
+
+impl Default for SessionBuilder {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            min_server_version: SessionDetails::MIN_SERVER_VERSION,
+            reconnect: RetryPolicy::none(),
+            retry_policy: None,
+            layer_priority: None,
+            control: Vec::new(),
+            subscribe: false,
+        }
+    }
+}
+
+impl SessionBuilder {
+    /// A builder with the default options: wait up to 5 seconds for one
+    /// connection attempt and apply no settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long each connection attempt waits for the `Connected` state.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Fail with `IncompatibleProtocol` unless the server is at least
+    /// `version`.
+    ///
+    /// Versions below [`SessionDetails::MIN_SERVER_VERSION`] have no effect,
+    /// as that minimum is always checked.
+    pub fn min_server_version(mut self, version: Version) -> Self {
+        self.min_server_version = version;
+        self
+    }
+
+    /// Repeat the connection attempt according to `policy`, e.g. while iCUE
+    /// is still starting.
+    ///
+    /// A timed-out or refused attempt fails with `NotConnected`, which the
+    /// default [`RetryPolicy`] retries.  By default only one attempt is made.
+    pub fn reconnect(mut self, policy: RetryPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    /// Install `policy` with [`Session::set_retry_policy`] before the
+    /// post-connect steps, so they are retried as well.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Set the layer priority (0–255) once connected.
+    pub fn layer_priority(mut self, priority: u32) -> Self {
+        self.layer_priority = Some(priority);
+        self
+    }
+
+    /// Request `level` for every connected device matching `filter`.
+    ///
+    /// May be called more than once; requests are made in order.
+    pub fn request_control(mut self, filter: DeviceType, level: AccessLevel) -> Self {
+        self.control.push((filter, level));
+        self
+    }
+
+    /// Subscribe to events once connected.  Take the subscription with
+    /// [`Session::take_event_subscription`].
+    pub fn subscribe_for_events(mut self, subscribe: bool) -> Self {
+        self.subscribe = subscribe;
+        self
+    }
+
+    /// Connect and apply every option.
+    ///
+    /// Returns the error of the first step that failed; see the
+    /// [module documentation](self) for the steps.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, err(level = "warn"))
+    )]
+    pub fn connect(self) -> Result<Session> {
+        let mut attempts = 0;
+        let (session, details) = self
+            .reconnect
+            .run(|| {
+                attempts += 1;
+                let session = Session::connect()?;
+                let details = session.wait_for_connection(self.timeout)?;
+                Ok((session, details))
+            })
+            .map_err(|e| e.with_detail(format!("gave up after {attempts} attempt(s)")))?;
+        details.check_server(self.min_server_version)?;
+        trace::event!(info, server = %details.server_version, attempts, "session connected");
+
+        if let Some(policy) = self.retry_policy {
+            session.set_retry_policy(Some(policy));
+        }
+        if let Some(priority) = self.layer_priority {
+            session.set_layer_priority(priority)?;
+        }
+        for (filter, level) in self.control {
+            let step = || format!("requesting {level:?} for {filter:?} devices");
+            for info in session
+                .get_devices(filter)
+                .map_err(|e| e.with_detail(step()))?
+            {
+                session
+                    .request_control(&info.id, level)
+                    .map_err(|e| e.with_detail(step()))?;
+            }
+        }
+        if self.subscribe {
+            session.set_event_subscription(session.subscribe_for_events()?);
+        }
+        Ok(session)
+    }
+}
@@ -11,15 +11,25 @@
 //! **Session state** is the exception: its sender lives in a process-wide static
 //! so the SDK's background thread can never dereference a freed pointer (see
 //! issue #18).
+//!
+//! **Flush results** are one-shot: the SDK gets one reference to an `Arc`ed
+//! sender through the `context` pointer, and a process-wide list of pending
+//! flushes holds the other.  The trampoline takes both back when it fires.
+//! The waiting side only holds the receiver, so it can time out or be
+//! cancelled while a late callback still finds a live sender, and a callback
+//! that never fires is cleaned up when the `Session` disconnects.
 
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
 
 use core::ffi::c_void;
 use cue_sdk_sys as ffi;
 
 use crate::event::Event;
+use crate::trace;
 
 // ---- Session state callback ------------------------------------------------
 
@@ -37,10 +47,22 @@ pub(crate) struct SessionStateChange {
 /// fires the callback during or after `CorsairDisconnect`.
 static SESSION_STATE_TX: Mutex<Option<mpsc::Sender<SessionStateChange>>> = Mutex::new(None);
 
+/// Most recent session state reported by the SDK.
+///
+/// Kept separately from the channel so errors can record the state at the
+/// time of failure without draining state changes meant for the session.
+static SESSION_STATE: AtomicU32 = AtomicU32::new(ffi::CorsairSessionState_CSS_Invalid);
+
+/// The most recent raw session state reported by the SDK.
+pub(crate) fn current_session_state() -> ffi::CorsairSessionState {
+    SESSION_STATE.load(Ordering::Relaxed)
+}
+
 /// Install a sender for session state changes into the process-wide static.
 pub(crate) fn install_session_sender(tx: mpsc::Sender<SessionStateChange>) {
-    // SAFETY (logical): Any previous sender is dropped here.  This is fine
-    // because it means the old receiver will see a disconnected channel.
+    // SAFETY (logical): Any previous sender is dropped here.  `Session`
+    // allows one session per process, so it belongs to a session that failed
+    // to connect and its receiver is gone.
     *SESSION_STATE_TX.lock().unwrap() = Some(tx);
 }
 
@@ -50,6 +72,7 @@ pub(crate) fn install_session_sender(tx: mpsc::Sender<SessionStateChange>) {
 /// thread cannot send into a half-dropped channel.
 pub(crate) fn clear_session_sender() {
     *SESSION_STATE_TX.lock().unwrap() = None;
+    SESSION_STATE.store(ffi::CorsairSessionState_CSS_Closed, Ordering::Relaxed);
 }
 
 /// Return a raw pointer suitable for the SDK `context` parameter.
@@ -73,6 +96,12 @@ pub(crate) unsafe extern "C" fn session_state_trampoline(
     // SAFETY: `event_data` is provided by the SDK and valid for the duration of
     // this callback invocation.
     let data = unsafe { &*event_data };
+    SESSION_STATE.store(data.state, Ordering::Relaxed);
+    trace::event!(
+        info,
+        state = ?crate::session::SessionState::from_ffi(data.state),
+        "session state changed"
+    );
     if let Ok(guard) = SESSION_STATE_TX.lock() {
         if let Some(tx) = guard.as_ref() {
             let _ = tx.send(SessionStateChange {
@@ -111,34 +140,151 @@ pub(crate) unsafe extern "C" fn event_trampoline(
     // SAFETY: `event` is provided by the SDK and valid for the duration of this
     // callback invocation.
     let ev = unsafe { &*event };
-    if let Some(parsed) = Event::from_ffi(ev) {
-        let _ = tx.send(parsed);
+    let parsed = match Event::from_ffi(ev) {
+        Ok(parsed) => parsed,
+        Err(_e) => {
+            trace::event!(warn, id = ev.id, error = %_e, "dropped malformed SDK event");
+            return;
+        }
+    };
+    trace::event!(trace, event = ?parsed, "SDK event");
+    if tx.send(parsed).is_err() {
+        trace::event!(warn, "dropped SDK event: subscription receiver is gone");
     }
 }
 
 // ---- Async flush callback --------------------------------------------------
 
-/// Pinned sender for a one-shot flush result.
-pub(crate) type FlushSender = Pin<Box<mpsc::Sender<ffi::CorsairError>>>;
+/// Data sent through a flush channel: the SDK's result code and when the
+/// callback fired.
+pub(crate) type FlushCompletion = (ffi::CorsairError, Instant);
 
-/// Create a (sender, receiver) pair for an async flush result.
-pub(crate) fn flush_channel() -> (FlushSender, mpsc::Receiver<ffi::CorsairError>) {
+/// Where a flush callback delivers its result.
+pub(crate) enum FlushSender {
+    Blocking(mpsc::Sender<FlushCompletion>),
+    #[cfg(feature = "async")]
+    Async(tokio::sync::mpsc::UnboundedSender<ffi::CorsairError>),
+}
+
+impl FlushSender {
+    /// Send the result, returning `false` if the receiver is gone.
+    fn send(&self, error: ffi::CorsairError) -> bool {
+        match self {
+            Self::Blocking(tx) => tx.send((error, Instant::now())).is_ok(),
+            #[cfg(feature = "async")]
+            Self::Async(tx) => tx.send(error).is_ok(),
+        }
+    }
+}
+
+/// Flushes whose callback has not fired yet.
+///
+/// Each entry shares its sender with the SDK, which holds a second reference
+/// through the `context` pointer.  Whoever removes an entry (the trampoline,
+/// `cancel_flush` or `release_pending_flushes`) also takes back the SDK's
+/// reference, so every context is freed exactly once, and a callback whose
+/// context is no longer listed is ignored.
+static PENDING_FLUSHES: Mutex<Vec<Arc<FlushSender>>> = Mutex::new(Vec::new());
+
+/// Lock `PENDING_FLUSHES`, ignoring poisoning: the trampoline must not panic.
+fn pending_flushes() -> MutexGuard<'static, Vec<Arc<FlushSender>>> {
+    PENDING_FLUSHES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Register a pending flush and return its `context` pointer.
+///
+/// The context must be passed to the SDK together with `flush_trampoline`,
+/// or released with `cancel_flush` if the SDK call fails.
+pub(crate) fn flush_context(sender: FlushSender) -> *mut c_void {
+    let sender = Arc::new(sender);
+    pending_flushes().push(sender.clone());
+    Arc::into_raw(sender).cast_mut().cast()
+}
+
+/// Create a (context, receiver) pair for an async flush result.
+///
+/// Ownership rules are those of `flush_context`.
+pub(crate) fn flush_channel() -> (*mut c_void, mpsc::Receiver<FlushCompletion>) {
     let (tx, rx) = mpsc::channel();
-    (Box::pin(tx), rx)
+    (flush_context(FlushSender::Blocking(tx)), rx)
+}
+
+/// Take the SDK's reference back from a listed `Arc`.
+///
+/// # Safety
+///
+/// `listed` must just have been removed from `PENDING_FLUSHES`.
+unsafe fn reclaim_flush(listed: Arc<FlushSender>) -> Arc<FlushSender> {
+    // SAFETY: The SDK's reference was created by `Arc::into_raw` in
+    // `flush_context` and is only taken back by whoever removes the entry,
+    // which the caller just did under the lock.
+    unsafe { Arc::from_raw(Arc::as_ptr(&listed)) }
+}
+
+/// Remove `context` from the pending flushes and take back the SDK's
+/// reference to its sender, or return `None` if it is no longer listed.
+fn take_flush(context: *mut c_void) -> Option<Arc<FlushSender>> {
+    let mut pending = pending_flushes();
+    let index = pending
+        .iter()
+        .position(|sender| Arc::as_ptr(sender).cast::<c_void>() == context.cast_const())?;
+    let listed = pending.swap_remove(index);
+    // SAFETY: The entry was removed above, with the lock still held.
+    Some(unsafe { reclaim_flush(listed) })
+}
+
+/// Free the context of a flush the SDK rejected.
+pub(crate) fn cancel_flush(context: *mut c_void) {
+    drop(take_flush(context));
+}
+
+/// Free every pending flush context.
+///
+/// Their senders are dropped, so anyone still waiting sees a closed channel.
+/// Must be called **after** `CorsairDisconnect`, once the SDK no longer
+/// invokes flush callbacks; without it, each flush whose callback never
+/// fired would keep its sender until the process exits.
+pub(crate) fn release_pending_flushes() {
+    let pending = std::mem::take(&mut *pending_flushes());
+    if !pending.is_empty() {
+        trace::event!(
+            debug,
+            count = pending.len(),
+            "releasing flushes that never completed"
+        );
+    }
+    for listed in pending {
+        // SAFETY: The entry was removed from the list by `take` above.
+        drop(unsafe { reclaim_flush(listed) });
+    }
 }
 
 /// `extern "C"` trampoline for `CorsairAsyncCallback`.
 ///
 /// # Safety
 ///
-/// - `context` must be a valid pointer to a live `mpsc::Sender<CorsairError>`
-///   (guaranteed by the `Pin<Box<Sender>>` kept alive in `flush_led_colors`).
+/// - `context` must have been created by `flush_context`.  It is only
+///   compared against the pending flushes, never dereferenced, so a context
+///   that was already freed is ignored.
 pub(crate) unsafe extern "C" fn flush_trampoline(context: *mut c_void, error: ffi::CorsairError) {
-    // SAFETY: `context` was created by `sender_as_context` from a pinned boxed
-    // sender that is held on the stack in `Session::flush_led_colors` until the
-    // receiver gets the result.
-    let tx = unsafe { &*(context as *const mpsc::Sender<ffi::CorsairError>) };
-    let _ = tx.send(error);
+    trace::event!(trace, code = error, "flush callback");
+    let Some(tx) = take_flush(context) else {
+        trace::event!(
+            debug,
+            code = error,
+            "flush completed after its session was dropped"
+        );
+        return;
+    };
+    // The receiver may already be gone after a timeout or a cancelled
+    // future, in which case the send is a no-op.
+    if !tx.send(error) {
+        trace::event!(
+            debug,
+            code = error,
+            "flush completed after the waiter gave up"
+        );
+    }
 }
 
 // ---- Async (tokio) variants -----------------------------------------------
@@ -152,13 +298,11 @@ mod async_impl {
     use tokio::sync::mpsc as tokio_mpsc;
 
     use crate::event::Event;
+    use crate::trace;
 
     /// Pinned sender kept alive by `AsyncEventSubscription`.
     pub(crate) type AsyncEventSender = Pin<Box<tokio_mpsc::UnboundedSender<Event>>>;
 
-    /// Pinned sender for a one-shot async flush result.
-    pub(crate) type AsyncFlushSender = Pin<Box<tokio_mpsc::UnboundedSender<ffi::CorsairError>>>;
-
     /// Create a (sender, receiver) pair for async SDK events.
     pub(crate) fn async_event_channel() -> (AsyncEventSender, tokio_mpsc::UnboundedReceiver<Event>)
     {
@@ -166,13 +310,16 @@ mod async_impl {
         (Box::pin(tx), rx)
     }
 
-    /// Create a (sender, receiver) pair for an async flush result.
+    /// Create a (context, receiver) pair for an async flush result.
+    ///
+    /// Ownership rules are those of `flush_context`; the context is passed
+    /// to the SDK together with the same `flush_trampoline`.
     pub(crate) fn async_flush_channel() -> (
-        AsyncFlushSender,
+        *mut c_void,
         tokio_mpsc::UnboundedReceiver<ffi::CorsairError>,
     ) {
         let (tx, rx) = tokio_mpsc::unbounded_channel();
-        (Box::pin(tx), rx)
+        (super::flush_context(super::FlushSender::Async(tx)), rx)
     }
 
     /// Return a raw pointer suitable for the SDK `context` parameter.
@@ -201,28 +348,18 @@ mod async_impl {
         // SAFETY: `event` is provided by the SDK and valid for the duration of
         // this callback invocation.
         let ev = unsafe { &*event };
-        if let Some(parsed) = Event::from_ffi(ev) {
-            let _ = tx.send(parsed);
+        let parsed = match Event::from_ffi(ev) {
+            Ok(parsed) => parsed,
+            Err(_e) => {
+                trace::event!(warn, id = ev.id, error = %_e, "dropped malformed SDK event");
+                return;
+            }
+        };
+        trace::event!(trace, event = ?parsed, "SDK event");
+        if tx.send(parsed).is_err() {
+            trace::event!(warn, "dropped SDK event: subscription receiver is gone");
         }
     }
-
-    /// `extern "C"` trampoline for `CorsairAsyncCallback` (async variant).
-    ///
-    /// # Safety
-    ///
-    /// - `context` must be a valid pointer to a live
-    ///   `tokio_mpsc::UnboundedSender<CorsairError>` (guaranteed by the
-    ///   `Pin<Box<Sender>>` kept alive in `flush_led_colors_async`).
-    pub(crate) unsafe extern "C" fn async_flush_trampoline(
-        context: *mut c_void,
-        error: ffi::CorsairError,
-    ) {
-        // SAFETY: `context` was created by `async_sender_as_context` from a
-        // pinned boxed sender held by the async method until the receiver
-        // gets the result.
-        let tx = unsafe { &*(context as *const tokio_mpsc::UnboundedSender<ffi::CorsairError>) };
-        let _ = tx.send(error);
-    }
 }
 
 #[cfg(feature = "async")]
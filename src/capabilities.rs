@@ -0,0 +1,93 @@
+//! Features that depend on the iCUE server version.
+//!
+//! [`ServerCapabilities`] answers whether the server a session connected to
+//! supports a [`Feature`].  It is information for the application; the
+//! session does not check it before each call.
+//!
+//! Every current feature is available on every server that
+//! [`wait_for_connection`](crate::Session::wait_for_connection) accepts; the
+//! answers start to differ once a feature needs a newer server.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use cue_sdk::capabilities::Feature;
+//! # let session = cue_sdk::connect().unwrap();
+//! let details = session.wait_for_connection(Duration::from_secs(5)).unwrap();
+//! if details.capabilities().supports(Feature::KeyEventInterception) {
+//!     // ...
+//! }
+//! ```
+
+use crate::session::Version;
+
+// ---------------------------------------------------------------------------
+// Feature
+// ---------------------------------------------------------------------------
+
+/// A server feature whose availability depends on the server version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// Intercepting macro keys with
+    /// [`Session::configure_key_event`](crate::Session::configure_key_event).
+    KeyEventInterception,
+    /// Writing device properties.
+    PropertyWrite,
+    /// Flushing the LED colour buffer asynchronously with
+    /// [`Session::start_flush_led_colors`](crate::Session::start_flush_led_colors).
+    AsyncFlush,
+}
+
+impl Feature {
+    /// Every feature known to this crate.
+    pub const ALL: [Feature; 3] = [
+        Self::KeyEventInterception,
+        Self::PropertyWrite,
+        Self::AsyncFlush,
+    ];
+
+    /// The oldest server version that supports this feature.
+    ///
+    /// All current features were introduced with protocol 4.0, the oldest
+    /// server a session connects to.
+    pub const fn min_server_version(self) -> Version {
+        match self {
+            Self::KeyEventInterception | Self::PropertyWrite | Self::AsyncFlush => {
+                Version::new(4, 0, 0)
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ServerCapabilities
+// ---------------------------------------------------------------------------
+
+/// The features supported by a particular iCUE server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ServerCapabilities {
+    server_version: Version,
+}
+
+impl ServerCapabilities {
+    /// The capabilities of a server with this version.
+    pub const fn new(server_version: Version) -> Self {
+        Self { server_version }
+    }
+
+    /// The server version these capabilities were derived from.
+    pub const fn server_version(&self) -> Version {
+        self.server_version
+    }
+
+    /// Whether the server supports `feature`.
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.server_version.major == feature.min_server_version().major
+            && self.server_version >= feature.min_server_version()
+    }
+
+    /// Iterate over the supported features.
+    pub fn iter(&self) -> impl Iterator<Item = Feature> + '_ {
+        Feature::ALL.into_iter().filter(|&f| self.supports(f))
+    }
+}
@@ -0,0 +1,390 @@
+//! Stacking independent lighting layers into one set of LED colors.
+//!
+//! A [`Compositor`] holds an ordered stack of [`Layer`]s.  Each layer sets
+//! RGBA colors for some LEDs and is blended onto the layers below it with a
+//! [`BlendMode`] and an opacity, so separate effects (a base color, a key
+//! highlight, a notification flash) can be written independently and
+//! combined each frame.
+//!
+//! Layers address LEDs by LUID.  LUIDs are only unique within one device, so
+//! use one compositor per device when devices need different content.
+//!
+//! ```
+//! # use cue_sdk::Rgba;
+//! # use cue_sdk::compositor::{BlendMode, Compositor, Layer};
+//! # use cue_sdk::led::keyboard;
+//! let mut compositor = Compositor::new();
+//! compositor.push(Layer::new("base").with_fill(Rgba::rgb(0, 0, 64)));
+//! let highlight = compositor.push(Layer::new("wasd").with_mode(BlendMode::Add));
+//! compositor
+//!     .layer_mut(highlight)
+//!     .unwrap()
+//!     .set(keyboard::W, Rgba::rgb(255, 0, 0));
+//!
+//! let colors = compositor.compose(&[keyboard::W, keyboard::Q]);
+//! assert_eq!(colors[0].color(), Rgba::rgb(255, 0, 64));
+//! assert_eq!(colors[1].color(), Rgba::rgb(0, 0, 64));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::frame::LedFrame;
+use crate::led::{LedColor, Rgba};
+use crate::zone::Zone;
+
+// ---------------------------------------------------------------------------
+// BlendMode
+// ---------------------------------------------------------------------------
+
+/// How a layer's color is combined with the colors below it.
+///
+/// The blended color is then mixed with the color below according to the
+/// layer's alpha and opacity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Replace the color below.
+    #[default]
+    Normal,
+    /// Add the channels, clamping at full brightness.
+    Add,
+    /// Multiply the channels; darkens.
+    Multiply,
+    /// Inverse of multiplying the inverted channels; lightens.
+    Screen,
+    /// Multiply dark channels and screen light ones.
+    Overlay,
+    /// Take the brighter of the two channels.
+    Max,
+}
+
+impl BlendMode {
+    /// Blend one channel, with both values in `0.0..=1.0`.
+    fn channel(self, below: f32, above: f32) -> f32 {
+        match self {
+            Self::Normal => above,
+            Self::Add => (below + above).min(1.0),
+            Self::Multiply => below * above,
+            Self::Screen => 1.0 - (1.0 - below) * (1.0 - above),
+            Self::Overlay if below < 0.5 => 2.0 * below * above,
+            Self::Overlay => 1.0 - 2.0 * (1.0 - below) * (1.0 - above),
+            Self::Max => below.max(above),
+        }
+    }
+
+    /// Blend `above` onto `below` with an extra opacity factor.
+    ///
+    /// The result keeps the alpha of `below`.
+    pub fn blend(self, below: Rgba, above: Rgba, opacity: f32) -> Rgba {
+        let alpha = f32::from(above.a) / 255.0 * opacity.clamp(0.0, 1.0);
+        if alpha <= 0.0 {
+            return below;
+        }
+        let mix = |b: u8, a: u8| {
+            let b = f32::from(b) / 255.0;
+            let blended = self.channel(b, f32::from(a) / 255.0);
+            ((b + (blended - b) * alpha) * 255.0).round() as u8
+        };
+        Rgba::new(
+            mix(below.r, above.r),
+            mix(below.g, above.g),
+            mix(below.b, above.b),
+            below.a,
+        )
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Layer
+// ---------------------------------------------------------------------------
+
+/// One layer of LED colors.
+///
+/// An LED's color on the layer is, in order of precedence: its own color,
+/// the color of the most recently filled zone containing it, or the layer's
+/// fill color.  LEDs with none of these are transparent.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    name: String,
+    mode: BlendMode,
+    opacity: f32,
+    visible: bool,
+    fill: Option<Rgba>,
+    zones: Vec<(Zone, Rgba)>,
+    colors: HashMap<u32, Rgba>,
+}
+
+impl Layer {
+    /// Create an empty, fully opaque, visible layer using [`BlendMode::Normal`].
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            mode: BlendMode::Normal,
+            opacity: 1.0,
+            visible: true,
+            fill: None,
+            zones: Vec::new(),
+            colors: HashMap::new(),
+        }
+    }
+
+    /// Use the given blend mode.
+    pub fn with_mode(mut self, mode: BlendMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Use the given opacity.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.set_opacity(opacity);
+        self
+    }
+
+    /// Give every LED without its own color this color.
+    pub fn with_fill(mut self, color: Rgba) -> Self {
+        self.fill = Some(color);
+        self
+    }
+
+    /// The layer's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The blend mode.
+    pub fn mode(&self) -> BlendMode {
+        self.mode
+    }
+
+    /// Change the blend mode.
+    pub fn set_mode(&mut self, mode: BlendMode) {
+        self.mode = mode;
+    }
+
+    /// The opacity, from `0.0` (invisible) to `1.0`.
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Change the opacity.  Values are clamped to `0.0..=1.0`.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Whether the layer takes part in composition.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the layer.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Set or clear the fill color.
+    pub fn set_fill(&mut self, color: Option<Rgba>) {
+        self.fill = color;
+    }
+
+    /// The color this layer gives an LED, if any.
+    pub fn get(&self, luid: u32) -> Option<Rgba> {
+        if let Some(&color) = self.colors.get(&luid) {
+            return Some(color);
+        }
+        self.zones
+            .iter()
+            .rev()
+            .find(|(zone, _)| zone.contains(luid))
+            .map(|&(_, color)| color)
+            .or(self.fill)
+    }
+
+    /// Set the color of one LED.
+    pub fn set(&mut self, luid: u32, color: Rgba) {
+        self.colors.insert(luid, color);
+    }
+
+    /// Set the same color on several LEDs.
+    pub fn set_leds(&mut self, luids: impl IntoIterator<Item = u32>, color: Rgba) {
+        for luid in luids {
+            self.set(luid, color);
+        }
+    }
+
+    /// Set the same color on every LED in `zone`.
+    ///
+    /// Per-LED colors set with [`set`](Self::set) still take precedence.
+    /// Filling a zone equal to one filled before replaces its color and makes
+    /// it the most recently filled, so filling the same zones every frame
+    /// does not grow the layer.
+    pub fn fill_zone(&mut self, zone: &Zone, color: Rgba) {
+        self.zones.retain(|(filled, _)| filled != zone);
+        self.zones.push((zone.clone(), color));
+    }
+
+    /// Remove the color of one LED, making it use the zone or fill color
+    /// again.
+    pub fn unset(&mut self, luid: u32) {
+        self.colors.remove(&luid);
+    }
+
+    /// Remove every per-LED color, zone fill and the fill color.
+    pub fn clear(&mut self) {
+        self.colors.clear();
+        self.zones.clear();
+        self.fill = None;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Compositor
+// ---------------------------------------------------------------------------
+
+/// Identifies a layer within a [`Compositor`].
+///
+/// Ids stay valid while other layers are added, removed or moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerId(u64);
+
+/// An ordered stack of layers, bottom first.
+#[derive(Debug, Clone)]
+pub struct Compositor {
+    background: Rgba,
+    layers: Vec<(LayerId, Layer)>,
+    next_id: u64,
+}
+
+impl Default for Compositor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compositor {
+    /// Create an empty compositor over a black background.
+    pub fn new() -> Self {
+        Self {
+            background: Rgba::BLACK,
+            layers: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// The color LEDs have before any layer is applied.
+    pub fn background(&self) -> Rgba {
+        self.background
+    }
+
+    /// Change the background color.
+    pub fn set_background(&mut self, color: Rgba) {
+        self.background = color;
+    }
+
+    /// Add a layer on top of the stack.
+    pub fn push(&mut self, layer: Layer) -> LayerId {
+        self.insert(self.layers.len(), layer)
+    }
+
+    /// Add a layer at `index`, counting from the bottom.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > self.len()`.
+    pub fn insert(&mut self, index: usize, layer: Layer) -> LayerId {
+        let id = LayerId(self.next_id);
+        self.next_id += 1;
+        self.layers.insert(index, (id, layer));
+        id
+    }
+
+    /// Remove a layer.
+    pub fn remove(&mut self, id: LayerId) -> Option<Layer> {
+        let index = self.index_of(id)?;
+        Some(self.layers.remove(index).1)
+    }
+
+    /// Move a layer to `index`, counting from the bottom.  Indices past the
+    /// top move the layer to the top.
+    ///
+    /// Returns `false` if there is no such layer.
+    pub fn move_to(&mut self, id: LayerId, index: usize) -> bool {
+        let Some(from) = self.index_of(id) else {
+            return false;
+        };
+        let entry = self.layers.remove(from);
+        let index = index.min(self.layers.len());
+        self.layers.insert(index, entry);
+        true
+    }
+
+    /// The position of a layer, counting from the bottom.
+    pub fn index_of(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter().position(|(layer_id, _)| *layer_id == id)
+    }
+
+    /// A layer by id.
+    pub fn layer(&self, id: LayerId) -> Option<&Layer> {
+        self.layers.iter().find(|(i, _)| *i == id).map(|(_, l)| l)
+    }
+
+    /// A layer by id, for changing its colors or settings.
+    pub fn layer_mut(&mut self, id: LayerId) -> Option<&mut Layer> {
+        self.layers
+            .iter_mut()
+            .find(|(i, _)| *i == id)
+            .map(|(_, l)| l)
+    }
+
+    /// The first layer with this name.
+    pub fn find(&self, name: &str) -> Option<LayerId> {
+        self.layers
+            .iter()
+            .find(|(_, l)| l.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Iterate over the layers, bottom first.
+    pub fn layers(&self) -> impl Iterator<Item = (LayerId, &Layer)> + '_ {
+        self.layers.iter().map(|(id, layer)| (*id, layer))
+    }
+
+    /// Number of layers.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Whether there are no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    // ---- Output -----------------------------------------------------------
+
+    /// The composed color of one LED.
+    pub fn color(&self, luid: u32) -> Rgba {
+        self.layers
+            .iter()
+            .filter(|(_, layer)| layer.visible && layer.opacity > 0.0)
+            .fold(self.background, |below, (_, layer)| match layer.get(luid) {
+                Some(above) => layer.mode.blend(below, above, layer.opacity),
+                None => below,
+            })
+    }
+
+    /// Compose the colors of the given LEDs.
+    pub fn compose(&self, luids: &[u32]) -> Vec<LedColor> {
+        luids
+            .iter()
+            .map(|&luid| LedColor::with_color(luid, self.color(luid)))
+            .collect()
+    }
+
+    /// Compose every LED of `frame` into it.
+    ///
+    /// Only LEDs whose composed color changed are marked dirty.
+    pub fn compose_into(&self, frame: &mut LedFrame) {
+        for index in 0..frame.len() {
+            let luid = frame.colors()[index].id;
+            frame.set_index(index, self.color(luid));
+        }
+    }
+}
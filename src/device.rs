@@ -1,9 +1,18 @@
 use core::ffi::c_char;
+use std::collections::HashSet;
 use std::fmt;
 
 use bitflags::bitflags;
 use cue_sdk_sys as ffi;
 
+use crate::error::{ErrorContext, Malformed, Operation, Result, SdkError};
+use crate::guard::ControlGuard;
+use crate::led::{LedColor, LedPosition, Rgba};
+use crate::property::{FromPropertyValue, PropertyId};
+use crate::registry::{Capabilities, DeviceEntry};
+use crate::session::{AccessLevel, Session};
+use crate::validate;
+
 // ---------------------------------------------------------------------------
 // DeviceId
 // ---------------------------------------------------------------------------
@@ -21,9 +30,11 @@ impl DeviceId {
         self.0.as_ptr()
     }
 
-    /// Create from the FFI type (copy).
-    pub(crate) fn from_ffi(raw: ffi::CorsairDeviceId) -> Self {
-        Self(raw)
+    /// Create from the FFI type (copy).  The id must be null-terminated so
+    /// it can be passed back to the SDK as a C string.
+    pub(crate) fn from_ffi(raw: ffi::CorsairDeviceId) -> std::result::Result<Self, Malformed> {
+        validate::terminated_len(&raw)?;
+        Ok(Self(raw))
     }
 }
 
@@ -42,6 +53,47 @@ impl fmt::Display for DeviceId {
     }
 }
 
+impl std::str::FromStr for DeviceId {
+    type Err = SdkError;
+
+    /// Parse a device id from its string form.
+    ///
+    /// Returns `SdkError::InvalidArguments` if the string is too long or
+    /// contains a null byte.
+    fn from_str(s: &str) -> std::result::Result<Self, SdkError> {
+        let mut raw: ffi::CorsairDeviceId = [0; ffi::CORSAIR_STRING_SIZE_M as usize];
+        // Leave room for the terminating null byte.
+        if s.len() >= raw.len() || s.bytes().any(|b| b == 0) {
+            return Err(SdkError::InvalidArguments);
+        }
+        for (dst, b) in raw.iter_mut().zip(s.bytes()) {
+            *dst = b as c_char;
+        }
+        Ok(Self(raw))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DeviceId {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DeviceId {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|_| serde::de::Error::custom("invalid device id"))
+    }
+}
+
 // ---------------------------------------------------------------------------
 // DeviceType (bitflags)
 // ---------------------------------------------------------------------------
@@ -68,6 +120,29 @@ bitflags! {
     }
 }
 
+impl DeviceType {
+    /// Every device type this crate has a name for.
+    const KNOWN: Self = Self::KEYBOARD
+        .union(Self::MOUSE)
+        .union(Self::MOUSEMAT)
+        .union(Self::HEADSET)
+        .union(Self::HEADSET_STAND)
+        .union(Self::FAN_LED_CONTROLLER)
+        .union(Self::LED_CONTROLLER)
+        .union(Self::MEMORY_MODULE)
+        .union(Self::COOLER)
+        .union(Self::MOTHERBOARD)
+        .union(Self::GRAPHICS_CARD)
+        .union(Self::TOUCHBAR)
+        .union(Self::GAME_CONTROLLER);
+
+    /// The bits of device types newer than this crate, as reported by the
+    /// SDK.  Zero if every bit is a known type.
+    pub fn unknown_bits(self) -> u32 {
+        self.bits() & !Self::KNOWN.bits()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // DeviceInfo
 // ---------------------------------------------------------------------------
@@ -75,7 +150,8 @@ bitflags! {
 /// Information about a connected Corsair device.
 #[derive(Debug, Clone)]
 pub struct DeviceInfo {
-    /// The device type bitmask.
+    /// The device type bitmask.  Bits for device types this crate does not
+    /// know about are kept; see [`DeviceType::unknown_bits`].
     pub device_type: DeviceType,
     /// Unique device identifier.
     pub id: DeviceId,
@@ -90,26 +166,315 @@ pub struct DeviceInfo {
 }
 
 impl DeviceInfo {
-    /// Convert from the FFI struct.
-    pub(crate) fn from_ffi(raw: &ffi::CorsairDeviceInfo) -> Self {
-        Self {
-            device_type: DeviceType::from_bits_truncate(raw.type_),
-            id: DeviceId::from_ffi(raw.id),
-            serial: c_char_array_to_string(&raw.serial),
-            model: c_char_array_to_string(&raw.model),
+    /// Convert from the FFI struct, rejecting unterminated strings and
+    /// negative or oversized counts.
+    pub(crate) fn from_ffi(raw: &ffi::CorsairDeviceInfo) -> std::result::Result<Self, Malformed> {
+        validate::count(raw.ledCount, ffi::CORSAIR_DEVICE_LEDCOUNT_MAX as usize)?;
+        validate::count(raw.channelCount, usize::MAX)?;
+        Ok(Self {
+            device_type: DeviceType::from_bits_retain(raw.type_),
+            id: DeviceId::from_ffi(raw.id)?,
+            serial: validate::c_string(&raw.serial)?,
+            model: validate::c_string(&raw.model)?,
             led_count: raw.ledCount,
             channel_count: raw.channelCount,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ChannelDeviceType
+// ---------------------------------------------------------------------------
+
+/// The kind of LED device attached to a DIY controller channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelDeviceType {
+    HdFan,
+    SpFan,
+    LlFan,
+    MlFan,
+    QlFan,
+    EightLedSeriesFan,
+    Strip,
+    Dap,
+    Pump,
+    Dram,
+    WaterBlock,
+    QxFan,
+    Unknown(u32),
+}
+
+impl ChannelDeviceType {
+    pub(crate) fn from_ffi(raw: ffi::CorsairChannelDeviceType) -> Self {
+        match raw {
+            ffi::CorsairChannelDeviceType_CCDT_HD_Fan => Self::HdFan,
+            ffi::CorsairChannelDeviceType_CCDT_SP_Fan => Self::SpFan,
+            ffi::CorsairChannelDeviceType_CCDT_LL_Fan => Self::LlFan,
+            ffi::CorsairChannelDeviceType_CCDT_ML_Fan => Self::MlFan,
+            ffi::CorsairChannelDeviceType_CCDT_QL_Fan => Self::QlFan,
+            ffi::CorsairChannelDeviceType_CCDT_8LedSeriesFan => Self::EightLedSeriesFan,
+            ffi::CorsairChannelDeviceType_CCDT_Strip => Self::Strip,
+            ffi::CorsairChannelDeviceType_CCDT_DAP => Self::Dap,
+            ffi::CorsairChannelDeviceType_CCDT_Pump => Self::Pump,
+            ffi::CorsairChannelDeviceType_CCDT_DRAM => Self::Dram,
+            ffi::CorsairChannelDeviceType_CCDT_WaterBlock => Self::WaterBlock,
+            ffi::CorsairChannelDeviceType_CCDT_QX_Fan => Self::QxFan,
+            other => Self::Unknown(other),
+        }
+    }
+
+    /// Whether this is one of the fan types.
+    pub fn is_fan(self) -> bool {
+        matches!(
+            self,
+            Self::HdFan
+                | Self::SpFan
+                | Self::LlFan
+                | Self::MlFan
+                | Self::QlFan
+                | Self::EightLedSeriesFan
+                | Self::QxFan
+        )
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ChannelDevice
+// ---------------------------------------------------------------------------
+
+/// An LED device attached to one channel of a DIY controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelDevice {
+    /// What kind of device this is.
+    pub device_type: ChannelDeviceType,
+    /// Number of LEDs driven by this device.
+    pub led_count: u32,
+}
+
+impl ChannelDevice {
+    /// Pair up a channel's `ChannelDeviceLedCountArray` and
+    /// `ChannelDeviceTypeArray`, which must have the same length.
+    pub(crate) fn from_ffi_arrays(
+        counts: &[i32],
+        types: &[i32],
+    ) -> std::result::Result<Vec<Self>, Malformed> {
+        if counts.len() != types.len() {
+            return Err(Malformed::LengthMismatch {
+                expected: counts.len(),
+                actual: types.len(),
+            });
+        }
+        counts
+            .iter()
+            .zip(types)
+            .map(|(&count, &ty)| {
+                Ok(Self {
+                    device_type: ChannelDeviceType::from_ffi(ty as u32),
+                    led_count: validate::count(count, ffi::CORSAIR_DEVICE_LEDCOUNT_MAX as usize)?
+                        as u32,
+                })
+            })
+            .collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Device
+// ---------------------------------------------------------------------------
+
+/// A device bound to the [`Session`] it was obtained from.
+///
+/// Carries the device's [`DeviceInfo`], LED positions and property
+/// [`Capabilities`], so per-device calls do not need a `&DeviceId` and LED
+/// colors are checked against the device's LEDs before they reach the SDK.
+///
+/// ```no_run
+/// # use cue_sdk::{DeviceType, LedColor, Rgba};
+/// # use cue_sdk::property::PropertyId;
+/// # let session = cue_sdk::connect().unwrap();
+/// for device in session.devices(DeviceType::ALL).unwrap() {
+///     let colors: Vec<LedColor> = device
+///         .leds()
+///         .map(|led| LedColor::with_color(led.id, Rgba::rgb(255, 0, 0)))
+///         .collect();
+///     device.set_colors(&colors).unwrap();
+///
+///     if device.capabilities().can_read(PropertyId::BatteryLevel) {
+///         let level: i32 = device.property(PropertyId::BatteryLevel, 0).unwrap();
+///         println!("{}: {level}%", device.info().model);
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Device<'s> {
+    session: &'s Session,
+    info: DeviceInfo,
+    positions: Vec<LedPosition>,
+    capabilities: Capabilities,
+    luids: HashSet<u32>,
+}
+
+impl<'s> Device<'s> {
+    /// Query a device's information, LED positions and capabilities.
+    pub fn new(session: &'s Session, device_id: &DeviceId) -> Result<Self> {
+        let info = session.get_device_info(device_id)?;
+        Self::with_info(session, info)
+    }
+
+    /// Bind a device whose [`DeviceInfo`] is already known, querying its LED
+    /// positions and capabilities.
+    pub fn with_info(session: &'s Session, info: DeviceInfo) -> Result<Self> {
+        let positions = session.get_led_positions(&info.id)?;
+        let capabilities = Capabilities::query(session, &info.id);
+        Ok(Self::from_parts(session, info, positions, capabilities))
+    }
+
+    /// Bind a device cached in a [`DeviceRegistry`](crate::registry::DeviceRegistry)
+    /// without querying the SDK.
+    pub fn from_entry(session: &'s Session, entry: &DeviceEntry) -> Self {
+        Self::from_parts(
+            session,
+            entry.info.clone(),
+            entry.positions.clone(),
+            entry.capabilities.clone(),
+        )
+    }
+
+    fn from_parts(
+        session: &'s Session,
+        info: DeviceInfo,
+        positions: Vec<LedPosition>,
+        capabilities: Capabilities,
+    ) -> Self {
+        let luids = positions.iter().map(|pos| pos.id).collect();
+        Self {
+            session,
+            info,
+            positions,
+            capabilities,
+            luids,
         }
     }
+
+    /// The session this device belongs to.
+    pub fn session(&self) -> &'s Session {
+        self.session
+    }
+
+    /// The device id.
+    pub fn id(&self) -> &DeviceId {
+        &self.info.id
+    }
+
+    /// Device information.
+    pub fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    /// Supported properties.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// LED positions, in the order reported by the SDK.
+    pub fn positions(&self) -> &[LedPosition] {
+        &self.positions
+    }
+
+    /// Iterate over the device's LEDs.
+    pub fn leds(&self) -> impl Iterator<Item = &LedPosition> + '_ {
+        self.positions.iter()
+    }
+
+    /// Whether the LUID belongs to this device.
+    pub fn contains(&self, luid: u32) -> bool {
+        self.luids.contains(&luid)
+    }
+
+    // ---- LEDs -------------------------------------------------------------
+
+    /// Set LED colors immediately.
+    ///
+    /// Returns `SdkError::InvalidArguments` without calling the SDK if any
+    /// LUID does not belong to this device.
+    pub fn set_colors(&self, colors: &[LedColor]) -> Result<()> {
+        self.validate(Operation::SetLedColors, colors)?;
+        self.session.set_led_colors(self.id(), colors)
+    }
+
+    /// Buffer LED colors for the next [`Session::flush_led_colors`].
+    ///
+    /// LUIDs are validated as for [`set_colors`](Self::set_colors).
+    pub fn buffer_colors(&self, colors: &[LedColor]) -> Result<()> {
+        self.validate(Operation::SetLedColorsBuffer, colors)?;
+        self.session.set_led_colors_buffer(self.id(), colors)
+    }
+
+    /// Read the current colors of the given LEDs.
+    ///
+    /// The `id` of each element selects the LED; LUIDs are validated as for
+    /// [`set_colors`](Self::set_colors).
+    pub fn read_colors(&self, colors: &mut [LedColor]) -> Result<()> {
+        self.validate(Operation::GetLedColors, colors)?;
+        self.session.get_led_colors(self.id(), colors)
+    }
+
+    /// Read the current colors of every LED on the device.
+    pub fn read_all_colors(&self) -> Result<Vec<LedColor>> {
+        let mut colors: Vec<LedColor> = self
+            .positions
+            .iter()
+            .map(|pos| LedColor::with_color(pos.id, Rgba::BLACK))
+            .collect();
+        self.session.get_led_colors(self.id(), &mut colors)?;
+        Ok(colors)
+    }
+
+    fn validate(&self, operation: Operation, colors: &[LedColor]) -> Result<()> {
+        if colors.iter().all(|led| self.contains(led.id)) {
+            Ok(())
+        } else {
+            Err(ErrorContext::new(operation)
+                .device(self.id())
+                .error(SdkError::InvalidArguments))
+        }
+    }
+
+    // ---- Access control ---------------------------------------------------
+
+    /// Request exclusive control of the device.
+    pub fn request_control(&self, level: AccessLevel) -> Result<()> {
+        self.session.request_control(self.id(), level)
+    }
+
+    /// Request exclusive control of the device, released when the returned
+    /// guard is dropped.
+    pub fn acquire_control(&self, level: AccessLevel) -> Result<ControlGuard<'s>> {
+        self.session.acquire_control(self.id(), level)
+    }
+
+    /// Release exclusive control of the device.
+    pub fn release_control(&self) -> Result<()> {
+        self.session.release_control(self.id())
+    }
+
+    // ---- Properties -------------------------------------------------------
+
+    /// Read a property as `T`.
+    ///
+    /// Returns `SdkError::TypeMismatch` if the property holds a different
+    /// type.
+    pub fn property<T: FromPropertyValue>(&self, property: PropertyId, index: u32) -> Result<T> {
+        self.session.read(self.id(), property, index)
+    }
 }
 
-/// Convert a fixed-size `c_char` array to an owned `String`, stopping at the
-/// first null byte.
-fn c_char_array_to_string(arr: &[c_char]) -> String {
-    let bytes: Vec<u8> = arr
-        .iter()
-        .map(|&c| c as u8)
-        .take_while(|&b| b != 0)
-        .collect();
-    String::from_utf8_lossy(&bytes).into_owned()
+impl fmt::Debug for Device<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Device")
+            .field("info", &self.info)
+            .field("positions", &self.positions.len())
+            .field("capabilities", &self.capabilities)
+            .finish_non_exhaustive()
+    }
 }
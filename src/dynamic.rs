@@ -0,0 +1,175 @@
+//! Loading the iCUE SDK library at runtime.
+//!
+//! With the `dynamic` feature the crate calls the SDK only through function
+//! pointers, never through a symbol resolved by the linker.  The library is
+//! loaded by [`connect()`](crate::connect) (or earlier by
+//! [`load`]), which fails with [`SdkError::LibraryNotFound`] if no
+//! candidate could be opened, or [`SdkError::SymbolMissing`] if the library
+//! found lacks a function this crate uses.  [`Error::detail`] lists every
+//! path tried and why it failed.
+//!
+//! Candidates are tried in order: each configured search path, then the
+//! platform's library name on the system search path.  A search path may be
+//! the library file itself or a directory containing it.
+//!
+//! ```no_run
+//! # use cue_sdk::{dynamic, SdkError};
+//! dynamic::add_search_path(r"C:\Program Files\Corsair\iCUE SDK");
+//! match cue_sdk::connect() {
+//!     Ok(session) => { /* ... */ }
+//!     Err(e) if e.kind() == SdkError::LibraryNotFound => println!("iCUE is not installed"),
+//!     Err(e) => println!("{e}"),
+//! }
+//! ```
+//!
+//! # Building
+//!
+//! The SDK types still come from `cue-sdk-sys`, whose build script runs with
+//! or without this feature:
+//!
+//! - **Linux**: nothing is linked, so no SDK is needed to build.
+//! - **Windows**: the build needs `CUE_SDK_LIB_FILES_PATH` and passes the
+//!   import library to the linker.  No function from it is used, so the
+//!   program does not load the DLL at startup and runs without iCUE.
+//! - **macOS**: the build needs `CUE_SDK_FRAMEWORK_PATH` and links
+//!   `iCUESDK.framework`, so the program still fails to start where the
+//!   framework cannot be found.
+//!
+//! To build without the SDK on Windows or macOS, override the `iCUESDK`
+//! link for the target in the application's `.cargo/config.toml`.  Cargo
+//! then skips the `cue-sdk-sys` build script and links nothing:
+//!
+//! ```toml
+//! [target.x86_64-pc-windows-msvc.iCUESDK]
+//! rustc-link-lib = []
+//!
+//! [target.aarch64-apple-darwin.iCUESDK]
+//! rustc-link-lib = []
+//! ```
+//!
+//! [`Error::detail`]: crate::Error::detail
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{ErrorContext, Operation, Result, SdkError};
+use crate::sdk::{self, Api};
+use crate::trace;
+
+/// The file name of the SDK library on this platform.
+///
+/// On macOS this is the library's path inside its framework.  When it is
+/// tried on its own, dyld looks for it in `DYLD_FRAMEWORK_PATH`, the current
+/// directory and the fallback framework directories such as
+/// `/Library/Frameworks`; add any other directory holding
+/// `iCUESDK.framework`, such as an app bundle's `Contents/Frameworks`, with
+/// [`add_search_path`].
+pub const LIBRARY_NAME: &str = if cfg!(target_os = "windows") {
+    "iCUESDK.x64_2019.dll"
+} else if cfg!(target_os = "macos") {
+    "iCUESDK.framework/iCUESDK"
+} else {
+    "libiCUESDK.so"
+};
+
+static SEARCH_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Look for the library in `path` before the system search path.
+///
+/// Paths are tried in the order they were added.  Has no effect once the
+/// library is loaded.
+pub fn add_search_path(path: impl Into<PathBuf>) {
+    lock_paths().push(path.into());
+}
+
+/// Replace the configured search paths.
+pub fn set_search_paths<I>(paths: I)
+where
+    I: IntoIterator,
+    I::Item: Into<PathBuf>,
+{
+    *lock_paths() = paths.into_iter().map(Into::into).collect();
+}
+
+/// The configured search paths.
+pub fn search_paths() -> Vec<PathBuf> {
+    lock_paths().clone()
+}
+
+/// Whether the library has been loaded.
+pub fn is_loaded() -> bool {
+    sdk::is_loaded()
+}
+
+/// Load the SDK library if it is not loaded yet.
+///
+/// Called by [`Session::connect`](crate::Session::connect); call it earlier
+/// to find out whether iCUE is installed without connecting.  Once loaded,
+/// the library stays loaded for the life of the process.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "info", skip_all, err(level = "warn"))
+)]
+pub fn load() -> Result<()> {
+    // Serialise loads so two threads do not both open the library.
+    let paths = lock_paths();
+    if sdk::is_loaded() {
+        return Ok(());
+    }
+
+    let mut tried = Vec::new();
+    let mut missing = None;
+    for candidate in candidates(&paths) {
+        // SAFETY: Loading a library runs its initialisers.  The candidates
+        // are the iCUE SDK as configured by the application or found on the
+        // system search path.
+        let library = match unsafe { libloading::Library::new(&candidate) } {
+            Ok(library) => library,
+            Err(e) => {
+                trace::event!(debug, path = %candidate.display(), error = %e, "SDK library not loaded");
+                tried.push(format!("{} ({e})", candidate.display()));
+                continue;
+            }
+        };
+        // SAFETY: The library was found under the SDK's name, so its symbols
+        // have the signatures declared in `cue_sdk_sys`.
+        match unsafe { Api::resolve(library) } {
+            Ok(api) => {
+                trace::event!(info, path = %candidate.display(), "loaded SDK library");
+                sdk::install(api);
+                return Ok(());
+            }
+            Err(symbol) => {
+                trace::event!(warn, path = %candidate.display(), symbol, "SDK library is missing a symbol");
+                tried.push(format!("{} (no symbol `{symbol}`)", candidate.display()));
+                missing.get_or_insert(symbol);
+            }
+        }
+    }
+
+    let kind = match missing {
+        Some(symbol) => SdkError::SymbolMissing(symbol),
+        None => SdkError::LibraryNotFound,
+    };
+    Err(ErrorContext::new(Operation::Connect)
+        .error_with_detail(kind, format!("tried {}", tried.join(", "))))
+}
+
+/// The paths to try, in order.
+fn candidates(search_paths: &[PathBuf]) -> Vec<PathBuf> {
+    search_paths
+        .iter()
+        .map(|path| {
+            if path.is_dir() {
+                path.join(LIBRARY_NAME)
+            } else {
+                path.clone()
+            }
+        })
+        .chain(std::iter::once(Path::new(LIBRARY_NAME).to_path_buf()))
+        .collect()
+}
+
+fn lock_paths() -> std::sync::MutexGuard<'static, Vec<PathBuf>> {
+    SEARCH_PATHS.lock().unwrap_or_else(|e| e.into_inner())
+}
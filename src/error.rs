@@ -1,6 +1,20 @@
+use std::fmt;
+
 use cue_sdk_sys as ffi;
 
-/// All errors that can be returned by SDK operations.
+use crate::callback;
+use crate::device::DeviceId;
+use crate::property::{DataType, PropertyId};
+use crate::session::SessionState;
+use crate::trace;
+
+// ---------------------------------------------------------------------------
+// SdkError
+// ---------------------------------------------------------------------------
+
+/// The kind of failure reported by an SDK operation.
+///
+/// Every [`Error`] carries one of these; use [`Error::kind`] to match on it.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
 pub enum SdkError {
     #[error("not connected to iCUE")]
@@ -17,15 +31,311 @@ pub enum SdkError {
     DeviceNotFound,
     #[error("operation not allowed")]
     NotAllowed,
+    #[error("operation timed out")]
+    Timeout,
+    #[error("property type mismatch: expected {expected:?}, got {actual:?}")]
+    TypeMismatch {
+        expected: DataType,
+        actual: DataType,
+    },
+    #[error("a session is already connected in this process")]
+    AlreadyConnected,
+    #[error("iCUE SDK library not found")]
+    LibraryNotFound,
+    #[error("iCUE SDK library has no symbol `{0}`")]
+    SymbolMissing(&'static str),
+    #[error("malformed SDK response: {0}")]
+    MalformedResponse(#[from] Malformed),
     #[error("unknown SDK error code: {0}")]
     Unknown(u32),
 }
 
+/// What was wrong with data returned by the SDK.
+///
+/// Carried by [`SdkError::MalformedResponse`].  These indicate a bug or
+/// version mismatch in iCUE rather than a problem with the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Malformed {
+    #[error("negative count {0}")]
+    NegativeCount(i32),
+    #[error("count {count} exceeds the maximum of {max}")]
+    CountTooLarge { count: u64, max: u64 },
+    #[error("null pointer")]
+    NullPointer,
+    #[error("string is not null-terminated")]
+    UnterminatedString,
+    #[error("expected {expected} items, got {actual}")]
+    LengthMismatch { expected: usize, actual: usize },
+}
+
+// ---------------------------------------------------------------------------
+// Operation
+// ---------------------------------------------------------------------------
+
+/// The [`Session`](crate::Session) operation that produced an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+    Connect,
+    WaitForConnection,
+    Details,
+    GetDevices,
+    GetDeviceInfo,
+    GetChannelDevices,
+    GetLedPositions,
+    SetLedColors,
+    SetLedColorsBuffer,
+    FlushLedColors,
+    GetLedColors,
+    GetLedLuidForKeyName,
+    SetLayerPriority,
+    RequestControl,
+    ReleaseControl,
+    SubscribeForEvents,
+    ConfigureKeyEvent,
+    GetDevicePropertyInfo,
+    ReadDeviceProperty,
+    WriteDeviceProperty,
+}
+
+impl Operation {
+    /// The name of the `Session` method for this operation.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::WaitForConnection => "wait_for_connection",
+            Self::Details => "details",
+            Self::GetDevices => "get_devices",
+            Self::GetDeviceInfo => "get_device_info",
+            Self::GetChannelDevices => "get_channel_devices",
+            Self::GetLedPositions => "get_led_positions",
+            Self::SetLedColors => "set_led_colors",
+            Self::SetLedColorsBuffer => "set_led_colors_buffer",
+            Self::FlushLedColors => "flush_led_colors",
+            Self::GetLedColors => "get_led_colors",
+            Self::GetLedLuidForKeyName => "get_led_luid_for_key_name",
+            Self::SetLayerPriority => "set_layer_priority",
+            Self::RequestControl => "request_control",
+            Self::ReleaseControl => "release_control",
+            Self::SubscribeForEvents => "subscribe_for_events",
+            Self::ConfigureKeyEvent => "configure_key_event",
+            Self::GetDevicePropertyInfo => "get_device_property_info",
+            Self::ReadDeviceProperty => "read_device_property",
+            Self::WriteDeviceProperty => "write_device_property",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Error
+// ---------------------------------------------------------------------------
+
+/// An error returned by an SDK operation, with context about where it
+/// happened.
+///
+/// [`kind`](Self::kind) returns the plain [`SdkError`] for matching; the
+/// remaining accessors describe the failing call.  `Error` compares equal to
+/// an `SdkError` of the same kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: SdkError,
+    // Boxed so `Result<T>` stays small on the success path.
+    context: Option<Box<Context>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Context {
+    operation: Operation,
+    device_id: Option<DeviceId>,
+    property: Option<PropertyId>,
+    code: Option<ffi::CorsairError>,
+    session_state: SessionState,
+    detail: Option<String>,
+}
+
+impl Error {
+    /// The kind of failure.
+    pub fn kind(&self) -> SdkError {
+        self.kind
+    }
+
+    /// The `Session` operation that failed.
+    pub fn operation(&self) -> Option<Operation> {
+        self.context.as_ref().map(|c| c.operation)
+    }
+
+    /// The device the operation targeted.
+    pub fn device_id(&self) -> Option<&DeviceId> {
+        self.context.as_ref()?.device_id.as_ref()
+    }
+
+    /// The property the operation targeted.
+    pub fn property(&self) -> Option<PropertyId> {
+        self.context.as_ref()?.property
+    }
+
+    /// The raw `CorsairError` code, if the failure came from the SDK.
+    pub fn code(&self) -> Option<u32> {
+        self.context.as_ref()?.code
+    }
+
+    /// The session state at the time of the failure.
+    pub fn session_state(&self) -> Option<SessionState> {
+        self.context.as_ref().map(|c| c.session_state)
+    }
+
+    /// More information about the failure, e.g. the paths searched for the
+    /// SDK library.
+    pub fn detail(&self) -> Option<&str> {
+        self.context.as_ref()?.detail.as_deref()
+    }
+}
+
+impl Error {
+    /// Add to the explanation of the failure, e.g. the step of a larger
+    /// operation that failed.
+    pub(crate) fn with_detail(mut self, detail: String) -> Self {
+        if let Some(context) = &mut self.context {
+            context.detail = Some(match context.detail.take() {
+                Some(existing) => format!("{existing}; {detail}"),
+                None => detail,
+            });
+        }
+        self
+    }
+}
+
+impl From<SdkError> for Error {
+    fn from(kind: SdkError) -> Self {
+        Self {
+            kind,
+            context: None,
+        }
+    }
+}
+
+impl PartialEq<SdkError> for Error {
+    fn eq(&self, other: &SdkError) -> bool {
+        self.kind == *other
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(ctx) = &self.context else {
+            return write!(f, "{}", self.kind);
+        };
+        write!(f, "{} failed: {}", ctx.operation, self.kind)?;
+        if let Some(detail) = &ctx.detail {
+            write!(f, ": {detail}")?;
+        }
+        if let Some(device_id) = &ctx.device_id {
+            write!(f, " (device {device_id}")?;
+        } else {
+            f.write_str(" (")?;
+        }
+        if let Some(property) = ctx.property {
+            if ctx.device_id.is_some() {
+                f.write_str(", ")?;
+            }
+            write!(f, "property {property:?}")?;
+        }
+        if ctx.device_id.is_some() || ctx.property.is_some() {
+            f.write_str(", ")?;
+        }
+        if let Some(code) = ctx.code {
+            write!(f, "code {code}, ")?;
+        }
+        write!(f, "session {:?})", ctx.session_state)
+    }
+}
+
+impl std::error::Error for Error {}
+
 /// Convenience alias used throughout this crate.
-pub type Result<T> = std::result::Result<T, SdkError>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+// ---------------------------------------------------------------------------
+// ErrorContext
+// ---------------------------------------------------------------------------
+
+/// Describes a `Session` call so failures can be turned into an [`Error`].
+#[derive(Clone, Copy)]
+pub(crate) struct ErrorContext<'a> {
+    operation: Operation,
+    device_id: Option<&'a DeviceId>,
+    property: Option<PropertyId>,
+}
+
+impl<'a> ErrorContext<'a> {
+    pub(crate) fn new(operation: Operation) -> Self {
+        Self {
+            operation,
+            device_id: None,
+            property: None,
+        }
+    }
+
+    pub(crate) fn device(mut self, device_id: &'a DeviceId) -> Self {
+        self.device_id = Some(device_id);
+        self
+    }
+
+    pub(crate) fn property(mut self, property: PropertyId) -> Self {
+        self.property = Some(property);
+        self
+    }
+
+    /// Convert a raw `CorsairError` code into a `Result<()>`.
+    pub(crate) fn check(&self, code: ffi::CorsairError) -> Result<()> {
+        trace::event!(trace, operation = %self.operation, code, "SDK call returned");
+        check(code).map_err(|kind| self.build(kind, Some(code)))
+    }
+
+    /// Build an error that did not come from an SDK return code.
+    pub(crate) fn error(&self, kind: SdkError) -> Error {
+        self.build(kind, None)
+    }
+
+    /// Build an error with a human-readable explanation.
+    pub(crate) fn error_with_detail(&self, kind: SdkError, detail: String) -> Error {
+        let mut error = self.build(kind, None);
+        if let Some(context) = &mut error.context {
+            context.detail = Some(detail);
+        }
+        error
+    }
+
+    /// Build a [`SdkError::MalformedResponse`] for data the SDK returned.
+    pub(crate) fn malformed(&self, what: Malformed) -> Error {
+        trace::event!(warn, operation = %self.operation, error = %what, "malformed SDK response");
+        self.error(SdkError::MalformedResponse(what))
+    }
+
+    fn build(&self, kind: SdkError, code: Option<ffi::CorsairError>) -> Error {
+        Error {
+            kind,
+            context: Some(Box::new(Context {
+                operation: self.operation,
+                device_id: self.device_id.copied(),
+                property: self.property,
+                code,
+                session_state: SessionState::from_ffi(callback::current_session_state()),
+                detail: None,
+            })),
+        }
+    }
+}
 
-/// Convert a raw `CorsairError` code into a `Result<()>`.
-pub(crate) fn check(code: ffi::CorsairError) -> Result<()> {
+/// Convert a raw `CorsairError` code into an error kind.
+pub(crate) fn check(code: ffi::CorsairError) -> std::result::Result<(), SdkError> {
     match code {
         ffi::CorsairError_CE_Success => Ok(()),
         ffi::CorsairError_CE_NotConnected => Err(SdkError::NotConnected),
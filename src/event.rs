@@ -4,64 +4,149 @@ use cue_sdk_sys as ffi;
 
 use crate::callback;
 use crate::device::DeviceId;
-use crate::error::{self, Result};
+use crate::error::{ErrorContext, Malformed, Operation, Result};
+use crate::sdk;
 
 // ---------------------------------------------------------------------------
 // MacroKeyId
 // ---------------------------------------------------------------------------
 
 /// Identifier for a G/M/S macro key.
+///
+/// Key ids this crate does not know about are reported as
+/// [`Unknown`](Self::Unknown) with the raw SDK value.  `Unknown` never holds
+/// the id of a named variant, so keys from
+/// [`from_number`](Self::from_number) compare equal to the named variant.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u32)]
+#[non_exhaustive]
 pub enum MacroKeyId {
-    Key1 = ffi::CorsairMacroKeyId_CMKI_1,
-    Key2 = ffi::CorsairMacroKeyId_CMKI_2,
-    Key3 = ffi::CorsairMacroKeyId_CMKI_3,
-    Key4 = ffi::CorsairMacroKeyId_CMKI_4,
-    Key5 = ffi::CorsairMacroKeyId_CMKI_5,
-    Key6 = ffi::CorsairMacroKeyId_CMKI_6,
-    Key7 = ffi::CorsairMacroKeyId_CMKI_7,
-    Key8 = ffi::CorsairMacroKeyId_CMKI_8,
-    Key9 = ffi::CorsairMacroKeyId_CMKI_9,
-    Key10 = ffi::CorsairMacroKeyId_CMKI_10,
-    Key11 = ffi::CorsairMacroKeyId_CMKI_11,
-    Key12 = ffi::CorsairMacroKeyId_CMKI_12,
-    Key13 = ffi::CorsairMacroKeyId_CMKI_13,
-    Key14 = ffi::CorsairMacroKeyId_CMKI_14,
-    Key15 = ffi::CorsairMacroKeyId_CMKI_15,
-    Key16 = ffi::CorsairMacroKeyId_CMKI_16,
-    Key17 = ffi::CorsairMacroKeyId_CMKI_17,
-    Key18 = ffi::CorsairMacroKeyId_CMKI_18,
-    Key19 = ffi::CorsairMacroKeyId_CMKI_19,
-    Key20 = ffi::CorsairMacroKeyId_CMKI_20,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key10,
+    Key11,
+    Key12,
+    Key13,
+    Key14,
+    Key15,
+    Key16,
+    Key17,
+    Key18,
+    Key19,
+    Key20,
+    /// A key id not known to this crate.
+    Unknown(UnknownMacroKey),
 }
 
 impl MacroKeyId {
-    pub(crate) fn from_ffi(raw: ffi::CorsairMacroKeyId) -> Option<Self> {
+    /// Every macro key known to this crate.
+    pub const ALL: [MacroKeyId; 20] = [
+        Self::Key1,
+        Self::Key2,
+        Self::Key3,
+        Self::Key4,
+        Self::Key5,
+        Self::Key6,
+        Self::Key7,
+        Self::Key8,
+        Self::Key9,
+        Self::Key10,
+        Self::Key11,
+        Self::Key12,
+        Self::Key13,
+        Self::Key14,
+        Self::Key15,
+        Self::Key16,
+        Self::Key17,
+        Self::Key18,
+        Self::Key19,
+        Self::Key20,
+    ];
+
+    /// The 1-based key number, e.g. `5` for `Key5`, or the raw id of an
+    /// unknown key.
+    pub fn number(self) -> u32 {
+        self.to_ffi()
+    }
+
+    /// The key with this 1-based number or raw id: the named variant if
+    /// there is one, otherwise [`Unknown`](Self::Unknown).
+    pub fn from_number(number: u32) -> Self {
+        Self::from_ffi(number)
+    }
+
+    pub(crate) fn from_ffi(raw: ffi::CorsairMacroKeyId) -> Self {
         match raw {
-            ffi::CorsairMacroKeyId_CMKI_1 => Some(Self::Key1),
-            ffi::CorsairMacroKeyId_CMKI_2 => Some(Self::Key2),
-            ffi::CorsairMacroKeyId_CMKI_3 => Some(Self::Key3),
-            ffi::CorsairMacroKeyId_CMKI_4 => Some(Self::Key4),
-            ffi::CorsairMacroKeyId_CMKI_5 => Some(Self::Key5),
-            ffi::CorsairMacroKeyId_CMKI_6 => Some(Self::Key6),
-            ffi::CorsairMacroKeyId_CMKI_7 => Some(Self::Key7),
-            ffi::CorsairMacroKeyId_CMKI_8 => Some(Self::Key8),
-            ffi::CorsairMacroKeyId_CMKI_9 => Some(Self::Key9),
-            ffi::CorsairMacroKeyId_CMKI_10 => Some(Self::Key10),
-            ffi::CorsairMacroKeyId_CMKI_11 => Some(Self::Key11),
-            ffi::CorsairMacroKeyId_CMKI_12 => Some(Self::Key12),
-            ffi::CorsairMacroKeyId_CMKI_13 => Some(Self::Key13),
-            ffi::CorsairMacroKeyId_CMKI_14 => Some(Self::Key14),
-            ffi::CorsairMacroKeyId_CMKI_15 => Some(Self::Key15),
-            ffi::CorsairMacroKeyId_CMKI_16 => Some(Self::Key16),
-            ffi::CorsairMacroKeyId_CMKI_17 => Some(Self::Key17),
-            ffi::CorsairMacroKeyId_CMKI_18 => Some(Self::Key18),
-            ffi::CorsairMacroKeyId_CMKI_19 => Some(Self::Key19),
-            ffi::CorsairMacroKeyId_CMKI_20 => Some(Self::Key20),
-            _ => None,
+            ffi::CorsairMacroKeyId_CMKI_1 => Self::Key1,
+            ffi::CorsairMacroKeyId_CMKI_2 => Self::Key2,
+            ffi::CorsairMacroKeyId_CMKI_3 => Self::Key3,
+            ffi::CorsairMacroKeyId_CMKI_4 => Self::Key4,
+            ffi::CorsairMacroKeyId_CMKI_5 => Self::Key5,
+            ffi::CorsairMacroKeyId_CMKI_6 => Self::Key6,
+            ffi::CorsairMacroKeyId_CMKI_7 => Self::Key7,
+            ffi::CorsairMacroKeyId_CMKI_8 => Self::Key8,
+            ffi::CorsairMacroKeyId_CMKI_9 => Self::Key9,
+            ffi::CorsairMacroKeyId_CMKI_10 => Self::Key10,
+            ffi::CorsairMacroKeyId_CMKI_11 => Self::Key11,
+            ffi::CorsairMacroKeyId_CMKI_12 => Self::Key12,
+            ffi::CorsairMacroKeyId_CMKI_13 => Self::Key13,
+            ffi::CorsairMacroKeyId_CMKI_14 => Self::Key14,
+            ffi::CorsairMacroKeyId_CMKI_15 => Self::Key15,
+            ffi::CorsairMacroKeyId_CMKI_16 => Self::Key16,
+            ffi::CorsairMacroKeyId_CMKI_17 => Self::Key17,
+            ffi::CorsairMacroKeyId_CMKI_18 => Self::Key18,
+            ffi::CorsairMacroKeyId_CMKI_19 => Self::Key19,
+            ffi::CorsairMacroKeyId_CMKI_20 => Self::Key20,
+            other => Self::Unknown(UnknownMacroKey(other)),
         }
     }
+
+    pub(crate) fn to_ffi(self) -> ffi::CorsairMacroKeyId {
+        match self {
+            Self::Key1 => ffi::CorsairMacroKeyId_CMKI_1,
+            Self::Key2 => ffi::CorsairMacroKeyId_CMKI_2,
+            Self::Key3 => ffi::CorsairMacroKeyId_CMKI_3,
+            Self::Key4 => ffi::CorsairMacroKeyId_CMKI_4,
+            Self::Key5 => ffi::CorsairMacroKeyId_CMKI_5,
+            Self::Key6 => ffi::CorsairMacroKeyId_CMKI_6,
+            Self::Key7 => ffi::CorsairMacroKeyId_CMKI_7,
+            Self::Key8 => ffi::CorsairMacroKeyId_CMKI_8,
+            Self::Key9 => ffi::CorsairMacroKeyId_CMKI_9,
+            Self::Key10 => ffi::CorsairMacroKeyId_CMKI_10,
+            Self::Key11 => ffi::CorsairMacroKeyId_CMKI_11,
+            Self::Key12 => ffi::CorsairMacroKeyId_CMKI_12,
+            Self::Key13 => ffi::CorsairMacroKeyId_CMKI_13,
+            Self::Key14 => ffi::CorsairMacroKeyId_CMKI_14,
+            Self::Key15 => ffi::CorsairMacroKeyId_CMKI_15,
+            Self::Key16 => ffi::CorsairMacroKeyId_CMKI_16,
+            Self::Key17 => ffi::CorsairMacroKeyId_CMKI_17,
+            Self::Key18 => ffi::CorsairMacroKeyId_CMKI_18,
+            Self::Key19 => ffi::CorsairMacroKeyId_CMKI_19,
+            Self::Key20 => ffi::CorsairMacroKeyId_CMKI_20,
+            Self::Unknown(raw) => raw.0,
+        }
+    }
+}
+
+/// The raw id of a macro key not known to this crate.
+///
+/// Only created for ids that are not one of the named [`MacroKeyId`]
+/// variants; use [`MacroKeyId::from_number`] to build a key id from a raw
+/// value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnknownMacroKey(u32);
+
+impl UnknownMacroKey {
+    /// The raw SDK id.
+    pub fn get(self) -> u32 {
+        self.0
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -70,6 +155,7 @@ impl MacroKeyId {
 
 /// An event received from the iCUE SDK.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Event {
     /// A device was connected or disconnected.
     DeviceConnectionChanged {
@@ -82,34 +168,45 @@ pub enum Event {
         key_id: MacroKeyId,
         is_pressed: bool,
     },
+    /// An event id not known to this crate.  Its payload is not read.
+    Unknown(u32),
 }
 
 impl Event {
-    /// Parse a raw FFI event.  Returns `None` for unrecognised event IDs.
-    pub(crate) fn from_ffi(raw: &ffi::CorsairEvent) -> Option<Self> {
+    /// Parse a raw FFI event.  Unrecognised event ids become
+    /// [`Event::Unknown`].
+    pub(crate) fn from_ffi(raw: &ffi::CorsairEvent) -> std::result::Result<Self, Malformed> {
         match raw.id {
             ffi::CorsairEventId_CEI_DeviceConnectionStatusChangedEvent => {
                 // SAFETY: The event `id` field is `CEI_DeviceConnectionStatusChangedEvent`,
-                // so the `deviceConnectionStatusChangedEvent` union variant is active
-                // and the pointer is valid for the callback's lifetime.
-                let inner = unsafe { &*raw.event_union.deviceConnectionStatusChangedEvent };
-                Some(Event::DeviceConnectionChanged {
-                    device_id: DeviceId::from_ffi(inner.deviceId),
+                // so the `deviceConnectionStatusChangedEvent` union variant is active.
+                let ptr = unsafe { raw.event_union.deviceConnectionStatusChangedEvent };
+                if ptr.is_null() {
+                    return Err(Malformed::NullPointer);
+                }
+                // SAFETY: The pointer is non-null and valid for the callback's lifetime.
+                let inner = unsafe { &*ptr };
+                Ok(Event::DeviceConnectionChanged {
+                    device_id: DeviceId::from_ffi(inner.deviceId)?,
                     is_connected: inner.isConnected,
                 })
             }
             ffi::CorsairEventId_CEI_KeyEvent => {
                 // SAFETY: The event `id` field is `CEI_KeyEvent`, so the `keyEvent`
-                // union variant is active and the pointer is valid.
-                let inner = unsafe { &*raw.event_union.keyEvent };
-                let key_id = MacroKeyId::from_ffi(inner.keyId)?;
-                Some(Event::KeyEvent {
-                    device_id: DeviceId::from_ffi(inner.deviceId),
-                    key_id,
+                // union variant is active.
+                let ptr = unsafe { raw.event_union.keyEvent };
+                if ptr.is_null() {
+                    return Err(Malformed::NullPointer);
+                }
+                // SAFETY: The pointer is non-null and valid for the callback's lifetime.
+                let inner = unsafe { &*ptr };
+                Ok(Event::KeyEvent {
+                    device_id: DeviceId::from_ffi(inner.deviceId)?,
+                    key_id: MacroKeyId::from_ffi(inner.keyId),
                     is_pressed: inner.isPressed,
                 })
             }
-            _ => None,
+            other => Ok(Event::Unknown(other)),
         }
     }
 }
@@ -135,8 +232,8 @@ impl EventSubscription {
         let ctx = callback::sender_as_context(&sender);
         // SAFETY: We pass a valid function pointer and a context pointer derived
         // from a pinned boxed sender that we keep alive in the returned struct.
-        error::check(unsafe {
-            ffi::CorsairSubscribeForEvents(Some(callback::event_trampoline), ctx)
+        ErrorContext::new(Operation::SubscribeForEvents).check(unsafe {
+            sdk::CorsairSubscribeForEvents(Some(callback::event_trampoline), ctx)
         })?;
         Ok(Self {
             rx,
@@ -166,7 +263,7 @@ impl Drop for EventSubscription {
         // and will stop the SDK from invoking the callback, after which the
         // pinned sender can be safely dropped.
         unsafe {
-            let _ = ffi::CorsairUnsubscribeFromEvents();
+            let _ = sdk::CorsairUnsubscribeFromEvents();
         }
     }
 }
@@ -201,8 +298,8 @@ impl AsyncEventSubscription {
         // SAFETY: We pass a valid function pointer and a context pointer
         // derived from a pinned boxed sender that we keep alive in the
         // returned struct.
-        error::check(unsafe {
-            ffi::CorsairSubscribeForEvents(Some(callback::async_event_trampoline), ctx)
+        ErrorContext::new(Operation::SubscribeForEvents).check(unsafe {
+            sdk::CorsairSubscribeForEvents(Some(callback::async_event_trampoline), ctx)
         })?;
         Ok(Self {
             rx,
@@ -226,7 +323,7 @@ impl Drop for AsyncEventSubscription {
         // and will stop the SDK from invoking the callback, after which the
         // pinned sender can be safely dropped.
         unsafe {
-            let _ = ffi::CorsairUnsubscribeFromEvents();
+            let _ = sdk::CorsairUnsubscribeFromEvents();
         }
     }
 }
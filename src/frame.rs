@@ -0,0 +1,251 @@
+//! Dense per-device LED color buffers with dirty tracking.
+//!
+//! An [`LedFrame`] holds one [`LedColor`] per LED of a device, in the order
+//! reported by [`Session::get_led_positions`].  LUIDs are mapped to dense
+//! indices once, so updating a single LED is a hash lookup instead of a scan,
+//! and only LEDs whose color actually changed are sent to the SDK.
+//!
+//! Because [`LedColor`] is `#[repr(C)]`, changed LEDs are handed to the SDK as
+//! sub-slices of the frame's own storage; nothing is copied or allocated per
+//! frame.
+
+use std::collections::HashMap;
+
+use crate::device::DeviceId;
+use crate::error::Result;
+use crate::led::{LedColor, LedPosition, Rgba};
+use crate::session::Session;
+use crate::zone::Zone;
+
+/// If a frame has more separate runs of dirty LEDs than this, the whole span
+/// from the first to the last dirty LED is sent in one call instead.
+const MAX_DIRTY_RUNS: usize = 8;
+
+/// A dense color buffer for every LED on one device.
+#[derive(Debug, Clone)]
+pub struct LedFrame {
+    device_id: DeviceId,
+    colors: Vec<LedColor>,
+    index: HashMap<u32, usize>,
+    dirty: Vec<bool>,
+    dirty_count: usize,
+    // Scratch list of dirty `(start, end)` index runs, reused between sends.
+    runs: Vec<(usize, usize)>,
+}
+
+impl LedFrame {
+    /// Create a frame for the given LEDs, all set to [`Rgba::BLACK`].
+    ///
+    /// Every LED starts out dirty so the first send establishes the full
+    /// device state.
+    pub fn new(device_id: DeviceId, positions: &[LedPosition]) -> Self {
+        let colors: Vec<LedColor> = positions
+            .iter()
+            .map(|pos| LedColor::with_color(pos.id, Rgba::BLACK))
+            .collect();
+        let index = colors
+            .iter()
+            .enumerate()
+            .map(|(i, led)| (led.id, i))
+            .collect();
+        let len = colors.len();
+        Self {
+            device_id,
+            colors,
+            index,
+            dirty: vec![true; len],
+            dirty_count: len,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Create a frame for every LED of a device, querying its LED positions
+    /// from the SDK.
+    pub fn for_device(session: &Session, device_id: &DeviceId) -> Result<Self> {
+        let positions = session.get_led_positions(device_id)?;
+        Ok(Self::new(*device_id, &positions))
+    }
+
+    /// The device this frame belongs to.
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    /// Number of LEDs in the frame.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Whether the frame has no LEDs.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Whether the LUID belongs to this frame's device.
+    pub fn contains(&self, luid: u32) -> bool {
+        self.index.contains_key(&luid)
+    }
+
+    /// The dense index of a LUID, if it belongs to this frame's device.
+    pub fn index_of(&self, luid: u32) -> Option<usize> {
+        self.index.get(&luid).copied()
+    }
+
+    /// All LED colors in dense order.
+    pub fn colors(&self) -> &[LedColor] {
+        &self.colors
+    }
+
+    /// Iterate over the LUIDs in dense order.
+    pub fn luids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.colors.iter().map(|led| led.id)
+    }
+
+    /// The current color of an LED.
+    pub fn get(&self, luid: u32) -> Option<Rgba> {
+        self.index_of(luid).map(|i| self.colors[i].color())
+    }
+
+    /// Set the color of an LED.
+    ///
+    /// Returns `false` if the LUID does not belong to this frame's device.
+    /// The LED is only marked dirty if its color changes.
+    pub fn set(&mut self, luid: u32, color: Rgba) -> bool {
+        match self.index_of(luid) {
+            Some(i) => {
+                self.set_index(i, color);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the color of the LED at a dense index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn set_index(&mut self, index: usize, color: Rgba) {
+        let led = &mut self.colors[index];
+        if led.color() == color {
+            return;
+        }
+        *led = LedColor::with_color(led.id, color);
+        if !self.dirty[index] {
+            self.dirty[index] = true;
+            self.dirty_count += 1;
+        }
+    }
+
+    /// Set every LED to the same color.
+    pub fn fill(&mut self, color: Rgba) {
+        for i in 0..self.colors.len() {
+            self.set_index(i, color);
+        }
+    }
+
+    /// Set the given LEDs to the same color, skipping LUIDs that do not
+    /// belong to this device.
+    pub fn fill_leds(&mut self, luids: impl IntoIterator<Item = u32>, color: Rgba) {
+        for luid in luids {
+            self.set(luid, color);
+        }
+    }
+
+    /// Set every LED of this device that lies in `zone` to the same color.
+    pub fn fill_zone(&mut self, zone: &Zone, color: Rgba) {
+        for i in 0..self.colors.len() {
+            if zone.contains(self.colors[i].id) {
+                self.set_index(i, color);
+            }
+        }
+    }
+
+    // ---- Dirty tracking ---------------------------------------------------
+
+    /// Whether any LED changed since the last send.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_count > 0
+    }
+
+    /// Number of LEDs changed since the last send.
+    pub fn dirty_count(&self) -> usize {
+        self.dirty_count
+    }
+
+    /// Mark every LED dirty so the next send transmits the whole frame.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty.fill(true);
+        self.dirty_count = self.dirty.len();
+    }
+
+    /// Forget all pending changes without sending them.
+    pub fn clear_dirty(&mut self) {
+        self.dirty.fill(false);
+        self.dirty_count = 0;
+    }
+
+    /// The slices of LED storage that need to be sent.
+    ///
+    /// Each contiguous run of dirty LEDs is returned as its own slice.  If
+    /// there are many scattered runs, a single slice spanning all of them is
+    /// returned instead, trading a few unchanged LEDs for fewer SDK calls.
+    pub fn dirty_slices(&mut self) -> impl Iterator<Item = &[LedColor]> + '_ {
+        self.update_runs();
+        self.runs.iter().map(|&(s, e)| &self.colors[s..e])
+    }
+
+    fn update_runs(&mut self) {
+        self.runs.clear();
+        let mut start = None;
+        for (i, &dirty) in self.dirty.iter().chain(std::iter::once(&false)).enumerate() {
+            match (dirty, start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    self.runs.push((s, i));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if self.runs.len() > MAX_DIRTY_RUNS {
+            let (first, _) = self.runs[0];
+            let (_, last) = self.runs[self.runs.len() - 1];
+            self.runs.clear();
+            self.runs.push((first, last));
+        }
+    }
+
+    // ---- Sending ----------------------------------------------------------
+
+    /// Buffer the changed LEDs with [`Session::set_led_colors_buffer`].
+    ///
+    /// Call [`Session::flush_led_colors`] afterwards to apply them.  Returns
+    /// the number of LEDs sent; on success the frame is clean.
+    pub fn buffer_changes(&mut self, session: &Session) -> Result<usize> {
+        let id = self.device_id;
+        self.send_with(|colors| session.set_led_colors_buffer(&id, colors))
+    }
+
+    /// Apply the changed LEDs immediately with [`Session::set_led_colors`].
+    ///
+    /// Returns the number of LEDs sent; on success the frame is clean.
+    pub fn apply_changes(&mut self, session: &Session) -> Result<usize> {
+        let id = self.device_id;
+        self.send_with(|colors| session.set_led_colors(&id, colors))
+    }
+
+    fn send_with(&mut self, send: impl Fn(&[LedColor]) -> Result<()>) -> Result<usize> {
+        if !self.is_dirty() {
+            return Ok(0);
+        }
+        self.update_runs();
+        let mut sent = 0;
+        for &(start, end) in &self.runs {
+            send(&self.colors[start..end])?;
+            sent += end - start;
+        }
+        self.clear_dirty();
+        Ok(sent)
+    }
+}
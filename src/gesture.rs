@@ -0,0 +1,517 @@
+//! Recognising taps, long presses, chords and sequences on macro keys.
+//!
+//! iCUE reports only raw press and release edges for macro keys.  A
+//! [`GestureRecognizer`] turns them into [`Gesture`]s: taps, double taps,
+//! long presses, repeats while a key is held, chords of several keys pressed
+//! together and ordered sequences.  Timing thresholds, chords and sequences
+//! are set per device with a [`GestureConfig`].
+//!
+//! Some gestures are only known after time passes without an event, e.g. a
+//! tap is reported once the double-tap window has closed.  Call
+//! [`poll`](GestureRecognizer::poll) regularly, or wait until
+//! [`next_deadline`](GestureRecognizer::next_deadline).
+//!
+//! The recognizer reads time from a [`Clock`].  Tests can use a
+//! [`ManualClock`] to step time deterministically:
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use cue_sdk::{Event, MacroKeyId};
+//! # use cue_sdk::gesture::{Gesture, GestureRecognizer, ManualClock};
+//! # let device_id = "device".parse().unwrap();
+//! let clock = ManualClock::new();
+//! let mut gestures = GestureRecognizer::with_clock(clock.clone());
+//! let edge = |is_pressed| Event::KeyEvent { device_id, key_id: MacroKeyId::Key1, is_pressed };
+//!
+//! gestures.handle_event(&edge(true));
+//! clock.advance(Duration::from_secs(1));
+//! let events = gestures.poll();
+//! assert_eq!(events[0].gesture, Gesture::LongPress(MacroKeyId::Key1));
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::device::DeviceId;
+use crate::event::{Event, MacroKeyId};
+
+// ---------------------------------------------------------------------------
+// Clock
+// ---------------------------------------------------------------------------
+
+/// A source of the current time.
+pub trait Clock {
+    /// The current time.
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    /// Create a clock stopped at the current time.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Gesture
+// ---------------------------------------------------------------------------
+
+/// A gesture made with macro keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Gesture {
+    /// A short press that was not followed by a second one.
+    Tap(MacroKeyId),
+    /// Two short presses of the same key in quick succession.
+    DoubleTap(MacroKeyId),
+    /// A key held for at least the long-press threshold.  Reported while
+    /// the key is still held.
+    LongPress(MacroKeyId),
+    /// Repeated while a key is held, if hold-repeat is enabled.  `count`
+    /// starts at 1.
+    HoldRepeat { key: MacroKeyId, count: u32 },
+    /// All keys of a configured chord pressed together, in the configured
+    /// order.
+    Chord(Vec<MacroKeyId>),
+    /// A configured sequence of key presses.
+    Sequence(Vec<MacroKeyId>),
+}
+
+/// A gesture together with the device it was made on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GestureEvent {
+    pub device_id: DeviceId,
+    pub gesture: Gesture,
+}
+
+// ---------------------------------------------------------------------------
+// GestureConfig
+// ---------------------------------------------------------------------------
+
+/// Thresholds, chords and sequences for one device.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use cue_sdk::MacroKeyId::*;
+/// # use cue_sdk::gesture::GestureConfig;
+/// let config = GestureConfig::default()
+///     .long_press(Duration::from_millis(800))
+///     .hold_repeat(Duration::from_millis(500), Duration::from_millis(100))
+///     .chord(&[Key1, Key2])
+///     .sequence(&[Key1, Key1, Key3]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GestureConfig {
+    tap: Duration,
+    double_tap: Duration,
+    long_press: Duration,
+    repeat: Option<(Duration, Duration)>,
+    chord_window: Duration,
+    sequence_timeout: Duration,
+    chords: Vec<Vec<MacroKeyId>>,
+    sequences: Vec<Vec<MacroKeyId>>,
+}
+
+impl Default for GestureConfig {
+    /// Taps up to 250 ms, double taps within 300 ms, long presses after 1 s,
+    /// chords within 50 ms, sequences with at most 1 s between presses, no
+    /// hold-repeat and no chords or sequences.
+    fn default() -> Self {
+        Self {
+            tap: Duration::from_millis(250),
+            double_tap: Duration::from_millis(300),
+            long_press: Duration::from_secs(1),
+            repeat: None,
+            chord_window: Duration::from_millis(50),
+            sequence_timeout: Duration::from_secs(1),
+            chords: Vec::new(),
+            sequences: Vec::new(),
+        }
+    }
+}
+
+impl GestureConfig {
+    /// The longest press that still counts as a tap.
+    pub fn tap(mut self, max: Duration) -> Self {
+        self.tap = max;
+        self
+    }
+
+    /// The longest time between the release of one tap and the release of
+    /// the next for them to form a double tap.
+    ///
+    /// A zero window disables double taps and reports taps immediately.
+    pub fn double_tap(mut self, window: Duration) -> Self {
+        self.double_tap = window;
+        self
+    }
+
+    /// How long a key must be held to report a long press.
+    pub fn long_press(mut self, threshold: Duration) -> Self {
+        self.long_press = threshold;
+        self
+    }
+
+    /// Report [`Gesture::HoldRepeat`] `delay` after a key is pressed, then
+    /// every `interval` while it stays held.
+    pub fn hold_repeat(mut self, delay: Duration, interval: Duration) -> Self {
+        self.repeat = Some((delay, interval.max(Duration::from_millis(1))));
+        self
+    }
+
+    /// The longest time between the first and last key press of a chord.
+    pub fn chord_window(mut self, window: Duration) -> Self {
+        self.chord_window = window;
+        self
+    }
+
+    /// The longest gap between two presses of a sequence.
+    pub fn sequence_timeout(mut self, timeout: Duration) -> Self {
+        self.sequence_timeout = timeout;
+        self
+    }
+
+    /// Recognise these keys pressed together as a chord.
+    ///
+    /// Keys that complete a chord do not report taps or long presses until
+    /// they are released.
+    pub fn chord(mut self, keys: &[MacroKeyId]) -> Self {
+        if !keys.is_empty() {
+            self.chords.push(keys.to_vec());
+        }
+        self
+    }
+
+    /// Recognise these keys pressed one after another as a sequence.
+    ///
+    /// The individual presses are still reported as taps.
+    pub fn sequence(mut self, keys: &[MacroKeyId]) -> Self {
+        if !keys.is_empty() {
+            self.sequences.push(keys.to_vec());
+        }
+        self
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Per-device state
+// ---------------------------------------------------------------------------
+
+/// A key that is currently held.
+#[derive(Debug, Clone, Copy)]
+struct Held {
+    pressed_at: Instant,
+    /// Part of a completed chord; reports nothing else until released.
+    consumed: bool,
+    long_pressed: bool,
+    repeats: u32,
+}
+
+#[derive(Debug, Default)]
+struct DeviceState {
+    held: HashMap<MacroKeyId, Held>,
+    /// Release time of taps waiting to see whether a second tap follows.
+    pending_taps: HashMap<MacroKeyId, Instant>,
+    /// Recent presses for sequence matching.
+    history: Vec<(MacroKeyId, Instant)>,
+}
+
+impl DeviceState {
+    fn press(&mut self, key: MacroKeyId, now: Instant, config: &GestureConfig) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+        self.held.insert(
+            key,
+            Held {
+                pressed_at: now,
+                consumed: false,
+                long_pressed: false,
+                repeats: 0,
+            },
+        );
+
+        for chord in config.chords.iter().filter(|chord| chord.contains(&key)) {
+            let Some(keys) = chord
+                .iter()
+                .map(|k| self.held.get(k).filter(|h| !h.consumed))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let first = keys.iter().map(|h| h.pressed_at).min().unwrap_or(now);
+            if now.saturating_duration_since(first) > config.chord_window {
+                continue;
+            }
+            for k in chord {
+                if let Some(held) = self.held.get_mut(k) {
+                    held.consumed = true;
+                }
+                self.pending_taps.remove(k);
+            }
+            gestures.push(Gesture::Chord(chord.clone()));
+            break;
+        }
+
+        if self
+            .history
+            .last()
+            .is_some_and(|&(_, at)| now.saturating_duration_since(at) > config.sequence_timeout)
+        {
+            self.history.clear();
+        }
+        self.history.push((key, now));
+        let matched = config.sequences.iter().find(|sequence| {
+            self.history.len() >= sequence.len()
+                && self.history[self.history.len() - sequence.len()..]
+                    .iter()
+                    .map(|&(k, _)| k)
+                    .eq(sequence.iter().copied())
+        });
+        if let Some(sequence) = matched {
+            gestures.push(Gesture::Sequence(sequence.clone()));
+            self.history.clear();
+        }
+        let longest = config.sequences.iter().map(Vec::len).max().unwrap_or(0);
+        let excess = self.history.len().saturating_sub(longest);
+        self.history.drain(..excess);
+
+        gestures
+    }
+
+    fn release(&mut self, key: MacroKeyId, now: Instant, config: &GestureConfig) -> Vec<Gesture> {
+        let Some(held) = self.held.remove(&key) else {
+            return Vec::new();
+        };
+        if held.consumed
+            || held.long_pressed
+            || now.saturating_duration_since(held.pressed_at) > config.tap
+        {
+            return Vec::new();
+        }
+        if self.pending_taps.remove(&key).is_some() {
+            return vec![Gesture::DoubleTap(key)];
+        }
+        if config.double_tap.is_zero() {
+            return vec![Gesture::Tap(key)];
+        }
+        self.pending_taps.insert(key, now);
+        Vec::new()
+    }
+
+    fn poll(&mut self, now: Instant, config: &GestureConfig) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+
+        let mut expired: Vec<MacroKeyId> = self
+            .pending_taps
+            .iter()
+            .filter(|&(_, &at)| now.saturating_duration_since(at) > config.double_tap)
+            .map(|(&key, _)| key)
+            .collect();
+        expired.sort_by_key(|key| self.pending_taps[key]);
+        for key in expired {
+            self.pending_taps.remove(&key);
+            gestures.push(Gesture::Tap(key));
+        }
+
+        let mut held: Vec<(&MacroKeyId, &mut Held)> = self.held.iter_mut().collect();
+        held.sort_by_key(|(_, h)| h.pressed_at);
+        for (&key, held) in held.into_iter().filter(|(_, h)| !h.consumed) {
+            let elapsed = now.saturating_duration_since(held.pressed_at);
+            if !held.long_pressed && elapsed >= config.long_press {
+                held.long_pressed = true;
+                gestures.push(Gesture::LongPress(key));
+            }
+            if let Some((delay, interval)) = config.repeat {
+                while elapsed >= delay + interval * held.repeats {
+                    held.repeats += 1;
+                    gestures.push(Gesture::HoldRepeat {
+                        key,
+                        count: held.repeats,
+                    });
+                }
+            }
+        }
+
+        gestures
+    }
+
+    fn next_deadline(&self, config: &GestureConfig) -> Option<Instant> {
+        let taps = self
+            .pending_taps
+            .values()
+            .map(|&at| at + config.double_tap + Duration::from_nanos(1));
+        let held = self.held.values().filter(|h| !h.consumed).flat_map(|h| {
+            let long = (!h.long_pressed).then_some(h.pressed_at + config.long_press);
+            let repeat = config
+                .repeat
+                .map(|(delay, interval)| h.pressed_at + delay + interval * h.repeats);
+            long.into_iter().chain(repeat)
+        });
+        taps.chain(held).min()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// GestureRecognizer
+// ---------------------------------------------------------------------------
+
+/// Turns macro key events into [`GestureEvent`]s.
+#[derive(Debug)]
+pub struct GestureRecognizer<C: Clock = SystemClock> {
+    clock: C,
+    default_config: GestureConfig,
+    configs: HashMap<DeviceId, GestureConfig>,
+    devices: HashMap<DeviceId, DeviceState>,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GestureRecognizer {
+    /// Create a recognizer using the system clock and the default
+    /// [`GestureConfig`] for every device.
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock> GestureRecognizer<C> {
+    /// Create a recognizer that reads time from `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            clock,
+            default_config: GestureConfig::default(),
+            configs: HashMap::new(),
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Use `config` for devices without a configuration of their own.
+    pub fn set_default_config(&mut self, config: GestureConfig) {
+        self.default_config = config;
+    }
+
+    /// Use `config` for one device.
+    pub fn set_config(&mut self, device_id: &DeviceId, config: GestureConfig) {
+        self.configs.insert(*device_id, config);
+    }
+
+    /// The configuration used for a device.
+    pub fn config(&self, device_id: &DeviceId) -> &GestureConfig {
+        self.configs.get(device_id).unwrap_or(&self.default_config)
+    }
+
+    /// Feed an SDK event to the recognizer.
+    ///
+    /// Returns the gestures completed by the event, preceded by any that
+    /// became due since the last call.  A device disconnecting discards the
+    /// keys it had pressed.
+    pub fn handle_event(&mut self, event: &Event) -> Vec<GestureEvent> {
+        let now = self.clock.now();
+        let mut events = self.poll_at(now);
+        match event {
+            Event::KeyEvent {
+                device_id,
+                key_id,
+                is_pressed,
+            } => {
+                let config = self.configs.get(device_id).unwrap_or(&self.default_config);
+                let state = self.devices.entry(*device_id).or_default();
+                let gestures = if *is_pressed {
+                    state.press(*key_id, now, config)
+                } else {
+                    state.release(*key_id, now, config)
+                };
+                events.extend(gestures.into_iter().map(|gesture| GestureEvent {
+                    device_id: *device_id,
+                    gesture,
+                }));
+            }
+            Event::DeviceConnectionChanged {
+                device_id,
+                is_connected: false,
+            } => {
+                self.devices.remove(device_id);
+            }
+            _ => {}
+        }
+        events
+    }
+
+    /// Report gestures that became due because time passed: taps whose
+    /// double-tap window closed, long presses and hold-repeats.
+    pub fn poll(&mut self) -> Vec<GestureEvent> {
+        let now = self.clock.now();
+        self.poll_at(now)
+    }
+
+    /// When [`poll`](Self::poll) will next report a gesture if no other
+    /// event arrives, or `None` if nothing is pending.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.devices
+            .iter()
+            .filter_map(|(id, state)| state.next_deadline(self.config(id)))
+            .min()
+    }
+
+    /// Forget all pressed keys, pending taps and partial sequences.
+    pub fn reset(&mut self) {
+        self.devices.clear();
+    }
+
+    fn poll_at(&mut self, now: Instant) -> Vec<GestureEvent> {
+        let mut events = Vec::new();
+        for (device_id, state) in &mut self.devices {
+            let config = self.configs.get(device_id).unwrap_or(&self.default_config);
+            events.extend(
+                state
+                    .poll(now, config)
+                    .into_iter()
+                    .map(|gesture| GestureEvent {
+                        device_id: *device_id,
+                        gesture,
+                    }),
+            );
+        }
+        events
+    }
+}
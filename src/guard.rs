@@ -0,0 +1,600 @@
+//! RAII guards for exclusive device control and macro key interception.
+//!
+//! [`ControlGuard`] releases exclusive control of a device when dropped, and
+//! [`KeyInterceptGuard`] stops intercepting a macro key.  Guards for the same
+//! device nest: control is only released when the last guard for a device is
+//! dropped, and dropping an inner guard that requested a different
+//! [`AccessLevel`] restores the level of the guard below it.
+//!
+//! Drop does not run if the process panics with `panic = "abort"` or exits
+//! through [`std::process::exit`].  Call [`install_panic_hook`] and
+//! [`install_exit_handler`] once at start-up to release everything held by
+//! guards in those cases too, or call [`release_all`] from your own shutdown
+//! path.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, Once};
+
+use cue_sdk_sys as ffi;
+
+use crate::device::DeviceId;
+use crate::error::Result;
+use crate::event::MacroKeyId;
+use crate::sdk;
+use crate::session::{AccessLevel, Session, SessionRecord};
+use crate::trace;
+
+// ---------------------------------------------------------------------------
+// Held resources
+// ---------------------------------------------------------------------------
+
+/// A live [`ControlGuard`].
+struct ControlEntry {
+    device_id: DeviceId,
+    id: u64,
+    level: AccessLevel,
+    /// The level the session held before the first guard for the device.
+    base: Option<AccessLevel>,
+    record: SessionRecord,
+}
+
+/// A live [`KeyInterceptGuard`].
+struct InterceptEntry {
+    device_id: DeviceId,
+    key_id: MacroKeyId,
+    id: u64,
+    /// Whether the key was intercepted before the first guard for it.
+    base: bool,
+    record: SessionRecord,
+}
+
+/// The SDK call that brings a device back to the state below a released
+/// control guard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlChange {
+    Request(AccessLevel),
+    Release,
+}
+
+impl ControlChange {
+    fn to(level: Option<AccessLevel>) -> Self {
+        level.map_or(Self::Release, Self::Request)
+    }
+}
+
+/// Everything currently held by live guards, process-wide.
+///
+/// The SDK is never called with this locked.  Instead a device is marked
+/// busy while a guard for it calls the SDK, so calls for one device happen
+/// in the order their guards were acquired or released while other devices
+/// are unaffected.
+struct Held {
+    /// In acquisition order.  The last entry for a device is the level
+    /// currently in effect.
+    control: Vec<ControlEntry>,
+    intercepts: Vec<InterceptEntry>,
+    /// Devices with an SDK call in flight.
+    busy: Vec<DeviceId>,
+}
+
+static HELD: Mutex<Held> = Mutex::new(Held {
+    control: Vec::new(),
+    intercepts: Vec::new(),
+    busy: Vec::new(),
+});
+
+/// Signalled whenever a device stops being busy.
+static IDLE: Condvar = Condvar::new();
+
+static NEXT_GUARD_ID: AtomicU64 = AtomicU64::new(0);
+
+fn held() -> MutexGuard<'static, Held> {
+    // A panic while the lock is held cannot leave `Held` inconsistent: every
+    // update is a single push or remove.
+    HELD.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Lock `HELD` once no SDK call for `device_id` is in flight.
+fn held_idle(device_id: &DeviceId) -> MutexGuard<'static, Held> {
+    let mut held = held();
+    while held.busy.contains(device_id) {
+        held = IDLE.wait(held).unwrap_or_else(|e| e.into_inner());
+    }
+    held
+}
+
+/// Marks a device busy until dropped.
+struct Busy(DeviceId);
+
+impl Busy {
+    fn mark(held: &mut Held, device_id: &DeviceId) -> Self {
+        held.busy.push(*device_id);
+        Self(*device_id)
+    }
+}
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        held().busy.retain(|device| *device != self.0);
+        IDLE.notify_all();
+    }
+}
+
+impl Held {
+    /// The level held for `device_id` before its first live guard, if it
+    /// has one.
+    fn control_base(&self, device_id: &DeviceId) -> Option<Option<AccessLevel>> {
+        self.control
+            .iter()
+            .find(|e| e.device_id == *device_id)
+            .map(|e| e.base)
+    }
+
+    /// Remove control guard `id` and return the call that restores the level
+    /// below it, if it was the one in effect and that level differs.
+    fn remove_control(&mut self, id: u64) -> Option<(DeviceId, ControlChange)> {
+        let pos = self.control.iter().position(|e| e.id == id)?;
+        let entry = self.control.remove(pos);
+        let was_current = !self.control[pos..]
+            .iter()
+            .any(|e| e.device_id == entry.device_id);
+        if !was_current {
+            return None;
+        }
+        let below = self
+            .control
+            .iter()
+            .rev()
+            .find(|e| e.device_id == entry.device_id)
+            .map_or(entry.base, |e| Some(e.level));
+        (below != Some(entry.level)).then(|| (entry.device_id, ControlChange::to(below)))
+    }
+
+    /// Whether `key_id` was intercepted before its first live guard, if it
+    /// has one.
+    fn intercept_base(&self, device_id: &DeviceId, key_id: MacroKeyId) -> Option<bool> {
+        self.intercepts
+            .iter()
+            .find(|e| e.device_id == *device_id && e.key_id == key_id)
+            .map(|e| e.base)
+    }
+
+    /// Remove interception guard `id` and return whether interception of its
+    /// key should stop.
+    fn remove_intercept(&mut self, id: u64) -> bool {
+        let Some(pos) = self.intercepts.iter().position(|e| e.id == id) else {
+            return false;
+        };
+        let entry = self.intercepts.remove(pos);
+        self.intercept_base(&entry.device_id, entry.key_id)
+            .is_none()
+            && !entry.base
+    }
+
+    /// Forget every guard and return the calls that undo them: one per
+    /// device whose level differs from before its first guard, and one per
+    /// key that was not intercepted before its first guard.
+    #[allow(clippy::type_complexity)]
+    fn take_all(
+        &mut self,
+    ) -> (
+        Vec<(DeviceId, ControlChange, SessionRecord)>,
+        Vec<(DeviceId, MacroKeyId, SessionRecord)>,
+    ) {
+        let mut control: Vec<(DeviceId, ControlChange, SessionRecord)> = Vec::new();
+        for entry in self.control.drain(..) {
+            if control
+                .iter()
+                .any(|(device, ..)| *device == entry.device_id)
+            {
+                continue;
+            }
+            control.push((entry.device_id, ControlChange::to(entry.base), entry.record));
+        }
+        let mut intercepts: Vec<(DeviceId, MacroKeyId, SessionRecord)> = Vec::new();
+        for entry in self.intercepts.drain(..) {
+            if entry.base
+                || intercepts
+                    .iter()
+                    .any(|(device, key, _)| *device == entry.device_id && *key == entry.key_id)
+            {
+                continue;
+            }
+            intercepts.push((entry.device_id, entry.key_id, entry.record));
+        }
+        (control, intercepts)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ControlGuard
+// ---------------------------------------------------------------------------
+
+/// Exclusive control of a device, released when dropped.
+///
+/// Created by [`Session::acquire_control`] or
+/// [`Device::acquire_control`](crate::Device::acquire_control).  When the
+/// last guard for a device is dropped, the device returns to the level the
+/// session held before the first one, or is released if it held none.
+#[must_use = "control is released as soon as the guard is dropped"]
+pub struct ControlGuard<'s> {
+    session: &'s Session,
+    device_id: DeviceId,
+    id: u64,
+}
+
+impl<'s> ControlGuard<'s> {
+    pub(crate) fn acquire(
+        session: &'s Session,
+        device_id: &DeviceId,
+        level: AccessLevel,
+    ) -> Result<Self> {
+        let mut state = held_idle(device_id);
+        let base = state
+            .control_base(device_id)
+            .unwrap_or_else(|| session.access_level(device_id));
+        let busy = Busy::mark(&mut state, device_id);
+        drop(state);
+
+        session.request_control(device_id, level)?;
+        let id = NEXT_GUARD_ID.fetch_add(1, Ordering::Relaxed);
+        held().control.push(ControlEntry {
+            device_id: *device_id,
+            id,
+            level,
+            base,
+            record: session.record(),
+        });
+        drop(busy);
+        Ok(Self {
+            session,
+            device_id: *device_id,
+            id,
+        })
+    }
+
+    /// The controlled device.
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    /// Release the guard now, returning any error from the SDK.
+    pub fn release(mut self) -> Result<()> {
+        let result = self.release_inner();
+        std::mem::forget(self);
+        result
+    }
+
+    fn release_inner(&mut self) -> Result<()> {
+        let mut held = held_idle(&self.device_id);
+        // `None` if another guard is still in effect, or if `release_all`
+        // already released this one.
+        let Some((_, change)) = held.remove_control(self.id) else {
+            return Ok(());
+        };
+        let _busy = Busy::mark(&mut held, &self.device_id);
+        drop(held);
+        match change {
+            ControlChange::Request(level) => self.session.request_control(&self.device_id, level),
+            ControlChange::Release => self.session.release_control(&self.device_id),
+        }
+    }
+}
+
+impl Drop for ControlGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(_e) = self.release_inner() {
+            trace::event!(warn, device = %self.device_id, error = %_e, "failed to release control");
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// KeyInterceptGuard
+// ---------------------------------------------------------------------------
+
+/// Interception of a macro key, stopped when dropped.
+///
+/// Created by [`Session::intercept_key`].  While any guard for a key exists,
+/// iCUE does not run its own action for that key.  If the session already
+/// intercepted the key before the first guard, it stays intercepted after
+/// the last one is dropped.
+#[must_use = "interception stops as soon as the guard is dropped"]
+pub struct KeyInterceptGuard<'s> {
+    session: &'s Session,
+    device_id: DeviceId,
+    key_id: MacroKeyId,
+    id: u64,
+}
+
+impl<'s> KeyInterceptGuard<'s> {
+    pub(crate) fn acquire(
+        session: &'s Session,
+        device_id: &DeviceId,
+        key_id: MacroKeyId,
+    ) -> Result<Self> {
+        let mut state = held_idle(device_id);
+        let existing = state.intercept_base(device_id, key_id);
+        let base = existing.unwrap_or_else(|| session.is_key_intercepted(device_id, key_id));
+        let intercepted = existing.is_some() || base;
+        let busy = (!intercepted).then(|| Busy::mark(&mut state, device_id));
+        drop(state);
+
+        if !intercepted {
+            session.configure_key_event(device_id, key_id, true)?;
+        }
+        let id = NEXT_GUARD_ID.fetch_add(1, Ordering::Relaxed);
+        held().intercepts.push(InterceptEntry {
+            device_id: *device_id,
+            key_id,
+            id,
+            base,
+            record: session.record(),
+        });
+        drop(busy);
+        Ok(Self {
+            session,
+            device_id: *device_id,
+            key_id,
+            id,
+        })
+    }
+
+    /// The device the key belongs to.
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    /// The intercepted key.
+    pub fn key_id(&self) -> MacroKeyId {
+        self.key_id
+    }
+
+    /// Release the guard now, returning any error from the SDK.
+    pub fn release(mut self) -> Result<()> {
+        let result = self.release_inner();
+        std::mem::forget(self);
+        result
+    }
+
+    fn release_inner(&mut self) -> Result<()> {
+        let mut held = held_idle(&self.device_id);
+        if !held.remove_intercept(self.id) {
+            return Ok(());
+        }
+        let _busy = Busy::mark(&mut held, &self.device_id);
+        drop(held);
+        self.session
+            .configure_key_event(&self.device_id, self.key_id, false)
+    }
+}
+
+impl Drop for KeyInterceptGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(_e) = self.release_inner() {
+            trace::event!(
+                warn,
+                device = %self.device_id,
+                key = ?self.key_id,
+                error = %_e,
+                "failed to stop key interception"
+            );
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Emergency cleanup
+// ---------------------------------------------------------------------------
+
+/// Undo every device control and key interception held by live guards.
+///
+/// Each device returns to the level its session held before the first guard,
+/// and each key to its interception state before the first guard, as if the
+/// guards had been dropped.  Guards dropped afterwards do nothing.
+///
+/// Safe to call from a panic hook.  It does not wait for SDK calls that other
+/// threads are making for guards; a guard acquired by such a call after this
+/// returns is released normally when dropped.
+pub fn release_all() {
+    // Taken outside any SDK call, so this cannot block for long.
+    let (control, intercepts) = held().take_all();
+    for (device_id, key_id, record) in intercepts {
+        let config = ffi::CorsairKeyEventConfiguration {
+            keyId: key_id.to_ffi(),
+            isIntercepted: false,
+        };
+        // SAFETY: `config` is a valid stack-allocated struct and `device_id`
+        // is a valid null-terminated C string.
+        let code = unsafe { sdk::CorsairConfigureKeyEvent(device_id.as_ptr(), &config) };
+        if code == ffi::CorsairError_CE_Success {
+            record.forget_intercept(&device_id, key_id);
+        }
+    }
+    for (device_id, change, record) in control {
+        let (code, level) = match change {
+            // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
+            ControlChange::Request(level) => (
+                unsafe {
+                    sdk::CorsairRequestControl(device_id.as_ptr(), level as ffi::CorsairAccessLevel)
+                },
+                Some(level),
+            ),
+            // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
+            ControlChange::Release => (
+                unsafe { sdk::CorsairReleaseControl(device_id.as_ptr()) },
+                None,
+            ),
+        };
+        if code == ffi::CorsairError_CE_Success {
+            record.set_control(&device_id, level);
+        }
+    }
+}
+
+/// Call [`release_all`] before the current panic hook runs.
+///
+/// This runs for every panic on any thread, including panics that are later
+/// caught with [`std::panic::catch_unwind`].  Only the first call installs
+/// the hook.
+pub fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            release_all();
+            previous(info);
+        }));
+    });
+}
+
+/// Call [`release_all`] when the process exits normally, including through
+/// [`std::process::exit`].
+///
+/// Only the first call registers the handler.
+pub fn install_exit_handler() {
+    static INSTALLED: Once = Once::new();
+
+    extern "C" {
+        fn atexit(callback: extern "C" fn()) -> core::ffi::c_int;
+    }
+
+    extern "C" fn on_exit() {
+        release_all();
+    }
+
+    INSTALLED.call_once(|| {
+        // SAFETY: `atexit` is provided by the C runtime that std links
+        // against, and `on_exit` is a valid `extern "C"` function that lives
+        // for the whole program.
+        let _ = unsafe { atexit(on_exit) };
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use AccessLevel::{ExclusiveLightingControl as Lighting, Shared};
+    use ControlChange::{Release, Request};
+
+    fn device(name: &str) -> DeviceId {
+        name.parse().unwrap()
+    }
+
+    fn empty() -> Held {
+        Held {
+            control: Vec::new(),
+            intercepts: Vec::new(),
+            busy: Vec::new(),
+        }
+    }
+
+    /// Push a control guard the way `ControlGuard::acquire` does.
+    fn control(
+        held: &mut Held,
+        device_id: DeviceId,
+        level: AccessLevel,
+        session: Option<AccessLevel>,
+    ) -> u64 {
+        let base = held.control_base(&device_id).unwrap_or(session);
+        let id = NEXT_GUARD_ID.fetch_add(1, Ordering::Relaxed);
+        held.control.push(ControlEntry {
+            device_id,
+            id,
+            level,
+            base,
+            record: SessionRecord::default(),
+        });
+        id
+    }
+
+    /// Push an interception guard the way `KeyInterceptGuard::acquire` does.
+    fn intercept(held: &mut Held, device_id: DeviceId, key_id: MacroKeyId, session: bool) -> u64 {
+        let base = held.intercept_base(&device_id, key_id).unwrap_or(session);
+        let id = NEXT_GUARD_ID.fetch_add(1, Ordering::Relaxed);
+        held.intercepts.push(InterceptEntry {
+            device_id,
+            key_id,
+            id,
+            base,
+            record: SessionRecord::default(),
+        });
+        id
+    }
+
+    #[test]
+    fn releasing_in_reverse_order_restores_each_level() {
+        let kb = device("{kb}");
+        let mut held = empty();
+        let outer = control(&mut held, kb, Shared, None);
+        let inner = control(&mut held, kb, Lighting, Some(Shared));
+        let same = control(&mut held, kb, Lighting, Some(Lighting));
+
+        assert_eq!(held.remove_control(same), None);
+        assert_eq!(held.remove_control(inner), Some((kb, Request(Shared))));
+        assert_eq!(held.remove_control(outer), Some((kb, Release)));
+        // Already removed, e.g. by `release_all`.
+        assert_eq!(held.remove_control(outer), None);
+    }
+
+    #[test]
+    fn releasing_a_lower_guard_first_changes_nothing_until_the_top_goes() {
+        let (kb, mouse) = (device("{kb}"), device("{mouse}"));
+        let mut held = empty();
+        let outer = control(&mut held, kb, Shared, None);
+        let other = control(&mut held, mouse, Lighting, None);
+        let inner = control(&mut held, kb, Lighting, Some(Shared));
+
+        assert_eq!(held.remove_control(outer), None);
+        assert_eq!(held.remove_control(inner), Some((kb, Release)));
+        assert_eq!(held.remove_control(other), Some((mouse, Release)));
+    }
+
+    #[test]
+    fn control_held_before_the_first_guard_is_restored() {
+        let kb = device("{kb}");
+        let mut held = empty();
+        let outer = control(&mut held, kb, Lighting, Some(Shared));
+        let inner = control(&mut held, kb, Shared, Some(Lighting));
+
+        assert_eq!(held.remove_control(inner), Some((kb, Request(Lighting))));
+        assert_eq!(held.remove_control(outer), Some((kb, Request(Shared))));
+
+        let guard = control(&mut held, kb, Lighting, Some(Lighting));
+        assert_eq!(held.remove_control(guard), None);
+    }
+
+    #[test]
+    fn interception_stops_with_the_last_guard_unless_it_predates_them() {
+        let kb = device("{kb}");
+        let mut held = empty();
+        let first = intercept(&mut held, kb, MacroKeyId::Key1, false);
+        let second = intercept(&mut held, kb, MacroKeyId::Key1, true);
+        let other = intercept(&mut held, kb, MacroKeyId::Key2, true);
+
+        assert!(!held.remove_intercept(first));
+        assert!(held.remove_intercept(second));
+        assert!(!held.remove_intercept(second));
+        assert!(!held.remove_intercept(other));
+    }
+
+    #[test]
+    fn release_all_undoes_each_device_and_key_once() {
+        let (kb, mouse) = (device("{kb}"), device("{mouse}"));
+        let mut held = empty();
+        control(&mut held, kb, Shared, None);
+        control(&mut held, kb, Lighting, Some(Shared));
+        control(&mut held, mouse, Lighting, Some(Shared));
+        intercept(&mut held, kb, MacroKeyId::Key1, false);
+        intercept(&mut held, kb, MacroKeyId::Key1, false);
+        intercept(&mut held, kb, MacroKeyId::Key2, true);
+
+        let (control, intercepts) = held.take_all();
+        let control: Vec<_> = control.into_iter().map(|(d, c, _)| (d, c)).collect();
+        assert_eq!(control, vec![(kb, Release), (mouse, Request(Shared))]);
+        let intercepts: Vec<_> = intercepts.into_iter().map(|(d, k, _)| (d, k)).collect();
+        assert_eq!(intercepts, vec![(kb, MacroKeyId::Key1)]);
+        assert!(held.control.is_empty() && held.intercepts.is_empty());
+    }
+}
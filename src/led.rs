@@ -10,6 +10,7 @@ use cue_sdk_sys as ffi;
 /// to FFI functions via pointer cast — no per-element copying required.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LedColor {
     /// LED locally-unique identifier.
     pub id: u32,
@@ -29,6 +30,49 @@ impl LedColor {
     pub fn rgb(id: u32, r: u8, g: u8, b: u8) -> Self {
         Self::new(id, r, g, b, 255)
     }
+
+    /// Create an LED color from a LUID and an [`Rgba`] value.
+    pub fn with_color(id: u32, color: Rgba) -> Self {
+        Self::new(id, color.r, color.g, color.b, color.a)
+    }
+
+    /// The color of this LED without its LUID.
+    pub fn color(&self) -> Rgba {
+        Rgba::new(self.r, self.g, self.b, self.a)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Rgba
+// ---------------------------------------------------------------------------
+
+/// A color value that is not tied to a particular LED.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    /// Opaque black.
+    pub const BLACK: Rgba = Rgba::rgb(0, 0, 0);
+    /// Opaque white.
+    pub const WHITE: Rgba = Rgba::rgb(255, 255, 255);
+    /// Fully transparent black.
+    pub const TRANSPARENT: Rgba = Rgba::new(0, 0, 0, 0);
+
+    /// Create a new color.
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Create an opaque color (alpha = 255).
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r, g, b, 255)
+    }
 }
 
 // Safety: LedColor is a plain-old-data type with the exact same layout as
@@ -69,10 +113,121 @@ impl LedPosition {
     }
 }
 
+// ---------------------------------------------------------------------------
+// LedGroup
+// ---------------------------------------------------------------------------
+
+/// The group an LED LUID belongs to.
+///
+/// SDK v4 LUIDs encode the group in the upper 16 bits and the LED index
+/// within that group in the lower 16 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LedGroup {
+    Keyboard,
+    KeyboardGKeys,
+    KeyboardEdge,
+    KeyboardOem,
+    Mouse,
+    Mousemat,
+    Headset,
+    HeadsetStand,
+    MemoryModule,
+    Motherboard,
+    GraphicsCard,
+    DiyChannel1,
+    DiyChannel2,
+    DiyChannel3,
+    Touchbar,
+    GameController,
+    Unknown(u32),
+}
+
+impl LedGroup {
+    pub(crate) fn from_ffi(raw: ffi::CorsairLedGroup) -> Self {
+        match raw {
+            ffi::CorsairLedGroup_CLG_Keyboard => Self::Keyboard,
+            ffi::CorsairLedGroup_CLG_KeyboardGKeys => Self::KeyboardGKeys,
+            ffi::CorsairLedGroup_CLG_KeyboardEdge => Self::KeyboardEdge,
+            ffi::CorsairLedGroup_CLG_KeyboardOem => Self::KeyboardOem,
+            ffi::CorsairLedGroup_CLG_Mouse => Self::Mouse,
+            ffi::CorsairLedGroup_CLG_Mousemat => Self::Mousemat,
+            ffi::CorsairLedGroup_CLG_Headset => Self::Headset,
+            ffi::CorsairLedGroup_CLG_HeadsetStand => Self::HeadsetStand,
+            ffi::CorsairLedGroup_CLG_MemoryModule => Self::MemoryModule,
+            ffi::CorsairLedGroup_CLG_Motherboard => Self::Motherboard,
+            ffi::CorsairLedGroup_CLG_GraphicsCard => Self::GraphicsCard,
+            ffi::CorsairLedGroup_CLG_DIY_Channel1 => Self::DiyChannel1,
+            ffi::CorsairLedGroup_CLG_DIY_Channel2 => Self::DiyChannel2,
+            ffi::CorsairLedGroup_CLG_DIY_Channel3 => Self::DiyChannel3,
+            ffi::CorsairLedGroup_CLG_Touchbar => Self::Touchbar,
+            ffi::CorsairLedGroup_CLG_GameController => Self::GameController,
+            other => Self::Unknown(other),
+        }
+    }
+
+    pub(crate) fn to_ffi(self) -> ffi::CorsairLedGroup {
+        match self {
+            Self::Keyboard => ffi::CorsairLedGroup_CLG_Keyboard,
+            Self::KeyboardGKeys => ffi::CorsairLedGroup_CLG_KeyboardGKeys,
+            Self::KeyboardEdge => ffi::CorsairLedGroup_CLG_KeyboardEdge,
+            Self::KeyboardOem => ffi::CorsairLedGroup_CLG_KeyboardOem,
+            Self::Mouse => ffi::CorsairLedGroup_CLG_Mouse,
+            Self::Mousemat => ffi::CorsairLedGroup_CLG_Mousemat,
+            Self::Headset => ffi::CorsairLedGroup_CLG_Headset,
+            Self::HeadsetStand => ffi::CorsairLedGroup_CLG_HeadsetStand,
+            Self::MemoryModule => ffi::CorsairLedGroup_CLG_MemoryModule,
+            Self::Motherboard => ffi::CorsairLedGroup_CLG_Motherboard,
+            Self::GraphicsCard => ffi::CorsairLedGroup_CLG_GraphicsCard,
+            Self::DiyChannel1 => ffi::CorsairLedGroup_CLG_DIY_Channel1,
+            Self::DiyChannel2 => ffi::CorsairLedGroup_CLG_DIY_Channel2,
+            Self::DiyChannel3 => ffi::CorsairLedGroup_CLG_DIY_Channel3,
+            Self::Touchbar => ffi::CorsairLedGroup_CLG_Touchbar,
+            Self::GameController => ffi::CorsairLedGroup_CLG_GameController,
+            Self::Unknown(raw) => raw,
+        }
+    }
+
+    /// The group encoded in an LED LUID.
+    pub fn of(luid: u32) -> Self {
+        Self::from_ffi(luid >> 16)
+    }
+
+    /// The DIY channel group for a 1-based channel number, if the SDK
+    /// defines one.
+    pub fn diy_channel(channel: u32) -> Option<Self> {
+        match channel {
+            1 => Some(Self::DiyChannel1),
+            2 => Some(Self::DiyChannel2),
+            3 => Some(Self::DiyChannel3),
+            _ => None,
+        }
+    }
+
+    /// Build the LUID of the LED at `index` within this group.
+    pub fn luid(self, index: u16) -> u32 {
+        (self.to_ffi() << 16) | u32::from(index)
+    }
+
+    /// The first and last LUID that can belong to this group.
+    pub fn luid_range(self) -> (u32, u32) {
+        (self.luid(0), self.luid(u16::MAX))
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Keyboard LED constants
 // ---------------------------------------------------------------------------
 
+/// Declare the keyboard LUID constants together with a name lookup table.
+macro_rules! keyboard_leds {
+    ($($name:ident = $ffi:ident,)*) => {
+        $(pub const $name: u32 = ffi::$ffi;)*
+
+        /// Every constant in this module paired with its name.
+        pub(crate) const NAMES: &[(&str, u32)] = &[$((stringify!($name), $name),)*];
+    };
+}
+
 /// Named constants for keyboard LED LUIDs from the `CLG_Keyboard` group.
 ///
 /// These correspond to `CorsairLedId_Keyboard_CLK_*` values and can be used
@@ -80,133 +235,153 @@ impl LedPosition {
 pub mod keyboard {
     use cue_sdk_sys as ffi;
 
-    pub const ESCAPE: u32 = ffi::CorsairLedId_Keyboard_CLK_Escape;
-    pub const F1: u32 = ffi::CorsairLedId_Keyboard_CLK_F1;
-    pub const F2: u32 = ffi::CorsairLedId_Keyboard_CLK_F2;
-    pub const F3: u32 = ffi::CorsairLedId_Keyboard_CLK_F3;
-    pub const F4: u32 = ffi::CorsairLedId_Keyboard_CLK_F4;
-    pub const F5: u32 = ffi::CorsairLedId_Keyboard_CLK_F5;
-    pub const F6: u32 = ffi::CorsairLedId_Keyboard_CLK_F6;
-    pub const F7: u32 = ffi::CorsairLedId_Keyboard_CLK_F7;
-    pub const F8: u32 = ffi::CorsairLedId_Keyboard_CLK_F8;
-    pub const F9: u32 = ffi::CorsairLedId_Keyboard_CLK_F9;
-    pub const F10: u32 = ffi::CorsairLedId_Keyboard_CLK_F10;
-    pub const F11: u32 = ffi::CorsairLedId_Keyboard_CLK_F11;
-    pub const F12: u32 = ffi::CorsairLedId_Keyboard_CLK_F12;
-    pub const GRAVE_ACCENT: u32 = ffi::CorsairLedId_Keyboard_CLK_GraveAccentAndTilde;
-    pub const KEY_1: u32 = ffi::CorsairLedId_Keyboard_CLK_1;
-    pub const KEY_2: u32 = ffi::CorsairLedId_Keyboard_CLK_2;
-    pub const KEY_3: u32 = ffi::CorsairLedId_Keyboard_CLK_3;
-    pub const KEY_4: u32 = ffi::CorsairLedId_Keyboard_CLK_4;
-    pub const KEY_5: u32 = ffi::CorsairLedId_Keyboard_CLK_5;
-    pub const KEY_6: u32 = ffi::CorsairLedId_Keyboard_CLK_6;
-    pub const KEY_7: u32 = ffi::CorsairLedId_Keyboard_CLK_7;
-    pub const KEY_8: u32 = ffi::CorsairLedId_Keyboard_CLK_8;
-    pub const KEY_9: u32 = ffi::CorsairLedId_Keyboard_CLK_9;
-    pub const KEY_0: u32 = ffi::CorsairLedId_Keyboard_CLK_0;
-    pub const MINUS: u32 = ffi::CorsairLedId_Keyboard_CLK_MinusAndUnderscore;
-    pub const EQUALS: u32 = ffi::CorsairLedId_Keyboard_CLK_EqualsAndPlus;
-    pub const BACKSPACE: u32 = ffi::CorsairLedId_Keyboard_CLK_Backspace;
-    pub const TAB: u32 = ffi::CorsairLedId_Keyboard_CLK_Tab;
-    pub const Q: u32 = ffi::CorsairLedId_Keyboard_CLK_Q;
-    pub const W: u32 = ffi::CorsairLedId_Keyboard_CLK_W;
-    pub const E: u32 = ffi::CorsairLedId_Keyboard_CLK_E;
-    pub const R: u32 = ffi::CorsairLedId_Keyboard_CLK_R;
-    pub const T: u32 = ffi::CorsairLedId_Keyboard_CLK_T;
-    pub const Y: u32 = ffi::CorsairLedId_Keyboard_CLK_Y;
-    pub const U: u32 = ffi::CorsairLedId_Keyboard_CLK_U;
-    pub const I: u32 = ffi::CorsairLedId_Keyboard_CLK_I;
-    pub const O: u32 = ffi::CorsairLedId_Keyboard_CLK_O;
-    pub const P: u32 = ffi::CorsairLedId_Keyboard_CLK_P;
-    pub const BRACKET_LEFT: u32 = ffi::CorsairLedId_Keyboard_CLK_BracketLeft;
-    pub const BRACKET_RIGHT: u32 = ffi::CorsairLedId_Keyboard_CLK_BracketRight;
-    pub const CAPS_LOCK: u32 = ffi::CorsairLedId_Keyboard_CLK_CapsLock;
-    pub const A: u32 = ffi::CorsairLedId_Keyboard_CLK_A;
-    pub const S: u32 = ffi::CorsairLedId_Keyboard_CLK_S;
-    pub const D: u32 = ffi::CorsairLedId_Keyboard_CLK_D;
-    pub const F: u32 = ffi::CorsairLedId_Keyboard_CLK_F;
-    pub const G: u32 = ffi::CorsairLedId_Keyboard_CLK_G;
-    pub const H: u32 = ffi::CorsairLedId_Keyboard_CLK_H;
-    pub const J: u32 = ffi::CorsairLedId_Keyboard_CLK_J;
-    pub const K: u32 = ffi::CorsairLedId_Keyboard_CLK_K;
-    pub const L: u32 = ffi::CorsairLedId_Keyboard_CLK_L;
-    pub const SEMICOLON: u32 = ffi::CorsairLedId_Keyboard_CLK_SemicolonAndColon;
-    pub const APOSTROPHE: u32 = ffi::CorsairLedId_Keyboard_CLK_ApostropheAndDoubleQuote;
-    pub const BACKSLASH: u32 = ffi::CorsairLedId_Keyboard_CLK_Backslash;
-    pub const ENTER: u32 = ffi::CorsairLedId_Keyboard_CLK_Enter;
-    pub const LEFT_SHIFT: u32 = ffi::CorsairLedId_Keyboard_CLK_LeftShift;
-    pub const NON_US_BACKSLASH: u32 = ffi::CorsairLedId_Keyboard_CLK_NonUsBackslash;
-    pub const Z: u32 = ffi::CorsairLedId_Keyboard_CLK_Z;
-    pub const X: u32 = ffi::CorsairLedId_Keyboard_CLK_X;
-    pub const C: u32 = ffi::CorsairLedId_Keyboard_CLK_C;
-    pub const V: u32 = ffi::CorsairLedId_Keyboard_CLK_V;
-    pub const B: u32 = ffi::CorsairLedId_Keyboard_CLK_B;
-    pub const N: u32 = ffi::CorsairLedId_Keyboard_CLK_N;
-    pub const M: u32 = ffi::CorsairLedId_Keyboard_CLK_M;
-    pub const COMMA: u32 = ffi::CorsairLedId_Keyboard_CLK_CommaAndLessThan;
-    pub const PERIOD: u32 = ffi::CorsairLedId_Keyboard_CLK_PeriodAndBiggerThan;
-    pub const SLASH: u32 = ffi::CorsairLedId_Keyboard_CLK_SlashAndQuestionMark;
-    pub const RIGHT_SHIFT: u32 = ffi::CorsairLedId_Keyboard_CLK_RightShift;
-    pub const LEFT_CTRL: u32 = ffi::CorsairLedId_Keyboard_CLK_LeftCtrl;
-    pub const LEFT_GUI: u32 = ffi::CorsairLedId_Keyboard_CLK_LeftGui;
-    pub const LEFT_ALT: u32 = ffi::CorsairLedId_Keyboard_CLK_LeftAlt;
-    pub const SPACE: u32 = ffi::CorsairLedId_Keyboard_CLK_Space;
-    pub const RIGHT_ALT: u32 = ffi::CorsairLedId_Keyboard_CLK_RightAlt;
-    pub const RIGHT_GUI: u32 = ffi::CorsairLedId_Keyboard_CLK_RightGui;
-    pub const APPLICATION: u32 = ffi::CorsairLedId_Keyboard_CLK_Application;
-    pub const RIGHT_CTRL: u32 = ffi::CorsairLedId_Keyboard_CLK_RightCtrl;
-    pub const LED_PROGRAMMING: u32 = ffi::CorsairLedId_Keyboard_CLK_LedProgramming;
-    pub const LANG1: u32 = ffi::CorsairLedId_Keyboard_CLK_Lang1;
-    pub const LANG2: u32 = ffi::CorsairLedId_Keyboard_CLK_Lang2;
-    pub const INTERNATIONAL1: u32 = ffi::CorsairLedId_Keyboard_CLK_International1;
-    pub const INTERNATIONAL2: u32 = ffi::CorsairLedId_Keyboard_CLK_International2;
-    pub const INTERNATIONAL3: u32 = ffi::CorsairLedId_Keyboard_CLK_International3;
-    pub const INTERNATIONAL4: u32 = ffi::CorsairLedId_Keyboard_CLK_International4;
-    pub const INTERNATIONAL5: u32 = ffi::CorsairLedId_Keyboard_CLK_International5;
-    pub const PRINT_SCREEN: u32 = ffi::CorsairLedId_Keyboard_CLK_PrintScreen;
-    pub const SCROLL_LOCK: u32 = ffi::CorsairLedId_Keyboard_CLK_ScrollLock;
-    pub const PAUSE_BREAK: u32 = ffi::CorsairLedId_Keyboard_CLK_PauseBreak;
-    pub const INSERT: u32 = ffi::CorsairLedId_Keyboard_CLK_Insert;
-    pub const HOME: u32 = ffi::CorsairLedId_Keyboard_CLK_Home;
-    pub const PAGE_UP: u32 = ffi::CorsairLedId_Keyboard_CLK_PageUp;
-    pub const DELETE: u32 = ffi::CorsairLedId_Keyboard_CLK_Delete;
-    pub const END: u32 = ffi::CorsairLedId_Keyboard_CLK_End;
-    pub const PAGE_DOWN: u32 = ffi::CorsairLedId_Keyboard_CLK_PageDown;
-    pub const UP_ARROW: u32 = ffi::CorsairLedId_Keyboard_CLK_UpArrow;
-    pub const LEFT_ARROW: u32 = ffi::CorsairLedId_Keyboard_CLK_LeftArrow;
-    pub const DOWN_ARROW: u32 = ffi::CorsairLedId_Keyboard_CLK_DownArrow;
-    pub const RIGHT_ARROW: u32 = ffi::CorsairLedId_Keyboard_CLK_RightArrow;
-    pub const NON_US_TILDE: u32 = ffi::CorsairLedId_Keyboard_CLK_NonUsTilde;
-    pub const BRIGHTNESS: u32 = ffi::CorsairLedId_Keyboard_CLK_Brightness;
-    pub const WIN_LOCK: u32 = ffi::CorsairLedId_Keyboard_CLK_WinLock;
-    pub const MUTE: u32 = ffi::CorsairLedId_Keyboard_CLK_Mute;
-    pub const STOP: u32 = ffi::CorsairLedId_Keyboard_CLK_Stop;
-    pub const SCAN_PREVIOUS_TRACK: u32 = ffi::CorsairLedId_Keyboard_CLK_ScanPreviousTrack;
-    pub const PLAY_PAUSE: u32 = ffi::CorsairLedId_Keyboard_CLK_PlayPause;
-    pub const SCAN_NEXT_TRACK: u32 = ffi::CorsairLedId_Keyboard_CLK_ScanNextTrack;
-    pub const NUM_LOCK: u32 = ffi::CorsairLedId_Keyboard_CLK_NumLock;
-    pub const KEYPAD_SLASH: u32 = ffi::CorsairLedId_Keyboard_CLK_KeypadSlash;
-    pub const KEYPAD_ASTERISK: u32 = ffi::CorsairLedId_Keyboard_CLK_KeypadAsterisk;
-    pub const KEYPAD_MINUS: u32 = ffi::CorsairLedId_Keyboard_CLK_KeypadMinus;
-    pub const KEYPAD_7: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad7;
-    pub const KEYPAD_8: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad8;
-    pub const KEYPAD_9: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad9;
-    pub const KEYPAD_PLUS: u32 = ffi::CorsairLedId_Keyboard_CLK_KeypadPlus;
-    pub const KEYPAD_4: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad4;
-    pub const KEYPAD_5: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad5;
-    pub const KEYPAD_6: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad6;
-    pub const KEYPAD_1: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad1;
-    pub const KEYPAD_2: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad2;
-    pub const KEYPAD_3: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad3;
-    pub const KEYPAD_COMMA: u32 = ffi::CorsairLedId_Keyboard_CLK_KeypadComma;
-    pub const KEYPAD_ENTER: u32 = ffi::CorsairLedId_Keyboard_CLK_KeypadEnter;
-    pub const KEYPAD_0: u32 = ffi::CorsairLedId_Keyboard_CLK_Keypad0;
-    pub const KEYPAD_PERIOD_DELETE: u32 = ffi::CorsairLedId_Keyboard_CLK_KeypadPeriodAndDelete;
-    pub const VOLUME_UP: u32 = ffi::CorsairLedId_Keyboard_CLK_VolumeUp;
-    pub const VOLUME_DOWN: u32 = ffi::CorsairLedId_Keyboard_CLK_VolumeDown;
-    pub const MR: u32 = ffi::CorsairLedId_Keyboard_CLK_MR;
-    pub const M1: u32 = ffi::CorsairLedId_Keyboard_CLK_M1;
-    pub const M2: u32 = ffi::CorsairLedId_Keyboard_CLK_M2;
-    pub const M3: u32 = ffi::CorsairLedId_Keyboard_CLK_M3;
-    pub const FN: u32 = ffi::CorsairLedId_Keyboard_CLK_Fn;
+    keyboard_leds! {
+        ESCAPE = CorsairLedId_Keyboard_CLK_Escape,
+        F1 = CorsairLedId_Keyboard_CLK_F1,
+        F2 = CorsairLedId_Keyboard_CLK_F2,
+        F3 = CorsairLedId_Keyboard_CLK_F3,
+        F4 = CorsairLedId_Keyboard_CLK_F4,
+        F5 = CorsairLedId_Keyboard_CLK_F5,
+        F6 = CorsairLedId_Keyboard_CLK_F6,
+        F7 = CorsairLedId_Keyboard_CLK_F7,
+        F8 = CorsairLedId_Keyboard_CLK_F8,
+        F9 = CorsairLedId_Keyboard_CLK_F9,
+        F10 = CorsairLedId_Keyboard_CLK_F10,
+        F11 = CorsairLedId_Keyboard_CLK_F11,
+        F12 = CorsairLedId_Keyboard_CLK_F12,
+        GRAVE_ACCENT = CorsairLedId_Keyboard_CLK_GraveAccentAndTilde,
+        KEY_1 = CorsairLedId_Keyboard_CLK_1,
+        KEY_2 = CorsairLedId_Keyboard_CLK_2,
+        KEY_3 = CorsairLedId_Keyboard_CLK_3,
+        KEY_4 = CorsairLedId_Keyboard_CLK_4,
+        KEY_5 = CorsairLedId_Keyboard_CLK_5,
+        KEY_6 = CorsairLedId_Keyboard_CLK_6,
+        KEY_7 = CorsairLedId_Keyboard_CLK_7,
+        KEY_8 = CorsairLedId_Keyboard_CLK_8,
+        KEY_9 = CorsairLedId_Keyboard_CLK_9,
+        KEY_0 = CorsairLedId_Keyboard_CLK_0,
+        MINUS = CorsairLedId_Keyboard_CLK_MinusAndUnderscore,
+        EQUALS = CorsairLedId_Keyboard_CLK_EqualsAndPlus,
+        BACKSPACE = CorsairLedId_Keyboard_CLK_Backspace,
+        TAB = CorsairLedId_Keyboard_CLK_Tab,
+        Q = CorsairLedId_Keyboard_CLK_Q,
+        W = CorsairLedId_Keyboard_CLK_W,
+        E = CorsairLedId_Keyboard_CLK_E,
+        R = CorsairLedId_Keyboard_CLK_R,
+        T = CorsairLedId_Keyboard_CLK_T,
+        Y = CorsairLedId_Keyboard_CLK_Y,
+        U = CorsairLedId_Keyboard_CLK_U,
+        I = CorsairLedId_Keyboard_CLK_I,
+        O = CorsairLedId_Keyboard_CLK_O,
+        P = CorsairLedId_Keyboard_CLK_P,
+        BRACKET_LEFT = CorsairLedId_Keyboard_CLK_BracketLeft,
+        BRACKET_RIGHT = CorsairLedId_Keyboard_CLK_BracketRight,
+        CAPS_LOCK = CorsairLedId_Keyboard_CLK_CapsLock,
+        A = CorsairLedId_Keyboard_CLK_A,
+        S = CorsairLedId_Keyboard_CLK_S,
+        D = CorsairLedId_Keyboard_CLK_D,
+        F = CorsairLedId_Keyboard_CLK_F,
+        G = CorsairLedId_Keyboard_CLK_G,
+        H = CorsairLedId_Keyboard_CLK_H,
+        J = CorsairLedId_Keyboard_CLK_J,
+        K = CorsairLedId_Keyboard_CLK_K,
+        L = CorsairLedId_Keyboard_CLK_L,
+        SEMICOLON = CorsairLedId_Keyboard_CLK_SemicolonAndColon,
+        APOSTROPHE = CorsairLedId_Keyboard_CLK_ApostropheAndDoubleQuote,
+        BACKSLASH = CorsairLedId_Keyboard_CLK_Backslash,
+        ENTER = CorsairLedId_Keyboard_CLK_Enter,
+        LEFT_SHIFT = CorsairLedId_Keyboard_CLK_LeftShift,
+        NON_US_BACKSLASH = CorsairLedId_Keyboard_CLK_NonUsBackslash,
+        Z = CorsairLedId_Keyboard_CLK_Z,
+        X = CorsairLedId_Keyboard_CLK_X,
+        C = CorsairLedId_Keyboard_CLK_C,
+        V = CorsairLedId_Keyboard_CLK_V,
+        B = CorsairLedId_Keyboard_CLK_B,
+        N = CorsairLedId_Keyboard_CLK_N,
+        M = CorsairLedId_Keyboard_CLK_M,
+        COMMA = CorsairLedId_Keyboard_CLK_CommaAndLessThan,
+        PERIOD = CorsairLedId_Keyboard_CLK_PeriodAndBiggerThan,
+        SLASH = CorsairLedId_Keyboard_CLK_SlashAndQuestionMark,
+        RIGHT_SHIFT = CorsairLedId_Keyboard_CLK_RightShift,
+        LEFT_CTRL = CorsairLedId_Keyboard_CLK_LeftCtrl,
+        LEFT_GUI = CorsairLedId_Keyboard_CLK_LeftGui,
+        LEFT_ALT = CorsairLedId_Keyboard_CLK_LeftAlt,
+        SPACE = CorsairLedId_Keyboard_CLK_Space,
+        RIGHT_ALT = CorsairLedId_Keyboard_CLK_RightAlt,
+        RIGHT_GUI = CorsairLedId_Keyboard_CLK_RightGui,
+        APPLICATION = CorsairLedId_Keyboard_CLK_Application,
+        RIGHT_CTRL = CorsairLedId_Keyboard_CLK_RightCtrl,
+        LED_PROGRAMMING = CorsairLedId_Keyboard_CLK_LedProgramming,
+        LANG1 = CorsairLedId_Keyboard_CLK_Lang1,
+        LANG2 = CorsairLedId_Keyboard_CLK_Lang2,
+        INTERNATIONAL1 = CorsairLedId_Keyboard_CLK_International1,
+        INTERNATIONAL2 = CorsairLedId_Keyboard_CLK_International2,
+        INTERNATIONAL3 = CorsairLedId_Keyboard_CLK_International3,
+        INTERNATIONAL4 = CorsairLedId_Keyboard_CLK_International4,
+        INTERNATIONAL5 = CorsairLedId_Keyboard_CLK_International5,
+        PRINT_SCREEN = CorsairLedId_Keyboard_CLK_PrintScreen,
+        SCROLL_LOCK = CorsairLedId_Keyboard_CLK_ScrollLock,
+        PAUSE_BREAK = CorsairLedId_Keyboard_CLK_PauseBreak,
+        INSERT = CorsairLedId_Keyboard_CLK_Insert,
+        HOME = CorsairLedId_Keyboard_CLK_Home,
+        PAGE_UP = CorsairLedId_Keyboard_CLK_PageUp,
+        DELETE = CorsairLedId_Keyboard_CLK_Delete,
+        END = CorsairLedId_Keyboard_CLK_End,
+        PAGE_DOWN = CorsairLedId_Keyboard_CLK_PageDown,
+        UP_ARROW = CorsairLedId_Keyboard_CLK_UpArrow,
+        LEFT_ARROW = CorsairLedId_Keyboard_CLK_LeftArrow,
+        DOWN_ARROW = CorsairLedId_Keyboard_CLK_DownArrow,
+        RIGHT_ARROW = CorsairLedId_Keyboard_CLK_RightArrow,
+        NON_US_TILDE = CorsairLedId_Keyboard_CLK_NonUsTilde,
+        BRIGHTNESS = CorsairLedId_Keyboard_CLK_Brightness,
+        WIN_LOCK = CorsairLedId_Keyboard_CLK_WinLock,
+        MUTE = CorsairLedId_Keyboard_CLK_Mute,
+        STOP = CorsairLedId_Keyboard_CLK_Stop,
+        SCAN_PREVIOUS_TRACK = CorsairLedId_Keyboard_CLK_ScanPreviousTrack,
+        PLAY_PAUSE = CorsairLedId_Keyboard_CLK_PlayPause,
+        SCAN_NEXT_TRACK = CorsairLedId_Keyboard_CLK_ScanNextTrack,
+        NUM_LOCK = CorsairLedId_Keyboard_CLK_NumLock,
+        KEYPAD_SLASH = CorsairLedId_Keyboard_CLK_KeypadSlash,
+        KEYPAD_ASTERISK = CorsairLedId_Keyboard_CLK_KeypadAsterisk,
+        KEYPAD_MINUS = CorsairLedId_Keyboard_CLK_KeypadMinus,
+        KEYPAD_7 = CorsairLedId_Keyboard_CLK_Keypad7,
+        KEYPAD_8 = CorsairLedId_Keyboard_CLK_Keypad8,
+        KEYPAD_9 = CorsairLedId_Keyboard_CLK_Keypad9,
+        KEYPAD_PLUS = CorsairLedId_Keyboard_CLK_KeypadPlus,
+        KEYPAD_4 = CorsairLedId_Keyboard_CLK_Keypad4,
+        KEYPAD_5 = CorsairLedId_Keyboard_CLK_Keypad5,
+        KEYPAD_6 = CorsairLedId_Keyboard_CLK_Keypad6,
+        KEYPAD_1 = CorsairLedId_Keyboard_CLK_Keypad1,
+        KEYPAD_2 = CorsairLedId_Keyboard_CLK_Keypad2,
+        KEYPAD_3 = CorsairLedId_Keyboard_CLK_Keypad3,
+        KEYPAD_COMMA = CorsairLedId_Keyboard_CLK_KeypadComma,
+        KEYPAD_ENTER = CorsairLedId_Keyboard_CLK_KeypadEnter,
+        KEYPAD_0 = CorsairLedId_Keyboard_CLK_Keypad0,
+        KEYPAD_PERIOD_DELETE = CorsairLedId_Keyboard_CLK_KeypadPeriodAndDelete,
+        VOLUME_UP = CorsairLedId_Keyboard_CLK_VolumeUp,
+        VOLUME_DOWN = CorsairLedId_Keyboard_CLK_VolumeDown,
+        MR = CorsairLedId_Keyboard_CLK_MR,
+        M1 = CorsairLedId_Keyboard_CLK_M1,
+        M2 = CorsairLedId_Keyboard_CLK_M2,
+        M3 = CorsairLedId_Keyboard_CLK_M3,
+        FN = CorsairLedId_Keyboard_CLK_Fn,
+    }
+
+    /// Look up a keyboard LED LUID by its constant name (case-insensitive).
+    ///
+    /// Both the full constant name (`"KEY_1"`, `"ESCAPE"`) and, for the digit
+    /// keys, the bare digit (`"1"`) are accepted.
+    pub fn from_name(name: &str) -> Option<u32> {
+        let upper = name.to_ascii_uppercase();
+        let key = match upper.as_str() {
+            d if d.len() == 1 && d.as_bytes()[0].is_ascii_digit() => format!("KEY_{d}"),
+            _ => upper,
+        };
+        NAMES.iter().find(|(n, _)| *n == key).map(|&(_, luid)| luid)
+    }
+
+    /// Return the constant name of a keyboard LED LUID, if it has one.
+    pub fn name(luid: u32) -> Option<&'static str> {
+        NAMES.iter().find(|&&(_, l)| l == luid).map(|&(n, _)| n)
+    }
 }
@@ -19,28 +19,55 @@
 //! # Architecture
 //!
 //! [`Session`] is the single entry point for all SDK operations.  Call
-//! [`connect()`] to create one; it calls `CorsairDisconnect` on drop.
+//! [`connect()`] to create one; it calls `CorsairDisconnect` on drop.  Only
+//! one session can exist per process; libraries that share the application's
+//! connection use the reference-counted [`Session::shared()`].
 //!
 //! Device information is returned as plain data structs ([`DeviceInfo`],
 //! [`LedPosition`]).  Operations that need a device take a [`&DeviceId`]
 //! parameter.
 
+pub mod battery;
+pub mod bindings;
+pub mod builder;
 pub(crate) mod callback;
+pub mod capabilities;
+pub mod compositor;
 pub mod device;
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
 pub mod error;
 pub mod event;
+pub mod frame;
+pub mod gesture;
+pub mod guard;
 pub mod led;
 pub mod property;
+pub mod reactive;
+pub mod registry;
+pub mod report;
+pub mod retry;
+pub mod scheduler;
+mod sdk;
 pub mod session;
+pub mod snapshot;
+mod trace;
+mod validate;
+pub mod watcher;
+pub mod zone;
 
-pub use device::{DeviceId, DeviceInfo, DeviceType};
-pub use error::{Result, SdkError};
+pub use builder::SessionBuilder;
+pub use capabilities::ServerCapabilities;
+pub use device::{ChannelDevice, ChannelDeviceType, Device, DeviceId, DeviceInfo, DeviceType};
+pub use error::{Error, Malformed, Operation, Result, SdkError};
 #[cfg(feature = "async")]
 pub use event::AsyncEventSubscription;
 pub use event::{Event, EventSubscription, MacroKeyId};
-pub use led::{LedColor, LedPosition};
-pub use property::{PropertyId, PropertyValue};
-pub use session::{AccessLevel, Session, SessionDetails, SessionState, Version};
+pub use frame::LedFrame;
+pub use led::{LedColor, LedGroup, LedPosition, Rgba};
+pub use property::{FromPropertyValue, PropertyId, PropertyValue};
+pub use session::{AccessLevel, PendingFlush, Session, SessionDetails, SessionState, Version};
+pub use zone::{DeviceLayout, Selector, Zone, Zones};
 
 /// Connect to the iCUE SDK and return a [`Session`].
 ///
@@ -1,36 +1,198 @@
-use core::ffi::c_int;
-use std::ffi::CStr;
+use core::ffi::{c_char, c_uint};
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::marker::PhantomData;
 
 use bitflags::bitflags;
 use cue_sdk_sys as ffi;
 
+use crate::error::SdkError;
+use crate::sdk;
+use crate::validate;
+
 // ---------------------------------------------------------------------------
 // PropertyId
 // ---------------------------------------------------------------------------
 
 /// Identifier for a device property.
+///
+/// Property ids this crate does not know about, e.g. from a device's
+/// [`PropertyArray`](Self::PropertyArray), are reported as
+/// [`Unknown`](Self::Unknown) with the raw SDK value.  `Unknown` never holds
+/// the id of a named variant, so ids from [`from_raw`](Self::from_raw)
+/// compare equal to the named variant.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u32)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum PropertyId {
-    PropertyArray = ffi::CorsairDevicePropertyId_CDPI_PropertyArray,
-    MicEnabled = ffi::CorsairDevicePropertyId_CDPI_MicEnabled,
-    SurroundSoundEnabled = ffi::CorsairDevicePropertyId_CDPI_SurroundSoundEnabled,
-    SidetoneEnabled = ffi::CorsairDevicePropertyId_CDPI_SidetoneEnabled,
-    EqualizerPreset = ffi::CorsairDevicePropertyId_CDPI_EqualizerPreset,
-    PhysicalLayout = ffi::CorsairDevicePropertyId_CDPI_PhysicalLayout,
-    LogicalLayout = ffi::CorsairDevicePropertyId_CDPI_LogicalLayout,
-    MacroKeyArray = ffi::CorsairDevicePropertyId_CDPI_MacroKeyArray,
-    BatteryLevel = ffi::CorsairDevicePropertyId_CDPI_BatteryLevel,
-    ChannelLedCount = ffi::CorsairDevicePropertyId_CDPI_ChannelLedCount,
-    ChannelDeviceCount = ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceCount,
-    ChannelDeviceLedCountArray = ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceLedCountArray,
-    ChannelDeviceTypeArray = ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceTypeArray,
+    PropertyArray,
+    MicEnabled,
+    SurroundSoundEnabled,
+    SidetoneEnabled,
+    EqualizerPreset,
+    PhysicalLayout,
+    LogicalLayout,
+    MacroKeyArray,
+    BatteryLevel,
+    ChannelLedCount,
+    ChannelDeviceCount,
+    ChannelDeviceLedCountArray,
+    ChannelDeviceTypeArray,
+    /// A property id not known to this crate.
+    Unknown(UnknownPropertyId),
 }
 
 impl PropertyId {
+    /// Every property known to this crate.
+    pub const ALL: [PropertyId; 13] = [
+        Self::PropertyArray,
+        Self::MicEnabled,
+        Self::SurroundSoundEnabled,
+        Self::SidetoneEnabled,
+        Self::EqualizerPreset,
+        Self::PhysicalLayout,
+        Self::LogicalLayout,
+        Self::MacroKeyArray,
+        Self::BatteryLevel,
+        Self::ChannelLedCount,
+        Self::ChannelDeviceCount,
+        Self::ChannelDeviceLedCountArray,
+        Self::ChannelDeviceTypeArray,
+    ];
+
+    /// The snake-case name of the property, e.g. `mic_enabled`, or
+    /// `unknown` for an unknown property.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PropertyArray => "property_array",
+            Self::MicEnabled => "mic_enabled",
+            Self::SurroundSoundEnabled => "surround_sound_enabled",
+            Self::SidetoneEnabled => "sidetone_enabled",
+            Self::EqualizerPreset => "equalizer_preset",
+            Self::PhysicalLayout => "physical_layout",
+            Self::LogicalLayout => "logical_layout",
+            Self::MacroKeyArray => "macro_key_array",
+            Self::BatteryLevel => "battery_level",
+            Self::ChannelLedCount => "channel_led_count",
+            Self::ChannelDeviceCount => "channel_device_count",
+            Self::ChannelDeviceLedCountArray => "channel_device_led_count_array",
+            Self::ChannelDeviceTypeArray => "channel_device_type_array",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+
+    /// Look up a property by its [`name`](Self::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// The property with this raw SDK id: the named variant if there is
+    /// one, otherwise [`Unknown`](Self::Unknown).
+    pub fn from_raw(raw: u32) -> Self {
+        Self::from_ffi(raw)
+    }
+
+    /// The raw SDK id.
+    pub fn raw(self) -> u32 {
+        self.to_ffi()
+    }
+
+    /// Convert from the FFI constant.
+    pub(crate) fn from_ffi(raw: ffi::CorsairDevicePropertyId) -> Self {
+        match raw {
+            ffi::CorsairDevicePropertyId_CDPI_PropertyArray => Self::PropertyArray,
+            ffi::CorsairDevicePropertyId_CDPI_MicEnabled => Self::MicEnabled,
+            ffi::CorsairDevicePropertyId_CDPI_SurroundSoundEnabled => Self::SurroundSoundEnabled,
+            ffi::CorsairDevicePropertyId_CDPI_SidetoneEnabled => Self::SidetoneEnabled,
+            ffi::CorsairDevicePropertyId_CDPI_EqualizerPreset => Self::EqualizerPreset,
+            ffi::CorsairDevicePropertyId_CDPI_PhysicalLayout => Self::PhysicalLayout,
+            ffi::CorsairDevicePropertyId_CDPI_LogicalLayout => Self::LogicalLayout,
+            ffi::CorsairDevicePropertyId_CDPI_MacroKeyArray => Self::MacroKeyArray,
+            ffi::CorsairDevicePropertyId_CDPI_BatteryLevel => Self::BatteryLevel,
+            ffi::CorsairDevicePropertyId_CDPI_ChannelLedCount => Self::ChannelLedCount,
+            ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceCount => Self::ChannelDeviceCount,
+            ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceLedCountArray => {
+                Self::ChannelDeviceLedCountArray
+            }
+            ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceTypeArray => {
+                Self::ChannelDeviceTypeArray
+            }
+            other => Self::Unknown(UnknownPropertyId(other)),
+        }
+    }
+
     /// Convert to the FFI constant.
     pub(crate) fn to_ffi(self) -> ffi::CorsairDevicePropertyId {
-        self as ffi::CorsairDevicePropertyId
+        match self {
+            Self::PropertyArray => ffi::CorsairDevicePropertyId_CDPI_PropertyArray,
+            Self::MicEnabled => ffi::CorsairDevicePropertyId_CDPI_MicEnabled,
+            Self::SurroundSoundEnabled => ffi::CorsairDevicePropertyId_CDPI_SurroundSoundEnabled,
+            Self::SidetoneEnabled => ffi::CorsairDevicePropertyId_CDPI_SidetoneEnabled,
+            Self::EqualizerPreset => ffi::CorsairDevicePropertyId_CDPI_EqualizerPreset,
+            Self::PhysicalLayout => ffi::CorsairDevicePropertyId_CDPI_PhysicalLayout,
+            Self::LogicalLayout => ffi::CorsairDevicePropertyId_CDPI_LogicalLayout,
+            Self::MacroKeyArray => ffi::CorsairDevicePropertyId_CDPI_MacroKeyArray,
+            Self::BatteryLevel => ffi::CorsairDevicePropertyId_CDPI_BatteryLevel,
+            Self::ChannelLedCount => ffi::CorsairDevicePropertyId_CDPI_ChannelLedCount,
+            Self::ChannelDeviceCount => ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceCount,
+            Self::ChannelDeviceLedCountArray => {
+                ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceLedCountArray
+            }
+            Self::ChannelDeviceTypeArray => {
+                ffi::CorsairDevicePropertyId_CDPI_ChannelDeviceTypeArray
+            }
+            Self::Unknown(raw) => raw.0,
+        }
+    }
+}
+
+impl fmt::Display for PropertyId {
+    /// Writes the [`name`](Self::name), or `unknown(<raw id>)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(raw) => write!(f, "unknown({})", raw.get()),
+            known => f.write_str(known.name()),
+        }
+    }
+}
+
+/// The raw id of a property not known to this crate.
+///
+/// Only created for ids that are not one of the named [`PropertyId`]
+/// variants; use [`PropertyId::from_raw`] to build a property id from a raw
+/// value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnknownPropertyId(u32);
+
+impl UnknownPropertyId {
+    /// The raw SDK id.
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for UnknownPropertyId {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for UnknownPropertyId {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        match PropertyId::from_raw(u32::deserialize(deserializer)?) {
+            PropertyId::Unknown(id) => Ok(id),
+            known => Err(serde::de::Error::custom(format!(
+                "property id {} is {known}, not unknown",
+                known.raw()
+            ))),
+        }
     }
 }
 
@@ -54,6 +216,8 @@ bitflags! {
 
 /// The data type of a property value.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum DataType {
     Boolean,
     Int32,
@@ -63,20 +227,23 @@ pub enum DataType {
     Int32Array,
     Float64Array,
     StringArray,
+    /// A data type not known to this crate.  Values of this type cannot be
+    /// read.
+    Unknown(u32),
 }
 
 impl DataType {
-    pub(crate) fn from_ffi(raw: ffi::CorsairDataType) -> Option<Self> {
+    pub(crate) fn from_ffi(raw: ffi::CorsairDataType) -> Self {
         match raw {
-            ffi::CorsairDataType_CT_Boolean => Some(Self::Boolean),
-            ffi::CorsairDataType_CT_Int32 => Some(Self::Int32),
-            ffi::CorsairDataType_CT_Float64 => Some(Self::Float64),
-            ffi::CorsairDataType_CT_String => Some(Self::String),
-            ffi::CorsairDataType_CT_Boolean_Array => Some(Self::BooleanArray),
-            ffi::CorsairDataType_CT_Int32_Array => Some(Self::Int32Array),
-            ffi::CorsairDataType_CT_Float64_Array => Some(Self::Float64Array),
-            ffi::CorsairDataType_CT_String_Array => Some(Self::StringArray),
-            _ => None,
+            ffi::CorsairDataType_CT_Boolean => Self::Boolean,
+            ffi::CorsairDataType_CT_Int32 => Self::Int32,
+            ffi::CorsairDataType_CT_Float64 => Self::Float64,
+            ffi::CorsairDataType_CT_String => Self::String,
+            ffi::CorsairDataType_CT_Boolean_Array => Self::BooleanArray,
+            ffi::CorsairDataType_CT_Int32_Array => Self::Int32Array,
+            ffi::CorsairDataType_CT_Float64_Array => Self::Float64Array,
+            ffi::CorsairDataType_CT_String_Array => Self::StringArray,
+            other => Self::Unknown(other),
         }
     }
 }
@@ -100,7 +267,8 @@ pub struct PropertyInfo {
 ///
 /// The SDK-allocated memory is freed immediately after the value is copied out,
 /// so there are no dangling pointers.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PropertyValue {
     Boolean(bool),
     Int32(i32),
@@ -113,138 +281,285 @@ pub enum PropertyValue {
 }
 
 impl PropertyValue {
-    /// Extract an owned value from the raw FFI property, then free it.
+    /// The data type of this value.
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Self::Boolean(_) => DataType::Boolean,
+            Self::Int32(_) => DataType::Int32,
+            Self::Float64(_) => DataType::Float64,
+            Self::String(_) => DataType::String,
+            Self::BooleanArray(_) => DataType::BooleanArray,
+            Self::Int32Array(_) => DataType::Int32Array,
+            Self::Float64Array(_) => DataType::Float64Array,
+            Self::StringArray(_) => DataType::StringArray,
+        }
+    }
+
+    /// Copy an owned value out of a raw FFI property.
+    ///
+    /// Returns [`SdkError::InvalidOperation`] for a data type this crate
+    /// cannot read and [`SdkError::MalformedResponse`] for a null or
+    /// oversized array.
     ///
     /// # Safety
     ///
-    /// `prop` must point to a valid `CorsairProperty` returned by
-    /// `CorsairReadDeviceProperty`, whose `value` union variant matches `type_`.
-    /// After this call the SDK memory is freed via `CorsairFreeProperty`.
-    pub(crate) unsafe fn from_ffi_and_free(prop: &mut ffi::CorsairProperty) -> Option<Self> {
+    /// `prop`'s `value` union variant must match `type_`, and its pointers
+    /// must be valid as described by [`validate::array`] and, for strings,
+    /// point to null-terminated C strings.
+    pub(crate) unsafe fn from_ffi(prop: &ffi::CorsairProperty) -> Result<Self, SdkError> {
         // In each arm below, we access the union variant that corresponds to
         // `prop.type_`.  This is safe because the caller guarantees that the
-        // property was just initialised by the SDK with a matching type.
+        // variant matches the type.
         let val = match prop.type_ {
             ffi::CorsairDataType_CT_Boolean => {
                 // SAFETY: `type_` is `CT_Boolean`, so `value.boolean` is active.
-                Some(PropertyValue::Boolean(unsafe { prop.value.boolean }))
+                PropertyValue::Boolean(unsafe { prop.value.boolean })
             }
             ffi::CorsairDataType_CT_Int32 => {
                 // SAFETY: `type_` is `CT_Int32`, so `value.int32` is active.
-                Some(PropertyValue::Int32(unsafe { prop.value.int32 }))
+                PropertyValue::Int32(unsafe { prop.value.int32 })
             }
             ffi::CorsairDataType_CT_Float64 => {
                 // SAFETY: `type_` is `CT_Float64`, so `value.float64` is active.
-                Some(PropertyValue::Float64(unsafe { prop.value.float64 }))
+                PropertyValue::Float64(unsafe { prop.value.float64 })
             }
             ffi::CorsairDataType_CT_String => {
-                // SAFETY: `type_` is `CT_String`, so `value.string` is active.
-                let ptr = unsafe { prop.value.string };
-                if ptr.is_null() {
-                    Some(PropertyValue::String(std::string::String::new()))
-                } else {
-                    // SAFETY: The SDK returns a valid null-terminated C string.
-                    let s = unsafe { CStr::from_ptr(ptr) }
-                        .to_string_lossy()
-                        .into_owned();
-                    Some(PropertyValue::String(s))
-                }
+                // SAFETY: `type_` is `CT_String`, so `value.string` is active,
+                // and the caller guarantees a non-null pointer is a C string.
+                PropertyValue::String(unsafe { string_from_ptr(prop.value.string) })
             }
             ffi::CorsairDataType_CT_Boolean_Array => {
-                // SAFETY: `type_` is `CT_Boolean_Array`, so `value.boolean_array` is active.
+                // SAFETY: `type_` is `CT_Boolean_Array`, so `value.boolean_array`
+                // is active, and the caller guarantees its pointer is valid.
                 let arr = unsafe { prop.value.boolean_array };
-                let slice = if arr.items.is_null() || arr.count == 0 {
-                    &[]
-                } else {
-                    // SAFETY: The SDK guarantees `items` points to `count` valid bools.
-                    unsafe { std::slice::from_raw_parts(arr.items, arr.count as usize) }
-                };
-                Some(PropertyValue::BooleanArray(slice.to_vec()))
+                let slice = unsafe { validate::array(arr.items, arr.count) }?;
+                PropertyValue::BooleanArray(slice.to_vec())
             }
             ffi::CorsairDataType_CT_Int32_Array => {
-                // SAFETY: `type_` is `CT_Int32_Array`, so `value.int32_array` is active.
+                // SAFETY: `type_` is `CT_Int32_Array`, so `value.int32_array`
+                // is active, and the caller guarantees its pointer is valid.
                 let arr = unsafe { prop.value.int32_array };
-                let slice = if arr.items.is_null() || arr.count == 0 {
-                    &[]
-                } else {
-                    // SAFETY: The SDK guarantees `items` points to `count` valid i32s.
-                    unsafe { std::slice::from_raw_parts(arr.items, arr.count as usize) }
-                };
-                Some(PropertyValue::Int32Array(slice.to_vec()))
+                let slice = unsafe { validate::array(arr.items, arr.count) }?;
+                PropertyValue::Int32Array(slice.to_vec())
             }
             ffi::CorsairDataType_CT_Float64_Array => {
-                // SAFETY: `type_` is `CT_Float64_Array`, so `value.float64_array` is active.
+                // SAFETY: `type_` is `CT_Float64_Array`, so `value.float64_array`
+                // is active, and the caller guarantees its pointer is valid.
                 let arr = unsafe { prop.value.float64_array };
-                let slice = if arr.items.is_null() || arr.count == 0 {
-                    &[]
-                } else {
-                    // SAFETY: The SDK guarantees `items` points to `count` valid f64s.
-                    unsafe { std::slice::from_raw_parts(arr.items, arr.count as usize) }
-                };
-                Some(PropertyValue::Float64Array(slice.to_vec()))
+                let slice = unsafe { validate::array(arr.items, arr.count) }?;
+                PropertyValue::Float64Array(slice.to_vec())
             }
             ffi::CorsairDataType_CT_String_Array => {
-                // SAFETY: `type_` is `CT_String_Array`, so `value.string_array` is active.
+                // SAFETY: `type_` is `CT_String_Array`, so `value.string_array`
+                // is active, and the caller guarantees its pointer is valid.
                 let arr = unsafe { prop.value.string_array };
-                let ptrs = if arr.items.is_null() || arr.count == 0 {
-                    &[]
-                } else {
-                    // SAFETY: The SDK guarantees `items` points to `count` valid char pointers.
-                    unsafe { std::slice::from_raw_parts(arr.items, arr.count as usize) }
-                };
+                let ptrs = unsafe { validate::array(arr.items, arr.count) }?;
                 let strings = ptrs
                     .iter()
-                    .map(|&p| {
-                        if p.is_null() {
-                            std::string::String::new()
-                        } else {
-                            // SAFETY: Each non-null pointer is a valid C string from the SDK.
-                            unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned()
-                        }
-                    })
+                    // SAFETY: Each non-null pointer is a C string from the SDK.
+                    .map(|&p| unsafe { string_from_ptr(p) })
                     .collect();
-                Some(PropertyValue::StringArray(strings))
+                PropertyValue::StringArray(strings)
             }
-            _ => None,
+            _ => return Err(SdkError::InvalidOperation),
         };
+        Ok(val)
+    }
+
+    /// Extract an owned value from the raw FFI property, then free it.
+    ///
+    /// # Safety
+    ///
+    /// `prop` must point to a valid `CorsairProperty` returned by
+    /// `CorsairReadDeviceProperty`, whose `value` union variant matches `type_`.
+    /// After this call the SDK memory is freed via `CorsairFreeProperty`.
+    pub(crate) unsafe fn from_ffi_and_free(
+        prop: &mut ffi::CorsairProperty,
+    ) -> Result<Self, SdkError> {
+        // SAFETY: The caller guarantees the property came from the SDK.
+        let val = unsafe { Self::from_ffi(prop) };
 
         // SAFETY: `CorsairFreeProperty` releases SDK-allocated memory inside
         // the property.  It is safe to call on any property returned by
         // `CorsairReadDeviceProperty`, and must be called exactly once.
         unsafe {
-            let _ = ffi::CorsairFreeProperty(prop as *mut ffi::CorsairProperty);
+            let _ = sdk::CorsairFreeProperty(prop as *mut ffi::CorsairProperty);
         }
 
         val
     }
 }
 
-/// Create a `CorsairProperty` for writing a boolean value.
-pub(crate) fn make_bool_property(value: bool) -> ffi::CorsairProperty {
-    // SAFETY: `CorsairProperty` is `#[repr(C)]` with no padding requirements
-    // that zero-init would violate.  We immediately overwrite `type_` and `value`.
-    let mut prop: ffi::CorsairProperty = unsafe { std::mem::zeroed() };
-    prop.type_ = ffi::CorsairDataType_CT_Boolean;
-    prop.value = ffi::CorsairDataValue { boolean: value };
-    prop
+/// Copy a C string from the SDK; null is the empty string.
+///
+/// # Safety
+///
+/// A non-null `ptr` must point to a null-terminated C string.
+unsafe fn string_from_ptr(ptr: *const c_char) -> std::string::String {
+    if ptr.is_null() {
+        return std::string::String::new();
+    }
+    // SAFETY: The caller guarantees `ptr` is a valid C string.
+    unsafe { CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+// ---------------------------------------------------------------------------
+// FromPropertyValue
+// ---------------------------------------------------------------------------
+
+/// Types that can be extracted from a [`PropertyValue`].
+///
+/// Used by [`Session::read`](crate::Session::read) and
+/// [`Device::property`](crate::Device::property), which report a value of
+/// another type as [`SdkError::TypeMismatch`].
+pub trait FromPropertyValue: Sized {
+    /// The data type this is extracted from, or `None` if any type is
+    /// accepted.
+    const DATA_TYPE: Option<DataType>;
+
+    /// Extract the value, or return `None` if the property holds a different
+    /// type.
+    fn from_property_value(value: PropertyValue) -> Option<Self>;
+}
+
+impl FromPropertyValue for PropertyValue {
+    const DATA_TYPE: Option<DataType> = None;
+
+    fn from_property_value(value: PropertyValue) -> Option<Self> {
+        Some(value)
+    }
+}
+
+macro_rules! from_property_value {
+    ($($ty:ty => $variant:ident),* $(,)?) => {$(
+        impl FromPropertyValue for $ty {
+            const DATA_TYPE: Option<DataType> = Some(DataType::$variant);
+
+            fn from_property_value(value: PropertyValue) -> Option<Self> {
+                match value {
+                    PropertyValue::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+from_property_value! {
+    bool => Boolean,
+    i32 => Int32,
+    f64 => Float64,
+    std::string::String => String,
+    Vec<bool> => BooleanArray,
+    Vec<i32> => Int32Array,
+    Vec<f64> => Float64Array,
+    Vec<std::string::String> => StringArray,
 }
 
-/// Create a `CorsairProperty` for writing an i32 value.
-pub(crate) fn make_int32_property(value: i32) -> ffi::CorsairProperty {
-    // SAFETY: Same as `make_bool_property`.
-    let mut prop: ffi::CorsairProperty = unsafe { std::mem::zeroed() };
-    prop.type_ = ffi::CorsairDataType_CT_Int32;
-    prop.value = ffi::CorsairDataValue {
-        int32: value as c_int,
-    };
-    prop
+// ---------------------------------------------------------------------------
+// FfiProperty
+// ---------------------------------------------------------------------------
+
+/// A `CorsairProperty` pointing at a [`PropertyValue`], for writing.
+///
+/// Arrays of numbers and booleans point straight into the value's storage.
+/// Strings are copied into null-terminated buffers owned by this struct, so
+/// the property stays valid for as long as both live.  The SDK only reads
+/// from the property and never frees it.
+pub(crate) struct FfiProperty<'a> {
+    raw: ffi::CorsairProperty,
+    _strings: Vec<CString>,
+    _pointers: Vec<*mut c_char>,
+    _value: PhantomData<&'a PropertyValue>,
 }
 
-/// Create a `CorsairProperty` for writing an f64 value.
-pub(crate) fn make_float64_property(value: f64) -> ffi::CorsairProperty {
-    // SAFETY: Same as `make_bool_property`.
-    let mut prop: ffi::CorsairProperty = unsafe { std::mem::zeroed() };
-    prop.type_ = ffi::CorsairDataType_CT_Float64;
-    prop.value = ffi::CorsairDataValue { float64: value };
-    prop
+impl<'a> FfiProperty<'a> {
+    /// Build the property.  Returns `None` if a string contains a null byte
+    /// or an array is too long for the SDK.
+    pub(crate) fn new(value: &'a PropertyValue) -> Option<Self> {
+        let mut strings = Vec::new();
+        let mut pointers = Vec::new();
+        let count = |len: usize| c_uint::try_from(len).ok();
+        let (type_, value) = match value {
+            PropertyValue::Boolean(v) => (
+                ffi::CorsairDataType_CT_Boolean,
+                ffi::CorsairDataValue { boolean: *v },
+            ),
+            PropertyValue::Int32(v) => (
+                ffi::CorsairDataType_CT_Int32,
+                ffi::CorsairDataValue { int32: *v },
+            ),
+            PropertyValue::Float64(v) => (
+                ffi::CorsairDataType_CT_Float64,
+                ffi::CorsairDataValue { float64: *v },
+            ),
+            PropertyValue::String(v) => {
+                strings.push(CString::new(v.as_str()).ok()?);
+                (
+                    ffi::CorsairDataType_CT_String,
+                    ffi::CorsairDataValue {
+                        string: strings[0].as_ptr().cast_mut(),
+                    },
+                )
+            }
+            PropertyValue::BooleanArray(v) => (
+                ffi::CorsairDataType_CT_Boolean_Array,
+                ffi::CorsairDataValue {
+                    boolean_array: ffi::CorsairDataType_BooleanArray {
+                        items: v.as_ptr().cast_mut(),
+                        count: count(v.len())?,
+                    },
+                },
+            ),
+            PropertyValue::Int32Array(v) => (
+                ffi::CorsairDataType_CT_Int32_Array,
+                ffi::CorsairDataValue {
+                    int32_array: ffi::CorsairDataType_Int32Array {
+                        items: v.as_ptr().cast_mut(),
+                        count: count(v.len())?,
+                    },
+                },
+            ),
+            PropertyValue::Float64Array(v) => (
+                ffi::CorsairDataType_CT_Float64_Array,
+                ffi::CorsairDataValue {
+                    float64_array: ffi::CorsairDataType_Float64Array {
+                        items: v.as_ptr().cast_mut(),
+                        count: count(v.len())?,
+                    },
+                },
+            ),
+            PropertyValue::StringArray(v) => {
+                strings = v
+                    .iter()
+                    .map(|s| CString::new(s.as_str()))
+                    .collect::<Result<_, _>>()
+                    .ok()?;
+                pointers = strings.iter().map(|s| s.as_ptr().cast_mut()).collect();
+                (
+                    ffi::CorsairDataType_CT_String_Array,
+                    ffi::CorsairDataValue {
+                        string_array: ffi::CorsairDataType_StringArray {
+                            items: pointers.as_mut_ptr(),
+                            count: count(pointers.len())?,
+                        },
+                    },
+                )
+            }
+        };
+        // Moving `strings` and `pointers` into the struct does not move their
+        // heap buffers, so the pointers taken above stay valid.
+        Some(Self {
+            raw: ffi::CorsairProperty { type_, value },
+            _strings: strings,
+            _pointers: pointers,
+            _value: PhantomData,
+        })
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const ffi::CorsairProperty {
+        &self.raw
+    }
 }
@@ -0,0 +1,394 @@
+//! Lighting effects that react to macro key presses.
+//!
+//! [`ReactiveEffects`] consumes [`Event::KeyEvent`]s for one device, maps
+//! each [`MacroKeyId`] to the LED of that key with a [`KeyMap`], and starts
+//! the configured [`Effect`]s from it.  Every frame, render the running
+//! effects into a compositor [`Layer`] or directly into an [`LedFrame`].
+//!
+//! Time is passed in explicitly, so effects can be rendered from a
+//! [`FrameScheduler`](crate::scheduler::FrameScheduler) callback or stepped
+//! deterministically in tests.
+//!
+//! ```
+//! # use std::time::{Duration, Instant};
+//! # use cue_sdk::{Event, LedPosition, MacroKeyId, Rgba};
+//! # use cue_sdk::reactive::{Decay, Effect, KeyMap, ReactiveEffects};
+//! # use cue_sdk::led::LedGroup;
+//! # let device_id = "device".parse().unwrap();
+//! let g1 = LedGroup::KeyboardGKeys.luid(1);
+//! let positions = [LedPosition { id: g1, cx: 0.0, cy: 0.0 }];
+//! let mut effects = ReactiveEffects::new(device_id, &positions).with_effect(Effect::FadeTrail {
+//!     color: Rgba::rgb(255, 0, 0),
+//!     decay: Decay::Linear(Duration::from_millis(500)),
+//! });
+//!
+//! let start = Instant::now();
+//! effects.handle_event(
+//!     &Event::KeyEvent { device_id, key_id: MacroKeyId::Key1, is_pressed: true },
+//!     start,
+//! );
+//! assert_eq!(effects.color(g1, start), Some(Rgba::rgb(255, 0, 0)));
+//! assert_eq!(effects.color(g1, start + Duration::from_secs(1)), None);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::compositor::{BlendMode, Layer};
+use crate::device::DeviceId;
+use crate::event::{Event, MacroKeyId};
+use crate::frame::LedFrame;
+use crate::led::{LedGroup, LedPosition, Rgba};
+
+// ---------------------------------------------------------------------------
+// KeyMap
+// ---------------------------------------------------------------------------
+
+/// Which LED belongs to each macro key of a device.
+///
+/// The default map assigns `KeyN` to LED `N` of the
+/// [`LedGroup::KeyboardGKeys`] group, which matches keyboards with G keys.
+/// Override single keys with [`insert`](Self::insert) for other devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    keys: HashMap<MacroKeyId, u32>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::g_keys()
+    }
+}
+
+impl KeyMap {
+    /// A map without any keys.
+    pub fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Map every macro key to the G key LED with the same number.
+    pub fn g_keys() -> Self {
+        let keys = MacroKeyId::ALL
+            .into_iter()
+            .map(|key| (key, LedGroup::KeyboardGKeys.luid(key.number() as u16)))
+            .collect();
+        Self { keys }
+    }
+
+    /// Map `key` to the LED `luid`, returning the previous LED.
+    pub fn insert(&mut self, key: MacroKeyId, luid: u32) -> Option<u32> {
+        self.keys.insert(key, luid)
+    }
+
+    /// Remove the mapping for `key`.
+    pub fn remove(&mut self, key: MacroKeyId) -> Option<u32> {
+        self.keys.remove(&key)
+    }
+
+    /// The LED of `key`, if it is mapped.
+    pub fn luid(&self, key: MacroKeyId) -> Option<u32> {
+        self.keys.get(&key).copied()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Decay
+// ---------------------------------------------------------------------------
+
+/// How an effect fades out after the key press that started it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decay {
+    /// Fade linearly to nothing over the given time.
+    Linear(Duration),
+    /// Halve the intensity every `half_life`.  The effect ends once it is
+    /// too faint to change an 8-bit color.
+    Exponential { half_life: Duration },
+}
+
+impl Decay {
+    /// The intensity `elapsed` after the press, from `1.0` down to `0.0`,
+    /// or `None` once the effect has ended.
+    pub fn level(&self, elapsed: Duration) -> Option<f32> {
+        let level = match *self {
+            Self::Linear(duration) if duration.is_zero() => return None,
+            Self::Linear(duration) => 1.0 - elapsed.as_secs_f32() / duration.as_secs_f32(),
+            Self::Exponential { half_life } if half_life.is_zero() => return None,
+            Self::Exponential { half_life } => {
+                0.5f32.powf(elapsed.as_secs_f32() / half_life.as_secs_f32())
+            }
+        };
+        (level >= 1.0 / 255.0).then_some(level.min(1.0))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Effect
+// ---------------------------------------------------------------------------
+
+/// An effect started by pressing a macro key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    /// A ring expanding outwards from the pressed key.
+    ///
+    /// `speed` is in LED position units per second and `width` is the
+    /// thickness of the ring in the same units.
+    Ripple {
+        color: Rgba,
+        speed: f64,
+        width: f64,
+        decay: Decay,
+    },
+    /// Light every LED of the device, then fade out.
+    Flash { color: Rgba, decay: Decay },
+    /// Light the pressed key, then fade out.  Pressing several keys in
+    /// quick succession leaves a fading trail.
+    FadeTrail { color: Rgba, decay: Decay },
+    /// Switch the pressed key between two states on every press, showing
+    /// `on` while it is toggled on and `off` (if any) while it is off.
+    Toggle { on: Rgba, off: Option<Rgba> },
+}
+
+impl Effect {
+    fn decay(&self) -> Option<Decay> {
+        match *self {
+            Self::Ripple { decay, .. }
+            | Self::Flash { decay, .. }
+            | Self::FadeTrail { decay, .. } => Some(decay),
+            Self::Toggle { .. } => None,
+        }
+    }
+}
+
+/// One running instance of an effect.
+#[derive(Debug, Clone, Copy)]
+struct Active {
+    effect: usize,
+    origin: LedPosition,
+    started: Instant,
+}
+
+// ---------------------------------------------------------------------------
+// ReactiveEffects
+// ---------------------------------------------------------------------------
+
+/// Key-press effects for one device.
+///
+/// Where running effects overlap, each color channel takes the brightest
+/// value.
+#[derive(Debug, Clone)]
+pub struct ReactiveEffects {
+    device_id: DeviceId,
+    positions: HashMap<u32, LedPosition>,
+    keys: KeyMap,
+    effects: Vec<Effect>,
+    active: Vec<Active>,
+    toggled: HashSet<MacroKeyId>,
+}
+
+impl ReactiveEffects {
+    /// Create reactive effects for the device with the given LED positions,
+    /// using the default [`KeyMap`] and no effects.
+    pub fn new(device_id: DeviceId, positions: &[LedPosition]) -> Self {
+        Self {
+            device_id,
+            positions: positions.iter().map(|pos| (pos.id, *pos)).collect(),
+            keys: KeyMap::default(),
+            effects: Vec::new(),
+            active: Vec::new(),
+            toggled: HashSet::new(),
+        }
+    }
+
+    /// Use the given key map.
+    pub fn with_keys(mut self, keys: KeyMap) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Start `effect` on every key press.
+    pub fn with_effect(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
+    /// The device these effects belong to.
+    pub fn device_id(&self) -> &DeviceId {
+        &self.device_id
+    }
+
+    /// The key map, for changing which LED belongs to a key.
+    pub fn keys_mut(&mut self) -> &mut KeyMap {
+        &mut self.keys
+    }
+
+    /// The configured effects.
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Remove every effect, stopping the running ones.  Toggle states are
+    /// kept.
+    pub fn clear_effects(&mut self) {
+        self.effects.clear();
+        self.active.clear();
+    }
+
+    // ---- Input ------------------------------------------------------------
+
+    /// React to an SDK event.
+    ///
+    /// Only key presses on this device start effects; returns whether the
+    /// event did.
+    pub fn handle_event(&mut self, event: &Event, now: Instant) -> bool {
+        match event {
+            Event::KeyEvent {
+                device_id,
+                key_id,
+                is_pressed: true,
+            } if *device_id == self.device_id => self.press(*key_id, now),
+            _ => false,
+        }
+    }
+
+    /// Start the effects for a press of `key`.
+    ///
+    /// Returns `false` if the key has no LED on this device.
+    pub fn press(&mut self, key: MacroKeyId, now: Instant) -> bool {
+        let Some(origin) = self
+            .keys
+            .luid(key)
+            .and_then(|luid| self.positions.get(&luid).copied())
+        else {
+            return false;
+        };
+        for (index, effect) in self.effects.iter().enumerate() {
+            if effect.decay().is_some() {
+                self.active.push(Active {
+                    effect: index,
+                    origin,
+                    started: now,
+                });
+            }
+        }
+        if !self.toggled.remove(&key) {
+            self.toggled.insert(key);
+        }
+        true
+    }
+
+    /// Whether `key` is toggled on.
+    pub fn is_toggled(&self, key: MacroKeyId) -> bool {
+        self.toggled.contains(&key)
+    }
+
+    /// Set the toggle state of `key`, e.g. to match an external setting.
+    pub fn set_toggled(&mut self, key: MacroKeyId, on: bool) {
+        if on {
+            self.toggled.insert(key);
+        } else {
+            self.toggled.remove(&key);
+        }
+    }
+
+    /// Number of effect instances still running at `now`.
+    pub fn active_count(&self, now: Instant) -> usize {
+        self.active
+            .iter()
+            .filter(|a| self.is_running(a, now))
+            .count()
+    }
+
+    // ---- Output -----------------------------------------------------------
+
+    /// The color the effects give an LED at `now`, if any.
+    pub fn color(&self, luid: u32, now: Instant) -> Option<Rgba> {
+        let pos = self.positions.get(&luid)?;
+        let running = self.active.iter().filter_map(|active| {
+            let elapsed = now.saturating_duration_since(active.started);
+            let effect = &self.effects[active.effect];
+            let level = effect.decay()?.level(elapsed)?;
+            let (color, intensity) = match *effect {
+                Effect::Ripple {
+                    color,
+                    speed,
+                    width,
+                    ..
+                } => {
+                    let radius = speed * elapsed.as_secs_f64();
+                    let distance = (pos.cx - active.origin.cx).hypot(pos.cy - active.origin.cy);
+                    let ring = 1.0 - (distance - radius).abs() / width.max(f64::EPSILON);
+                    (color, ring.max(0.0) as f32)
+                }
+                Effect::Flash { color, .. } => (color, 1.0),
+                Effect::FadeTrail { color, .. } if active.origin.id == luid => (color, 1.0),
+                _ => return None,
+            };
+            let alpha = (f32::from(color.a) * intensity * level).round() as u8;
+            (alpha > 0).then_some(Rgba::new(color.r, color.g, color.b, alpha))
+        });
+        let toggles = self.toggle_colors(luid);
+        running.chain(toggles).reduce(brightest)
+    }
+
+    /// Render the effects into `layer`, replacing its per-LED colors.
+    ///
+    /// Finished effects are dropped.
+    pub fn render(&mut self, now: Instant, layer: &mut Layer) {
+        self.prune(now);
+        layer.clear();
+        for &luid in self.positions.keys() {
+            if let Some(color) = self.color(luid, now) {
+                layer.set(luid, color);
+            }
+        }
+    }
+
+    /// Draw the effects over the current contents of `frame`.
+    ///
+    /// Finished effects are dropped.
+    pub fn render_frame(&mut self, now: Instant, frame: &mut LedFrame) {
+        self.prune(now);
+        for index in 0..frame.len() {
+            let below = frame.colors()[index];
+            if let Some(above) = self.color(below.id, now) {
+                frame.set_index(index, BlendMode::Normal.blend(below.color(), above, 1.0));
+            }
+        }
+    }
+
+    fn toggle_colors(&self, luid: u32) -> impl Iterator<Item = Rgba> + '_ {
+        let key = MacroKeyId::ALL
+            .into_iter()
+            .find(|&key| self.keys.luid(key) == Some(luid));
+        self.effects.iter().filter_map(move |effect| match *effect {
+            Effect::Toggle { on, off } => match key {
+                Some(key) if self.is_toggled(key) => Some(on),
+                Some(_) => off,
+                None => None,
+            },
+            _ => None,
+        })
+    }
+
+    fn is_running(&self, active: &Active, now: Instant) -> bool {
+        self.effects[active.effect]
+            .decay()
+            .and_then(|decay| decay.level(now.saturating_duration_since(active.started)))
+            .is_some()
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let active = std::mem::take(&mut self.active);
+        self.active = active
+            .into_iter()
+            .filter(|a| self.is_running(a, now))
+            .collect();
+    }
+}
+
+/// Combine two overlapping effect colors channel by channel.
+fn brightest(a: Rgba, b: Rgba) -> Rgba {
+    Rgba::new(a.r.max(b.r), a.g.max(b.g), a.b.max(b.b), a.a.max(b.a))
+}
@@ -0,0 +1,448 @@
+//! A live cache of connected devices.
+//!
+//! [`DeviceRegistry`] keeps the [`DeviceInfo`], LED positions and property
+//! [`Capabilities`] of every device so they do not have to be queried again
+//! for each operation.  Feed it the [`Event`]s from an event subscription and
+//! it adds and removes devices as they are plugged in and out.
+//!
+//! iCUE may assign a device a new [`DeviceId`] when it is reconnected, so the
+//! registry identifies devices by serial number.  An entry stays in the
+//! registry after its device disconnects and is updated in place when a
+//! device with the same serial number comes back.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use cue_sdk::registry::{DeviceRegistry, RegistryEvent};
+//! # let session = cue_sdk::connect().unwrap();
+//! # session.wait_for_connection(Duration::from_secs(5)).unwrap();
+//! let mut registry = DeviceRegistry::new(&session).unwrap();
+//! let events = session.subscribe_for_events().unwrap();
+//! while let Some(event) = events.recv() {
+//!     match registry.handle_event(&session, &event).unwrap() {
+//!         Some(RegistryEvent::Added { serial, .. }) => println!("added {serial}"),
+//!         Some(RegistryEvent::Removed { serial, .. }) => println!("removed {serial}"),
+//!         None => {}
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+
+use crate::device::{DeviceId, DeviceInfo, DeviceType};
+use crate::error::{Error, Result};
+use crate::event::Event;
+use crate::led::LedPosition;
+use crate::property::{PropertyFlags, PropertyId, PropertyInfo, PropertyValue};
+use crate::session::Session;
+
+// ---------------------------------------------------------------------------
+// Capabilities
+// ---------------------------------------------------------------------------
+
+/// The properties a device supports, with their type and access flags.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    properties: HashMap<PropertyId, PropertyInfo>,
+}
+
+impl Capabilities {
+    /// Query the supported properties of a device.
+    ///
+    /// Devices that do not report a property list have no capabilities;
+    /// this is not an error.
+    pub fn query(session: &Session, device_id: &DeviceId) -> Self {
+        let ids = match session.read_device_property(device_id, PropertyId::PropertyArray, 0) {
+            Ok(PropertyValue::Int32Array(ids)) => ids,
+            _ => return Self::default(),
+        };
+        let properties = ids
+            .into_iter()
+            .map(|raw| PropertyId::from_ffi(raw as u32))
+            .filter_map(|id| {
+                let info = session.get_device_property_info(device_id, id, 0).ok()?;
+                Some((id, info))
+            })
+            .collect();
+        Self { properties }
+    }
+
+    /// Whether the device has this property.
+    pub fn supports(&self, property: PropertyId) -> bool {
+        self.properties.contains_key(&property)
+    }
+
+    /// Type and flags of a property, if the device has it.
+    pub fn info(&self, property: PropertyId) -> Option<PropertyInfo> {
+        self.properties.get(&property).copied()
+    }
+
+    /// Whether the property can be read.
+    pub fn can_read(&self, property: PropertyId) -> bool {
+        self.has_flag(property, PropertyFlags::CAN_READ)
+    }
+
+    /// Whether the property can be written.
+    pub fn can_write(&self, property: PropertyId) -> bool {
+        self.has_flag(property, PropertyFlags::CAN_WRITE)
+    }
+
+    /// Iterate over the supported properties.
+    pub fn iter(&self) -> impl Iterator<Item = (PropertyId, PropertyInfo)> + '_ {
+        self.properties.iter().map(|(&id, &info)| (id, info))
+    }
+
+    fn has_flag(&self, property: PropertyId, flag: PropertyFlags) -> bool {
+        self.info(property)
+            .is_some_and(|info| info.flags.contains(flag))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// DeviceEntry
+// ---------------------------------------------------------------------------
+
+/// Everything the registry knows about one device.
+#[derive(Debug, Clone)]
+pub struct DeviceEntry {
+    /// Device information from the last time the device connected.
+    pub info: DeviceInfo,
+    /// LED positions, in the order reported by the SDK.
+    pub positions: Vec<LedPosition>,
+    /// Supported properties.
+    pub capabilities: Capabilities,
+    /// Whether the device is currently connected.
+    pub connected: bool,
+}
+
+impl DeviceEntry {
+    fn load(session: &Session, info: DeviceInfo) -> Result<Self> {
+        let positions = session.get_led_positions(&info.id)?;
+        let capabilities = Capabilities::query(session, &info.id);
+        Ok(Self {
+            info,
+            positions,
+            capabilities,
+            connected: true,
+        })
+    }
+
+    /// The key the registry stores this device under: its serial number, or
+    /// its device id for devices that report no serial.
+    pub fn stable_id(&self) -> String {
+        if self.info.serial.is_empty() {
+            self.info.id.to_string()
+        } else {
+            self.info.serial.clone()
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RegistryEvent
+// ---------------------------------------------------------------------------
+
+/// A change to the set of connected devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryEvent {
+    /// A device connected.  `reconnected` is `true` if the registry had seen
+    /// a device with this serial number before.
+    Added {
+        serial: String,
+        device_id: DeviceId,
+        reconnected: bool,
+    },
+    /// A device disconnected.  Its entry stays in the registry.
+    Removed { serial: String, device_id: DeviceId },
+}
+
+// ---------------------------------------------------------------------------
+// DeviceRegistry
+// ---------------------------------------------------------------------------
+
+/// A cache of devices that follows hot-plug events.
+#[derive(Debug, Default)]
+pub struct DeviceRegistry {
+    devices: HashMap<String, DeviceEntry>,
+    by_id: HashMap<DeviceId, String>,
+    listeners: Vec<mpsc::Sender<RegistryEvent>>,
+}
+
+impl DeviceRegistry {
+    /// Create a registry containing every currently connected device.
+    pub fn new(session: &Session) -> Result<Self> {
+        let mut registry = Self::default();
+        registry.refresh(session)?;
+        Ok(registry)
+    }
+
+    /// Re-enumerate devices, adding new ones and marking missing ones as
+    /// disconnected.
+    ///
+    /// Use this to resynchronise after missing events, e.g. after the
+    /// session reconnects.
+    ///
+    /// A device whose LED positions cannot be read is left out, and the
+    /// first such error is returned once every other change has been applied
+    /// and sent to subscribers.  The device is tried again on the next
+    /// refresh.
+    pub fn refresh(&mut self, session: &Session) -> Result<Vec<RegistryEvent>> {
+        let infos = session.get_devices(DeviceType::ALL)?;
+        let (changes, errors) = self.apply(infos, |info| DeviceEntry::load(session, info));
+        let failed = errors.len();
+        match errors.into_iter().next() {
+            Some((device_id, e)) => Err(e.with_detail(format!(
+                "{failed} device(s) not added, starting with {device_id}; {} other change(s) applied",
+                changes.len()
+            ))),
+            None => Ok(changes),
+        }
+    }
+
+    /// Update the registry from an SDK event.
+    ///
+    /// Returns the resulting change for `DeviceConnectionChanged` events and
+    /// `None` for all other events.
+    pub fn handle_event(
+        &mut self,
+        session: &Session,
+        event: &Event,
+    ) -> Result<Option<RegistryEvent>> {
+        match event {
+            Event::DeviceConnectionChanged {
+                device_id,
+                is_connected: true,
+            } => {
+                let info = session.get_device_info(device_id)?;
+                let entry = DeviceEntry::load(session, info)?;
+                Ok(Some(self.insert(entry)))
+            }
+            Event::DeviceConnectionChanged {
+                device_id,
+                is_connected: false,
+            } => Ok(self.remove(device_id)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Receive every future [`RegistryEvent`] on a channel.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<RegistryEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.listeners.push(tx);
+        rx
+    }
+
+    // ---- Lookup -----------------------------------------------------------
+
+    /// The device currently or last known under this id.
+    pub fn get(&self, device_id: &DeviceId) -> Option<&DeviceEntry> {
+        self.devices.get(self.by_id.get(device_id)?)
+    }
+
+    /// The device with this serial number.
+    pub fn by_serial(&self, serial: &str) -> Option<&DeviceEntry> {
+        self.devices.get(serial)
+    }
+
+    /// Iterate over all known devices, connected or not.
+    pub fn iter(&self) -> impl Iterator<Item = &DeviceEntry> + '_ {
+        self.devices.values()
+    }
+
+    /// Iterate over the connected devices.
+    pub fn connected(&self) -> impl Iterator<Item = &DeviceEntry> + '_ {
+        self.iter().filter(|entry| entry.connected)
+    }
+
+    /// Number of known devices, connected or not.
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// Whether the registry has never seen a device.
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Drop a disconnected device from the registry.
+    ///
+    /// Returns `None` if no device has this serial number or it is still
+    /// connected.
+    pub fn forget(&mut self, serial: &str) -> Option<DeviceEntry> {
+        if self.devices.get(serial)?.connected {
+            return None;
+        }
+        let entry = self.devices.remove(serial)?;
+        self.by_id.retain(|_, key| key != serial);
+        Some(entry)
+    }
+
+    // ---- Updates ----------------------------------------------------------
+
+    fn insert(&mut self, entry: DeviceEntry) -> RegistryEvent {
+        let key = entry.stable_id();
+        let device_id = entry.info.id;
+        // Drop the mapping for the id this device had before replugging.
+        if let Some(old) = self.devices.get(&key) {
+            if old.info.id != device_id {
+                self.by_id.remove(&old.info.id);
+            }
+        }
+        let reconnected = self.devices.insert(key.clone(), entry).is_some();
+        self.by_id.insert(device_id, key.clone());
+        self.emit(RegistryEvent::Added {
+            serial: key,
+            device_id,
+            reconnected,
+        })
+    }
+
+    /// Bring the registry in line with the devices in `infos`, loading the
+    /// entries of newly connected ones with `load`.
+    ///
+    /// Devices that fail to load are skipped and returned with their error.
+    fn apply(
+        &mut self,
+        infos: Vec<DeviceInfo>,
+        mut load: impl FnMut(DeviceInfo) -> Result<DeviceEntry>,
+    ) -> (Vec<RegistryEvent>, Vec<(DeviceId, Error)>) {
+        let mut changes = Vec::new();
+        let mut errors = Vec::new();
+
+        let present: Vec<DeviceId> = infos.iter().map(|info| info.id).collect();
+        let gone: Vec<DeviceId> = self
+            .connected()
+            .map(|entry| entry.info.id)
+            .filter(|id| !present.contains(id))
+            .collect();
+        for id in gone {
+            changes.extend(self.remove(&id));
+        }
+
+        for info in infos {
+            if self.get(&info.id).is_some_and(|entry| entry.connected) {
+                continue;
+            }
+            let device_id = info.id;
+            match load(info) {
+                Ok(entry) => changes.push(self.insert(entry)),
+                Err(e) => errors.push((device_id, e)),
+            }
+        }
+        (changes, errors)
+    }
+
+    fn remove(&mut self, device_id: &DeviceId) -> Option<RegistryEvent> {
+        let key = self.by_id.get(device_id)?.clone();
+        let entry = self.devices.get_mut(&key)?;
+        if !entry.connected {
+            return None;
+        }
+        entry.connected = false;
+        Some(self.emit(RegistryEvent::Removed {
+            serial: key,
+            device_id: *device_id,
+        }))
+    }
+
+    fn emit(&mut self, event: RegistryEvent) -> RegistryEvent {
+        self.listeners.retain(|tx| tx.send(event.clone()).is_ok());
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::{ErrorContext, Operation, SdkError};
+
+    fn info(id: &str, serial: &str) -> DeviceInfo {
+        DeviceInfo {
+            device_type: DeviceType::KEYBOARD,
+            id: id.parse().unwrap(),
+            serial: serial.to_owned(),
+            model: "K70".to_owned(),
+            led_count: 0,
+            channel_count: 0,
+        }
+    }
+
+    fn load(info: DeviceInfo) -> Result<DeviceEntry> {
+        Ok(DeviceEntry {
+            info,
+            positions: Vec::new(),
+            capabilities: Capabilities::default(),
+            connected: true,
+        })
+    }
+
+    #[test]
+    fn replugged_devices_are_rekeyed_by_serial() {
+        let mut registry = DeviceRegistry::default();
+        let rx = registry.subscribe();
+        let (old, new) = ("{old}".parse().unwrap(), "{new}".parse().unwrap());
+
+        registry.apply(vec![info("{old}", "SN1")], load);
+        let (changes, errors) = registry.apply(vec![info("{new}", "SN1")], load);
+        assert!(errors.is_empty());
+        assert_eq!(
+            changes,
+            vec![
+                RegistryEvent::Removed {
+                    serial: "SN1".to_owned(),
+                    device_id: old,
+                },
+                RegistryEvent::Added {
+                    serial: "SN1".to_owned(),
+                    device_id: new,
+                    reconnected: true,
+                },
+            ]
+        );
+        assert_eq!(rx.try_iter().count(), 3);
+
+        assert_eq!(registry.len(), 1);
+        assert!(registry.get(&old).is_none());
+        assert_eq!(registry.get(&new).unwrap().info.id, new);
+        assert!(registry.by_serial("SN1").unwrap().connected);
+    }
+
+    #[test]
+    fn a_device_that_fails_to_load_does_not_stop_the_others() {
+        let mut registry = DeviceRegistry::default();
+        registry.apply(vec![info("{a}", "A")], load);
+
+        let broken: DeviceId = "{b}".parse().unwrap();
+        let (changes, errors) = registry.apply(
+            vec![info("{b}", "B"), info("{c}", "C")],
+            |info: DeviceInfo| {
+                if info.id == broken {
+                    Err(ErrorContext::new(Operation::GetLedPositions)
+                        .device(&info.id)
+                        .error(SdkError::DeviceNotFound))
+                } else {
+                    load(info)
+                }
+            },
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, broken);
+        assert_eq!(changes.len(), 2);
+        assert!(!registry.by_serial("A").unwrap().connected);
+        assert!(registry.by_serial("B").is_none());
+        assert!(registry.by_serial("C").unwrap().connected);
+
+        // The broken device is added once it loads.
+        let (changes, errors) = registry.apply(vec![info("{b}", "B"), info("{c}", "C")], load);
+        assert!(errors.is_empty());
+        assert_eq!(
+            changes,
+            vec![RegistryEvent::Added {
+                serial: "B".to_owned(),
+                device_id: broken,
+                reconnected: false,
+            }]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Dumping every device property for support and debugging.
+//!
+//! A [`PropertyReport`] records the value of every readable property of
+//! every connected device.  The properties of a device are discovered from
+//! its [`PropertyArray`](PropertyId::PropertyArray); properties flagged
+//! [`INDEXED`](PropertyFlags::INDEXED) are read once per channel.  A property
+//! that cannot be read is recorded with its error instead of failing the
+//! whole report.
+//!
+//! Two reports can be compared with [`diff`](PropertyReport::diff), e.g. one
+//! taken on a working setup and one on a broken one.  With the `serde`
+//! feature, reports can be saved and loaded.
+//!
+//! ```no_run
+//! # use cue_sdk::report::PropertyReport;
+//! # let session = cue_sdk::connect().unwrap();
+//! # let working = PropertyReport::capture(&session).unwrap();
+//! let now = PropertyReport::capture(&session).unwrap();
+//! for change in working.diff(&now) {
+//!     println!("{change}");
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::device::{DeviceId, DeviceInfo, DeviceType};
+use crate::error::Result;
+use crate::property::{PropertyFlags, PropertyId, PropertyValue};
+use crate::session::Session;
+
+// ---------------------------------------------------------------------------
+// PropertyReading
+// ---------------------------------------------------------------------------
+
+/// One property of a device at one index.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyReading {
+    pub property: PropertyId,
+    /// The index read; always 0 for properties that are not indexed.
+    pub index: u32,
+    /// The value, or the error message if it could not be read.
+    pub value: std::result::Result<PropertyValue, String>,
+}
+
+// ---------------------------------------------------------------------------
+// DeviceReport
+// ---------------------------------------------------------------------------
+
+/// The properties of one device.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceReport {
+    /// The device id at capture time.
+    pub device_id: DeviceId,
+    /// The device serial number, used to match devices between reports.
+    pub serial: String,
+    pub model: String,
+    pub led_count: i32,
+    pub channel_count: i32,
+    /// Every readable property, in the order the device lists them.
+    pub properties: Vec<PropertyReading>,
+}
+
+impl DeviceReport {
+    /// Read every readable property of one device.
+    ///
+    /// A device that does not report a property list has no properties; this
+    /// is not an error.
+    pub fn capture(session: &Session, info: &DeviceInfo) -> Self {
+        let ids = match session.read_device_property(&info.id, PropertyId::PropertyArray, 0) {
+            Ok(PropertyValue::Int32Array(ids)) => ids,
+            _ => Vec::new(),
+        };
+        let mut properties = Vec::new();
+        for property in ids.into_iter().map(|raw| PropertyId::from_ffi(raw as u32)) {
+            let flags = match session.get_device_property_info(&info.id, property, 0) {
+                Ok(prop_info) => prop_info.flags,
+                Err(e) => {
+                    properties.push(PropertyReading {
+                        property,
+                        index: 0,
+                        value: Err(e.to_string()),
+                    });
+                    continue;
+                }
+            };
+            if !flags.contains(PropertyFlags::CAN_READ) {
+                continue;
+            }
+            let indices = if flags.contains(PropertyFlags::INDEXED) {
+                info.channel_count.max(1) as u32
+            } else {
+                1
+            };
+            for index in 0..indices {
+                let value = session
+                    .read_device_property(&info.id, property, index)
+                    .map_err(|e| e.to_string());
+                properties.push(PropertyReading {
+                    property,
+                    index,
+                    value,
+                });
+            }
+        }
+        Self {
+            device_id: info.id,
+            serial: info.serial.clone(),
+            model: info.model.clone(),
+            led_count: info.led_count,
+            channel_count: info.channel_count,
+            properties,
+        }
+    }
+
+    /// The reading of a property at an index.
+    pub fn get(&self, property: PropertyId, index: u32) -> Option<&PropertyReading> {
+        self.properties
+            .iter()
+            .find(|r| r.property == property && r.index == index)
+    }
+
+    fn same_device(&self, other: &DeviceReport) -> bool {
+        if self.serial.is_empty() {
+            self.device_id == other.device_id
+        } else {
+            self.serial == other.serial
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PropertyReport
+// ---------------------------------------------------------------------------
+
+/// The properties of every device.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyReport {
+    pub devices: Vec<DeviceReport>,
+}
+
+impl PropertyReport {
+    /// Read every readable property of every connected device.
+    pub fn capture(session: &Session) -> Result<Self> {
+        let infos = session.get_devices(DeviceType::ALL)?;
+        Ok(Self::capture_devices(session, &infos))
+    }
+
+    /// Read every readable property of the given devices.
+    pub fn capture_devices(session: &Session, devices: &[DeviceInfo]) -> Self {
+        Self {
+            devices: devices
+                .iter()
+                .map(|info| DeviceReport::capture(session, info))
+                .collect(),
+        }
+    }
+
+    /// The report of a device, looked up by serial number.
+    pub fn device(&self, serial: &str) -> Option<&DeviceReport> {
+        self.devices.iter().find(|d| d.serial == serial)
+    }
+
+    /// Everything that differs between this report and `after`.
+    ///
+    /// Devices are matched by serial number, or by id if they have none.
+    /// Changes are listed in the order devices and properties appear in the
+    /// reports.
+    pub fn diff(&self, after: &PropertyReport) -> Vec<ReportChange> {
+        let mut changes = Vec::new();
+        for before in &self.devices {
+            match after.devices.iter().find(|d| d.same_device(before)) {
+                Some(now) => diff_device(before, now, &mut changes),
+                None => changes.push(ReportChange::DeviceRemoved {
+                    serial: before.serial.clone(),
+                    model: before.model.clone(),
+                }),
+            }
+        }
+        for now in &after.devices {
+            if !self.devices.iter().any(|d| d.same_device(now)) {
+                changes.push(ReportChange::DeviceAdded {
+                    serial: now.serial.clone(),
+                    model: now.model.clone(),
+                });
+            }
+        }
+        changes
+    }
+}
+
+fn diff_device(before: &DeviceReport, after: &DeviceReport, changes: &mut Vec<ReportChange>) {
+    let serial = || after.serial.clone();
+    for old in &before.properties {
+        match after.get(old.property, old.index) {
+            Some(new) if new.value == old.value => {}
+            Some(new) => changes.push(ReportChange::PropertyChanged {
+                serial: serial(),
+                property: old.property,
+                index: old.index,
+                old: old.value.clone(),
+                new: new.value.clone(),
+            }),
+            None => changes.push(ReportChange::PropertyRemoved {
+                serial: serial(),
+                property: old.property,
+                index: old.index,
+                old: old.value.clone(),
+            }),
+        }
+    }
+    for new in &after.properties {
+        if before.get(new.property, new.index).is_none() {
+            changes.push(ReportChange::PropertyAdded {
+                serial: serial(),
+                property: new.property,
+                index: new.index,
+                new: new.value.clone(),
+            });
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ReportChange
+// ---------------------------------------------------------------------------
+
+/// One difference between two [`PropertyReport`]s.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReportChange {
+    /// A device is only in the second report.
+    DeviceAdded { serial: String, model: String },
+    /// A device is only in the first report.
+    DeviceRemoved { serial: String, model: String },
+    /// A property is only in the second report.
+    PropertyAdded {
+        serial: String,
+        property: PropertyId,
+        index: u32,
+        new: std::result::Result<PropertyValue, String>,
+    },
+    /// A property is only in the first report.
+    PropertyRemoved {
+        serial: String,
+        property: PropertyId,
+        index: u32,
+        old: std::result::Result<PropertyValue, String>,
+    },
+    /// A property has a different value or error.
+    PropertyChanged {
+        serial: String,
+        property: PropertyId,
+        index: u32,
+        old: std::result::Result<PropertyValue, String>,
+        new: std::result::Result<PropertyValue, String>,
+    },
+}
+
+impl fmt::Display for ReportChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DeviceAdded { serial, model } => write!(f, "+ {model} ({serial})"),
+            Self::DeviceRemoved { serial, model } => write!(f, "- {model} ({serial})"),
+            Self::PropertyAdded {
+                serial,
+                property,
+                index,
+                new,
+            } => write!(f, "+ {serial} {property}[{index}] = {}", Value(new)),
+            Self::PropertyRemoved {
+                serial,
+                property,
+                index,
+                old,
+            } => write!(f, "- {serial} {property}[{index}] = {}", Value(old)),
+            Self::PropertyChanged {
+                serial,
+                property,
+                index,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ {serial} {property}[{index}]: {} -> {}",
+                Value(old),
+                Value(new)
+            ),
+        }
+    }
+}
+
+/// Formats a reading's value, or its error in angle brackets.
+struct Value<'a>(&'a std::result::Result<PropertyValue, String>);
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Ok(value) => write!(f, "{value:?}"),
+            Err(error) => write!(f, "<{error}>"),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Opt-in retries for transient SDK failures.
+//!
+//! Right after the session connects, or while a device is being hot-plugged,
+//! iCUE briefly answers some calls with `NotConnected` or `DeviceNotFound`.
+//! A [`RetryPolicy`] installed with [`Session::set_retry_policy`] makes the
+//! session repeat such calls with a backoff; [`Session::with_retry`]
+//! overrides the policy for the calls made inside a closure.
+//!
+//! Only synchronous calls are retried.  Connecting, waiting for the
+//! connection, subscribing to events and the async flush are never repeated;
+//! use [`SessionBuilder::reconnect`] to repeat connection attempts.
+//!
+//! [`SessionBuilder::reconnect`]: crate::SessionBuilder::reconnect
+//! [`Session::set_retry_policy`]: crate::Session::set_retry_policy
+//! [`Session::with_retry`]: crate::Session::with_retry
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Operation, Result, SdkError};
+use crate::trace;
+
+// ---------------------------------------------------------------------------
+// Backoff
+// ---------------------------------------------------------------------------
+
+/// How long to wait between attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backoff {
+    /// Retry immediately.
+    None,
+    /// Wait the same time before every retry.
+    Fixed(Duration),
+    /// Wait `initial`, then multiply the delay by `factor` after every retry,
+    /// never exceeding `max`.
+    Exponential {
+        initial: Duration,
+        max: Duration,
+        factor: f64,
+    },
+}
+
+impl Backoff {
+    /// The delay before retry number `retry` (starting at 1).
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            Self::None => Duration::ZERO,
+            Self::Fixed(delay) => delay,
+            Self::Exponential {
+                initial,
+                max,
+                factor,
+            } => {
+                let scale = factor.max(1.0).powi(retry.saturating_sub(1) as i32);
+                Duration::try_from_secs_f64(initial.as_secs_f64() * scale)
+                    .map_or(max, |delay| delay.min(max))
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RetryEvent
+// ---------------------------------------------------------------------------
+
+/// Passed to [`RetryPolicy::on_retry`] hooks before each retry.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryEvent<'a> {
+    /// The attempt that just failed, starting at 1.
+    pub attempt: u32,
+    /// The error returned by that attempt.
+    pub error: &'a Error,
+    /// How long the policy will wait before the next attempt.
+    pub delay: Duration,
+    /// Time since the first attempt started.
+    pub elapsed: Duration,
+}
+
+impl RetryEvent<'_> {
+    /// The operation being retried, if the error recorded it.
+    pub fn operation(&self) -> Option<Operation> {
+        self.error.operation()
+    }
+}
+
+type RetryHook = Arc<dyn Fn(&RetryEvent<'_>) + Send + Sync>;
+
+// ---------------------------------------------------------------------------
+// RetryPolicy
+// ---------------------------------------------------------------------------
+
+/// When and how often to repeat a failed SDK call.
+///
+/// The default policy makes up to 3 attempts with exponential backoff from
+/// 25 ms to 500 ms, retrying `NotConnected`, `DeviceNotFound` and `Timeout`.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use cue_sdk::SdkError;
+/// # use cue_sdk::retry::{Backoff, RetryPolicy};
+/// let policy = RetryPolicy::default()
+///     .max_attempts(5)
+///     .backoff(Backoff::Fixed(Duration::from_millis(100)))
+///     .retry_on(&[SdkError::DeviceNotFound])
+///     .deadline(Duration::from_secs(2))
+///     .on_retry(|ev| eprintln!("retrying after {}", ev.error));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    retryable: Vec<SdkError>,
+    deadline: Option<Duration>,
+    hooks: Vec<RetryHook>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Backoff::Exponential {
+                initial: Duration::from_millis(25),
+                max: Duration::from_millis(500),
+                factor: 2.0,
+            },
+            retryable: vec![
+                SdkError::NotConnected,
+                SdkError::DeviceNotFound,
+                SdkError::Timeout,
+            ],
+            deadline: None,
+            hooks: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Make at most `attempts` attempts in total, including the first.
+    ///
+    /// Values below 1 are treated as 1.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Set the delay between attempts.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Retry only errors of these kinds.
+    pub fn retry_on(mut self, kinds: &[SdkError]) -> Self {
+        self.retryable = kinds.to_vec();
+        self
+    }
+
+    /// Give up once this much time has passed since the first attempt.
+    ///
+    /// A retry whose backoff would end after the deadline is not attempted.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Call `hook` before every retry, e.g. to log it.
+    ///
+    /// Hooks run in the order they were added, on the thread making the call.
+    pub fn on_retry(mut self, hook: impl Fn(&RetryEvent<'_>) + Send + Sync + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Whether errors of this kind are retried.
+    pub fn is_retryable(&self, kind: SdkError) -> bool {
+        self.retryable.contains(&kind)
+    }
+
+    /// Run `call` until it succeeds, fails with a non-retryable error, or the
+    /// attempt limit or deadline is reached.
+    ///
+    /// Returns the last error if every attempt failed.
+    pub fn run<T>(&self, mut call: impl FnMut() -> Result<T>) -> Result<T> {
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let error = match call() {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            if attempt >= self.max_attempts || !self.is_retryable(error.kind()) {
+                return Err(error);
+            }
+            let delay = self.backoff.delay(attempt);
+            let elapsed = started.elapsed();
+            if self.deadline.is_some_and(|d| elapsed + delay > d) {
+                return Err(error);
+            }
+            let event = RetryEvent {
+                attempt,
+                error: &error,
+                delay,
+                elapsed,
+            };
+            trace::event!(
+                debug,
+                operation = ?error.operation(),
+                attempt,
+                error = %error,
+                delay_ms = delay.as_millis() as u64,
+                "retrying SDK call"
+            );
+            for hook in &self.hooks {
+                hook(&event);
+            }
+            if !delay.is_zero() {
+                std::thread::sleep(delay);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("retryable", &self.retryable)
+            .field("deadline", &self.deadline)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Per-call overrides
+// ---------------------------------------------------------------------------
+
+thread_local! {
+    /// Policy set by `Session::with_retry` for calls on this thread.
+    static OVERRIDE: RefCell<Option<Arc<RetryPolicy>>> = const { RefCell::new(None) };
+}
+
+/// The policy overriding the session default on this thread, if any.
+pub(crate) fn current_override() -> Option<Arc<RetryPolicy>> {
+    OVERRIDE.with(|o| o.borrow().clone())
+}
+
+/// Run `f` with `policy` overriding the session default on this thread.
+///
+/// The previous override is restored afterwards, even if `f` panics.
+pub(crate) fn with_override<R>(policy: Arc<RetryPolicy>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Arc<RetryPolicy>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OVERRIDE.with(|o| *o.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(OVERRIDE.with(|o| o.borrow_mut().replace(policy)));
+    f()
+}
@@ -0,0 +1,463 @@
+//! Fixed-rate LED frame rendering.
+//!
+//! A [`FrameScheduler`] owns one [`LedFrame`] per device and drives a render
+//! callback at a target frame rate.  Each frame it buffers the changed LEDs
+//! of every device with `set_led_colors_buffer` and then issues a single
+//! non-blocking flush.  If the previous flush is still in flight when the next
+//! frame is due, that frame is dropped rather than queued, so a slow iCUE
+//! server lowers the frame rate instead of building up latency.  A flush that
+//! has not completed within the [flush
+//! timeout](FrameScheduler::with_flush_timeout) is abandoned and reported as
+//! [`SdkError::Timeout`], so a server that stops answering cannot stall the
+//! scheduler for good.
+
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use crate::device::DeviceId;
+use crate::error::{ErrorContext, Operation, Result, SdkError};
+use crate::frame::LedFrame;
+use crate::session::{PendingFlush, Session};
+
+/// Window over which [`FrameStats::actual_fps`] is measured.
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Default flush timeout, in frame intervals.
+const FLUSH_TIMEOUT_FRAMES: u32 = 4;
+
+// ---------------------------------------------------------------------------
+// FrameStats
+// ---------------------------------------------------------------------------
+
+/// Timing statistics collected by a [`FrameScheduler`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    /// Frames rendered and sent to the SDK.
+    pub frames_sent: u64,
+    /// Frames skipped because the previous flush had not completed or the
+    /// scheduler fell more than one frame behind.
+    pub frames_dropped: u64,
+    /// Flushes abandoned because the SDK did not signal completion within
+    /// the flush timeout.
+    pub flushes_timed_out: u64,
+    /// Frames sent during the last second.
+    pub actual_fps: f64,
+    /// Latency of the most recently completed flush.
+    pub last_flush_latency: Duration,
+    /// Shortest flush latency observed.
+    pub min_flush_latency: Duration,
+    /// Longest flush latency observed.
+    pub max_flush_latency: Duration,
+    /// Mean flush latency over all completed flushes.
+    pub mean_flush_latency: Duration,
+}
+
+// ---------------------------------------------------------------------------
+// FrameContext
+// ---------------------------------------------------------------------------
+
+/// Timing information passed to the render callback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameContext {
+    /// Index of the frame being rendered, counting only sent frames.
+    pub frame: u64,
+    /// Time since the scheduler started.
+    pub elapsed: Duration,
+    /// Time since the previous rendered frame.
+    pub delta: Duration,
+}
+
+// ---------------------------------------------------------------------------
+// TickOutcome
+// ---------------------------------------------------------------------------
+
+/// What happened during one [`FrameScheduler::tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickOutcome {
+    /// A frame was rendered; `leds` changed LEDs were sent.
+    Sent { leds: usize },
+    /// A frame was rendered but nothing changed, so no flush was issued.
+    Unchanged,
+    /// The previous flush had not completed; the frame was dropped.
+    Dropped,
+}
+
+// ---------------------------------------------------------------------------
+// FrameScheduler
+// ---------------------------------------------------------------------------
+
+/// Renders and sends LED frames at a fixed target rate.
+///
+/// ```no_run
+/// # use std::ops::ControlFlow;
+/// # use cue_sdk::{DeviceType, LedFrame, Rgba};
+/// # use cue_sdk::scheduler::FrameScheduler;
+/// # let session = cue_sdk::connect().unwrap();
+/// let mut scheduler = FrameScheduler::new(30.0);
+/// for dev in session.get_devices(DeviceType::ALL).unwrap() {
+///     scheduler.add_frame(LedFrame::for_device(&session, &dev.id).unwrap());
+/// }
+/// scheduler
+///     .run(&session, |frames, ctx| {
+///         let level = (ctx.frame % 256) as u8;
+///         for frame in frames.iter_mut() {
+///             frame.fill(Rgba::rgb(level, 0, 255 - level));
+///         }
+///         ControlFlow::Continue(())
+///     })
+///     .unwrap();
+/// ```
+pub struct FrameScheduler {
+    interval: Duration,
+    flush_timeout: Duration,
+    frames: Vec<LedFrame>,
+    pending: Option<PendingFlush>,
+    started: Option<Instant>,
+    next_deadline: Option<Instant>,
+    last_render: Option<Instant>,
+    sent_at: VecDeque<Instant>,
+    latency_total: Duration,
+    latency_count: u32,
+    stats: FrameStats,
+}
+
+impl FrameScheduler {
+    /// Create a scheduler targeting `target_fps` frames per second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `target_fps` is not a positive, finite number, or if the
+    /// frame interval it gives is zero or too long to represent.
+    pub fn new(target_fps: f64) -> Self {
+        assert!(
+            target_fps.is_finite() && target_fps > 0.0,
+            "target_fps must be positive"
+        );
+        let interval =
+            Duration::try_from_secs_f64(target_fps.recip()).expect("target_fps is too low");
+        assert!(!interval.is_zero(), "target_fps is too high");
+        Self {
+            interval,
+            flush_timeout: interval.saturating_mul(FLUSH_TIMEOUT_FRAMES),
+            frames: Vec::new(),
+            pending: None,
+            started: None,
+            next_deadline: None,
+            last_render: None,
+            sent_at: VecDeque::new(),
+            latency_total: Duration::ZERO,
+            latency_count: 0,
+            stats: FrameStats::default(),
+        }
+    }
+
+    /// Abandon a flush that has not completed `timeout` after it started.
+    ///
+    /// Defaults to four frame intervals.
+    pub fn with_flush_timeout(mut self, timeout: Duration) -> Self {
+        self.flush_timeout = timeout;
+        self
+    }
+
+    /// The time between frames.
+    pub fn frame_interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// How long a flush may take before it is abandoned.
+    pub fn flush_timeout(&self) -> Duration {
+        self.flush_timeout
+    }
+
+    /// Add a device frame, replacing any existing frame for the same device.
+    pub fn add_frame(&mut self, frame: LedFrame) {
+        self.remove_frame(frame.device_id());
+        self.frames.push(frame);
+    }
+
+    /// Remove and return the frame for a device.
+    pub fn remove_frame(&mut self, device_id: &DeviceId) -> Option<LedFrame> {
+        let idx = self
+            .frames
+            .iter()
+            .position(|f| f.device_id() == device_id)?;
+        Some(self.frames.remove(idx))
+    }
+
+    /// The frame for a device.
+    pub fn frame_mut(&mut self, device_id: &DeviceId) -> Option<&mut LedFrame> {
+        self.frames.iter_mut().find(|f| f.device_id() == device_id)
+    }
+
+    /// All device frames.
+    pub fn frames(&self) -> &[LedFrame] {
+        &self.frames
+    }
+
+    /// Timing statistics collected so far.
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    /// Run one frame: sleep until it is due, then render and send it unless
+    /// the previous flush is still in flight.
+    ///
+    /// Returns the error of a failed buffer call or of the previous flush.
+    /// If the previous flush has not completed within the flush timeout, it
+    /// is abandoned and this returns [`SdkError::Timeout`]; the next tick
+    /// sends a frame again.
+    pub fn tick(
+        &mut self,
+        session: &Session,
+        render: impl FnOnce(&mut [LedFrame], FrameContext),
+    ) -> Result<TickOutcome> {
+        self.wait_for_deadline();
+        let now = Instant::now();
+
+        if let Some(result) = self.poll_pending(now) {
+            result?;
+        }
+        if self.pending.is_some() {
+            self.stats.frames_dropped += 1;
+            return Ok(TickOutcome::Dropped);
+        }
+
+        let started = *self.started.get_or_insert(now);
+        let ctx = FrameContext {
+            frame: self.stats.frames_sent,
+            elapsed: now - started,
+            delta: self.last_render.map_or(Duration::ZERO, |t| now - t),
+        };
+        self.last_render = Some(now);
+        render(&mut self.frames, ctx);
+
+        let mut leds = 0;
+        for frame in &mut self.frames {
+            leds += frame.buffer_changes(session)?;
+        }
+        self.record_sent(now);
+        if leds == 0 {
+            return Ok(TickOutcome::Unchanged);
+        }
+
+        self.pending = Some(session.start_flush_led_colors()?);
+        Ok(TickOutcome::Sent { leds })
+    }
+
+    /// Call [`tick`](Self::tick) until `render` returns
+    /// [`ControlFlow::Break`] or an SDK call fails.
+    ///
+    /// The frame on which `render` breaks is still sent.
+    pub fn run(
+        &mut self,
+        session: &Session,
+        mut render: impl FnMut(&mut [LedFrame], FrameContext) -> ControlFlow<()>,
+    ) -> Result<()> {
+        let mut flow = ControlFlow::Continue(());
+        while flow.is_continue() {
+            self.tick(session, |frames, ctx| flow = render(frames, ctx))?;
+        }
+        self.finish()
+    }
+
+    /// Wait for any in-flight flush to complete.
+    ///
+    /// Returns [`SdkError::Timeout`] if it has not completed within the flush
+    /// timeout.
+    pub fn finish(&mut self) -> Result<()> {
+        let Some(mut flush) = self.pending.take() else {
+            return Ok(());
+        };
+        let remaining = self.flush_timeout.saturating_sub(flush.started().elapsed());
+        let result = flush.wait_timeout(remaining);
+        if let Some(latency) = flush.latency() {
+            self.record_latency(latency);
+        } else if flush.try_result().is_none() {
+            self.stats.flushes_timed_out += 1;
+        }
+        result
+    }
+
+    fn wait_for_deadline(&mut self) {
+        let now = Instant::now();
+        let deadline = *self.next_deadline.get_or_insert(now);
+        if deadline > now {
+            std::thread::sleep(deadline - now);
+        }
+        let (next, missed) = advance_deadline(deadline, self.interval, Instant::now());
+        self.stats.frames_dropped = self.stats.frames_dropped.saturating_add(missed);
+        self.next_deadline = Some(next);
+    }
+
+    /// The result of the pending flush once it has completed or timed out.
+    fn poll_pending(&mut self, now: Instant) -> Option<Result<()>> {
+        let flush = self.pending.as_mut()?;
+        if let Some(result) = flush.try_result() {
+            if let Some(latency) = flush.latency() {
+                self.record_latency(latency);
+            }
+            self.pending = None;
+            return Some(result);
+        }
+        if now.saturating_duration_since(flush.started()) < self.flush_timeout {
+            return None;
+        }
+        // Give up on it; a late callback is discarded.
+        self.pending = None;
+        self.stats.flushes_timed_out += 1;
+        Some(Err(
+            ErrorContext::new(Operation::FlushLedColors).error(SdkError::Timeout)
+        ))
+    }
+
+    fn record_latency(&mut self, latency: Duration) {
+        let stats = &mut self.stats;
+        stats.last_flush_latency = latency;
+        stats.max_flush_latency = stats.max_flush_latency.max(latency);
+        stats.min_flush_latency = if self.latency_count == 0 {
+            latency
+        } else {
+            stats.min_flush_latency.min(latency)
+        };
+        self.latency_total += latency;
+        self.latency_count += 1;
+        stats.mean_flush_latency = self.latency_total / self.latency_count;
+    }
+
+    fn record_sent(&mut self, now: Instant) {
+        self.stats.frames_sent += 1;
+        self.sent_at.push_back(now);
+        while self.sent_at.front().is_some_and(|&t| now - t > FPS_WINDOW) {
+            self.sent_at.pop_front();
+        }
+        self.stats.actual_fps = self.sent_at.len() as f64 / FPS_WINDOW.as_secs_f64();
+    }
+}
+
+/// The deadline of the frame after the one due at `deadline`, and how many
+/// frames were skipped because `now` is already past it.
+fn advance_deadline(deadline: Instant, interval: Duration, now: Instant) -> (Instant, u64) {
+    let next = deadline + interval;
+    if next > now {
+        return (next, 0);
+    }
+    // More than a whole frame behind: skip the missed frames instead of
+    // rendering a burst to catch up, keeping deadlines on the original grid.
+    let behind = (now - next).as_nanos();
+    let interval_nanos = interval.as_nanos();
+    let missed = behind / interval_nanos + 1;
+    let into_frame = behind % interval_nanos;
+    // `into_frame` is less than `interval`, so its seconds fit in a `u64`.
+    let into_frame = Duration::new(
+        (into_frame / 1_000_000_000) as u64,
+        (into_frame % 1_000_000_000) as u32,
+    );
+    (
+        now + (interval - into_frame),
+        u64::try_from(missed).unwrap_or(u64::MAX),
+    )
+}
+
+// These tests exercise the deadline arithmetic and flush bookkeeping on their
+// own and do not call into the iCUE SDK.
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn on_time_frames_advance_by_one_interval() {
+        let start = Instant::now();
+        assert_eq!(
+            advance_deadline(start, 10 * MS, start),
+            (start + 10 * MS, 0)
+        );
+        assert_eq!(
+            advance_deadline(start, 10 * MS, start + 9 * MS),
+            (start + 10 * MS, 0)
+        );
+    }
+
+    #[test]
+    fn late_frames_are_dropped_and_the_grid_is_kept() {
+        let start = Instant::now();
+        // Exactly one frame late: that frame is dropped.
+        assert_eq!(
+            advance_deadline(start, 10 * MS, start + 10 * MS),
+            (start + 20 * MS, 1)
+        );
+        // 3.5 frames behind the next deadline: frames at 10, 20, 30 and 40 ms
+        // are missed; the next one is due at 50 ms.
+        assert_eq!(
+            advance_deadline(start, 10 * MS, start + 45 * MS),
+            (start + 50 * MS, 4)
+        );
+    }
+
+    #[test]
+    fn long_stalls_with_short_intervals_do_not_overflow() {
+        let start = Instant::now();
+        let interval = Duration::from_nanos(1);
+        let stall = Duration::from_secs(10 * 24 * 3600);
+        let (next, missed) = advance_deadline(start, interval, start + stall);
+        assert_eq!(missed, stall.as_nanos() as u64);
+        assert_eq!(next, start + stall + interval);
+        // More than `u32::MAX` frames, which the old cast truncated.
+        assert!(missed > u64::from(u32::MAX));
+    }
+
+    #[test]
+    fn frame_rates_must_give_a_nonzero_interval() {
+        assert_eq!(FrameScheduler::new(50.0).frame_interval(), 20 * MS);
+        assert_eq!(
+            FrameScheduler::new(1e9).frame_interval(),
+            Duration::from_nanos(1)
+        );
+        for fps in [1e10, f64::MAX, 0.0, -1.0, f64::NAN, f64::INFINITY, 1e-300] {
+            let panicked = std::panic::catch_unwind(|| FrameScheduler::new(fps)).is_err();
+            assert!(panicked, "{fps}");
+        }
+    }
+
+    #[test]
+    fn flushes_that_never_complete_time_out() {
+        let mut scheduler = FrameScheduler::new(100.0);
+        assert_eq!(scheduler.flush_timeout(), 40 * MS);
+        // The sender stays alive and never sends, like a lost SDK callback.
+        let (_tx, rx) = mpsc::channel();
+        let flush = PendingFlush::new(rx);
+        let started = flush.started();
+        scheduler.pending = Some(flush);
+
+        assert_eq!(scheduler.poll_pending(started + 39 * MS), None);
+        assert!(scheduler.pending.is_some());
+
+        let err = scheduler
+            .poll_pending(started + 40 * MS)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.kind(), SdkError::Timeout);
+        assert_eq!(err.operation(), Some(Operation::FlushLedColors));
+        assert!(scheduler.pending.is_none());
+        assert_eq!(scheduler.stats().flushes_timed_out, 1);
+        assert_eq!(scheduler.poll_pending(started + 80 * MS), None);
+    }
+
+    #[test]
+    fn finish_gives_up_on_a_flush_that_never_completes() {
+        let mut scheduler = FrameScheduler::new(100.0).with_flush_timeout(20 * MS);
+        let (_tx, rx) = mpsc::channel();
+        scheduler.pending = Some(PendingFlush::new(rx));
+
+        let err = scheduler.finish().unwrap_err();
+        assert_eq!(err.kind(), SdkError::Timeout);
+        assert_eq!(scheduler.stats().flushes_timed_out, 1);
+        assert_eq!(scheduler.stats().last_flush_latency, Duration::ZERO);
+        // Nothing is left to wait for.
+        assert_eq!(scheduler.finish(), Ok(()));
+    }
+}
@@ -0,0 +1,154 @@
+//! Internal entry points for the iCUE SDK functions.
+//!
+//! Everything in the crate calls the SDK through this module instead of
+//! `cue_sdk_sys` directly.  Without the `dynamic` feature the functions are
+//! the linked `cue_sdk_sys` externs.  With it, they call through a table of
+//! function pointers resolved by [`dynamic::load`](crate::dynamic::load) and
+//! return `CE_NotConnected` until the library has been loaded, so no SDK
+//! symbol is referenced at link time.
+
+#![allow(non_snake_case)]
+
+use cue_sdk_sys as ffi;
+
+#[cfg(feature = "dynamic")]
+use core::ffi::{c_char, c_int, c_uint, c_void};
+#[cfg(feature = "dynamic")]
+use std::sync::OnceLock;
+
+macro_rules! sdk_functions {
+    ($( fn $name:ident($($arg:ident: $ty:ty),* $(,)?); )*) => {
+        #[cfg(not(feature = "dynamic"))]
+        pub(crate) use ffi::{$($name),*};
+
+        /// The resolved SDK functions.  The library is never unloaded, as
+        /// SDK threads may still call back into this process.
+        #[cfg(feature = "dynamic")]
+        pub(crate) struct Api {
+            _library: libloading::Library,
+            $($name: unsafe extern "C" fn($($ty),*) -> ffi::CorsairError,)*
+        }
+
+        #[cfg(feature = "dynamic")]
+        impl Api {
+            /// Resolve every function used by the crate from `library`.
+            ///
+            /// Returns the name of the first missing symbol on failure.
+            ///
+            /// # Safety
+            ///
+            /// `library` must be the iCUE SDK, so that each symbol has the
+            /// signature declared in `cue_sdk_sys`.
+            pub(crate) unsafe fn resolve(
+                library: libloading::Library,
+            ) -> Result<Self, &'static str> {
+                $(
+                    // SAFETY: The caller guarantees the symbol has this
+                    // signature.  The pointer is kept together with the
+                    // library, which is never unloaded.
+                    let $name = *unsafe {
+                        library.get::<unsafe extern "C" fn($($ty),*) -> ffi::CorsairError>(
+                            concat!(stringify!($name), "\0").as_bytes(),
+                        )
+                    }
+                    .map_err(|_| stringify!($name))?;
+                )*
+                Ok(Self {
+                    _library: library,
+                    $($name,)*
+                })
+            }
+        }
+
+        $(
+            #[cfg(feature = "dynamic")]
+            #[must_use]
+            pub(crate) unsafe fn $name($($arg: $ty),*) -> ffi::CorsairError {
+                match API.get() {
+                    // SAFETY: The caller upholds the SDK function's contract;
+                    // the pointer was resolved from the loaded library.
+                    Some(api) => unsafe { (api.$name)($($arg),*) },
+                    None => ffi::CorsairError_CE_NotConnected,
+                }
+            }
+        )*
+    };
+}
+
+sdk_functions! {
+    fn CorsairConnect(on_state_changed: ffi::CorsairSessionStateChangedHandler, context: *mut c_void);
+    fn CorsairGetSessionDetails(details: *mut ffi::CorsairSessionDetails);
+    fn CorsairDisconnect();
+    fn CorsairGetDevices(
+        filter: *const ffi::CorsairDeviceFilter,
+        size_max: c_int,
+        devices: *mut ffi::CorsairDeviceInfo,
+        size: *mut c_int,
+    );
+    fn CorsairGetDeviceInfo(device_id: *const c_char, device_info: *mut ffi::CorsairDeviceInfo);
+    fn CorsairGetLedPositions(
+        device_id: *const c_char,
+        size_max: c_int,
+        led_positions: *mut ffi::CorsairLedPosition,
+        size: *mut c_int,
+    );
+    fn CorsairSubscribeForEvents(on_event: ffi::CorsairEventHandler, context: *mut c_void);
+    fn CorsairUnsubscribeFromEvents();
+    fn CorsairConfigureKeyEvent(
+        device_id: *const c_char,
+        config: *const ffi::CorsairKeyEventConfiguration,
+    );
+    fn CorsairGetDevicePropertyInfo(
+        device_id: *const c_char,
+        property_id: ffi::CorsairDevicePropertyId,
+        index: c_uint,
+        data_type: *mut ffi::CorsairDataType,
+        flags: *mut c_uint,
+    );
+    fn CorsairReadDeviceProperty(
+        device_id: *const c_char,
+        property_id: ffi::CorsairDevicePropertyId,
+        index: c_uint,
+        property: *mut ffi::CorsairProperty,
+    );
+    fn CorsairWriteDeviceProperty(
+        device_id: *const c_char,
+        property_id: ffi::CorsairDevicePropertyId,
+        index: c_uint,
+        property: *const ffi::CorsairProperty,
+    );
+    fn CorsairFreeProperty(property: *mut ffi::CorsairProperty);
+    fn CorsairSetLedColors(device_id: *const c_char, size: c_int, led_colors: *const ffi::CorsairLedColor);
+    fn CorsairSetLedColorsBuffer(
+        device_id: *const c_char,
+        size: c_int,
+        led_colors: *const ffi::CorsairLedColor,
+    );
+    fn CorsairSetLedColorsFlushBufferAsync(callback: ffi::CorsairAsyncCallback, context: *mut c_void);
+    fn CorsairGetLedColors(device_id: *const c_char, size: c_int, led_colors: *mut ffi::CorsairLedColor);
+    fn CorsairSetLayerPriority(priority: c_uint);
+    fn CorsairGetLedLuidForKeyName(
+        device_id: *const c_char,
+        key_name: c_char,
+        led_id: *mut ffi::CorsairLedLuid,
+    );
+    fn CorsairRequestControl(device_id: *const c_char, access_level: ffi::CorsairAccessLevel);
+    fn CorsairReleaseControl(device_id: *const c_char);
+}
+
+/// The loaded SDK, set once by [`install`].
+#[cfg(feature = "dynamic")]
+static API: OnceLock<Api> = OnceLock::new();
+
+/// Whether the SDK functions have been loaded.
+#[cfg(feature = "dynamic")]
+pub(crate) fn is_loaded() -> bool {
+    API.get().is_some()
+}
+
+/// Make `api` the SDK used by every call.  Does nothing if one is already
+/// installed.
+#[cfg(feature = "dynamic")]
+pub(crate) fn install(api: Api) {
+    let _ = API.set(api);
+}
@@ -1,18 +1,31 @@
+use std::collections::{HashMap, HashSet};
 use std::mem::MaybeUninit;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, TryLockError, Weak};
+use std::time::{Duration, Instant};
 
 use core::ffi::{c_char, c_int};
 use cue_sdk_sys as ffi;
 
+use crate::builder::SessionBuilder;
 use crate::callback::{self, SessionStateChange};
-use crate::device::{DeviceId, DeviceInfo, DeviceType};
-use crate::error::{self, Result, SdkError};
+use crate::capabilities::ServerCapabilities;
+use crate::device::{ChannelDevice, Device, DeviceId, DeviceInfo, DeviceType};
+use crate::error::{Error, ErrorContext, Operation, Result, SdkError};
 #[cfg(feature = "async")]
 use crate::event::AsyncEventSubscription;
 use crate::event::{EventSubscription, MacroKeyId};
+use crate::guard::{ControlGuard, KeyInterceptGuard};
 use crate::led::{LedColor, LedPosition};
-use crate::property::{DataType, PropertyFlags, PropertyId, PropertyInfo, PropertyValue};
+use crate::property::{
+    DataType, FfiProperty, FromPropertyValue, PropertyFlags, PropertyId, PropertyInfo,
+    PropertyValue,
+};
+use crate::retry::{self, RetryPolicy};
+use crate::sdk;
+use crate::snapshot::LightingSnapshot;
+use crate::trace;
+use crate::validate;
 use std::ptr;
 
 // ---------------------------------------------------------------------------
@@ -20,7 +33,10 @@ use std::ptr;
 // ---------------------------------------------------------------------------
 
 /// A semantic version triple as reported by the SDK.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Versions compare by major, then minor, then patch, and parse from
+/// `major.minor` or `major.minor.patch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Version {
     pub major: i32,
     pub minor: i32,
@@ -28,6 +44,15 @@ pub struct Version {
 }
 
 impl Version {
+    /// Create a version from its parts.
+    pub const fn new(major: i32, minor: i32, patch: i32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
     pub(crate) fn from_ffi(v: &ffi::CorsairVersion) -> Self {
         Self {
             major: v.major,
@@ -43,19 +68,46 @@ impl std::fmt::Display for Version {
     }
 }
 
+impl std::str::FromStr for Version {
+    type Err = SdkError;
+
+    /// Parse `major.minor` or `major.minor.patch`.
+    ///
+    /// Returns `SdkError::InvalidArguments` for anything else.
+    fn from_str(s: &str) -> std::result::Result<Self, SdkError> {
+        let parts = s
+            .trim()
+            .split('.')
+            .map(|part| part.parse::<u32>().ok().and_then(|n| i32::try_from(n).ok()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(SdkError::InvalidArguments)?;
+        match parts[..] {
+            [major, minor] => Ok(Self::new(major, minor, 0)),
+            [major, minor, patch] => Ok(Self::new(major, minor, patch)),
+            _ => Err(SdkError::InvalidArguments),
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // SessionDetails
 // ---------------------------------------------------------------------------
 
 /// Version information about the client, server, and host.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SessionDetails {
+    /// The version of the SDK library this program uses.
     pub client_version: Version,
+    /// The version of the SDK server inside iCUE.
     pub server_version: Version,
+    /// The version of iCUE itself.
     pub server_host_version: Version,
 }
 
 impl SessionDetails {
+    /// The oldest SDK server this crate supports.
+    pub const MIN_SERVER_VERSION: Version = Version::new(4, 0, 0);
+
     pub(crate) fn from_ffi(d: &ffi::CorsairSessionDetails) -> Self {
         Self {
             client_version: Version::from_ffi(&d.clientVersion),
@@ -63,6 +115,35 @@ impl SessionDetails {
             server_host_version: Version::from_ffi(&d.serverHostVersion),
         }
     }
+
+    /// Check that the server speaks the client's protocol and is at least
+    /// `minimum`.
+    ///
+    /// The server must have the same major version as the client.  On
+    /// failure the error is [`SdkError::IncompatibleProtocol`] and its
+    /// [`detail`](Error::detail) names all three versions.
+    pub fn check_server(&self, minimum: Version) -> Result<()> {
+        let server = self.server_version;
+        if server.major == self.client_version.major && server >= minimum {
+            return Ok(());
+        }
+        let detail = format!(
+            "client {}, server {}, host {}; requires a {}.x server of at least {}",
+            self.client_version,
+            server,
+            self.server_host_version,
+            self.client_version.major,
+            minimum
+        );
+        trace::event!(warn, %detail, "incompatible iCUE server");
+        Err(ErrorContext::new(Operation::WaitForConnection)
+            .error_with_detail(SdkError::IncompatibleProtocol, detail))
+    }
+
+    /// The features the server supports.
+    pub fn capabilities(&self) -> ServerCapabilities {
+        ServerCapabilities::new(self.server_version)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -103,6 +184,7 @@ impl SessionState {
 
 /// SDK access level for a device.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum AccessLevel {
     Shared = ffi::CorsairAccessLevel_CAL_Shared,
@@ -112,6 +194,111 @@ pub enum AccessLevel {
         ffi::CorsairAccessLevel_CAL_ExclusiveLightingControlAndKeyEventsListening,
 }
 
+// ---------------------------------------------------------------------------
+// PendingFlush
+// ---------------------------------------------------------------------------
+
+/// An LED flush that has been handed to the SDK but may not have completed.
+///
+/// Returned by [`Session::start_flush_led_colors`].  The SDK shares the
+/// callback's sender, so a `PendingFlush` can be dropped at any time; a
+/// callback arriving afterwards is discarded.  If the callback never
+/// arrives, the sender is freed when the [`Session`] is dropped, and a flush
+/// still being waited on then fails with [`SdkError::NotConnected`].
+pub struct PendingFlush {
+    rx: mpsc::Receiver<callback::FlushCompletion>,
+    started: Instant,
+    latency: Option<Duration>,
+    outcome: Option<Result<()>>,
+}
+
+impl PendingFlush {
+    /// Wait for the result of a flush handed to the SDK just now.
+    pub(crate) fn new(rx: mpsc::Receiver<callback::FlushCompletion>) -> Self {
+        Self {
+            rx,
+            started: Instant::now(),
+            latency: None,
+            outcome: None,
+        }
+    }
+
+    /// When the flush was handed to the SDK.
+    pub(crate) fn started(&self) -> Instant {
+        self.started
+    }
+
+    /// Check whether the flush has completed, without blocking.
+    ///
+    /// Returns `None` while the flush is still in flight and the SDK's result
+    /// once it has finished.
+    pub fn try_result(&mut self) -> Option<Result<()>> {
+        if self.outcome.is_none() {
+            match self.rx.try_recv() {
+                Ok(completion) => self.complete(completion),
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.outcome = Some(Err(flush_error(SdkError::NotConnected)));
+                }
+            }
+        }
+        self.outcome.clone()
+    }
+
+    /// Block until the flush completes and return the SDK's result.
+    pub fn wait(&mut self) -> Result<()> {
+        if let Some(outcome) = &self.outcome {
+            return outcome.clone();
+        }
+        match self.rx.recv() {
+            Ok(completion) => self.complete(completion),
+            Err(_) => self.outcome = Some(Err(flush_error(SdkError::NotConnected))),
+        }
+        self.outcome.clone().unwrap_or(Ok(()))
+    }
+
+    /// Block until the flush completes or `timeout` elapses.
+    ///
+    /// Returns `Err(SdkError::Timeout)` if the SDK has not signalled
+    /// completion in time; the flush stays pending and can be waited on
+    /// again.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<()> {
+        if let Some(outcome) = &self.outcome {
+            return outcome.clone();
+        }
+        match self.rx.recv_timeout(timeout) {
+            Ok(completion) => self.complete(completion),
+            Err(mpsc::RecvTimeoutError::Timeout) => return Err(flush_error(SdkError::Timeout)),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                self.outcome = Some(Err(flush_error(SdkError::NotConnected)));
+            }
+        }
+        self.outcome.clone().unwrap_or(Ok(()))
+    }
+
+    /// Time from starting the flush until the SDK signalled completion, once
+    /// it has completed.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    fn complete(&mut self, (code, at): callback::FlushCompletion) {
+        let latency = at.saturating_duration_since(self.started);
+        trace::event!(
+            trace,
+            code,
+            latency_us = latency.as_micros() as u64,
+            "flush completed"
+        );
+        self.latency = Some(latency);
+        self.outcome = Some(ErrorContext::new(Operation::FlushLedColors).check(code));
+    }
+}
+
+fn flush_error(kind: SdkError) -> Error {
+    ErrorContext::new(Operation::FlushLedColors).error(kind)
+}
+
 // ---------------------------------------------------------------------------
 // Session
 // ---------------------------------------------------------------------------
@@ -121,35 +308,196 @@ pub enum AccessLevel {
 /// All SDK operations are methods on this struct.  Dropping the session calls
 /// `CorsairDisconnect`.
 ///
-/// Only one `Session` should exist at a time per process.
+/// The SDK keeps one connection per process, so only one `Session` can
+/// exist at a time: [`connect`](Self::connect) fails with
+/// [`SdkError::AlreadyConnected`] while another is alive.  Code that does not
+/// own the application's session, such as a library, should use
+/// [`shared`](Self::shared) instead.
 pub struct Session {
-    state_rx: mpsc::Receiver<SessionStateChange>,
+    state_rx: Mutex<mpsc::Receiver<SessionStateChange>>,
+    details: Mutex<Option<SessionDetails>>,
+    events: Mutex<Option<EventSubscription>>,
+    retry_policy: Mutex<Option<Arc<RetryPolicy>>>,
+    // Shared with the guard module, which updates it from `release_all`.
+    lighting: Arc<Mutex<LightingState>>,
+    // Released by `Drop for Session` once it has disconnected.
+    claim: Option<SessionClaim>,
+    // Whether this is the session in `SHARED_SESSION`.
+    shared: bool,
 }
 
-// SAFETY: The iCUE SDK is documented as thread-safe.  All SDK functions may be
-// called from any thread, and our callback trampolines only send through
-// `mpsc::Sender` which is `Send`.
-unsafe impl Send for Session {}
-unsafe impl Sync for Session {}
+/// Lighting state set through this session, recorded for snapshots.
+#[derive(Default)]
+struct LightingState {
+    layer_priority: Option<u32>,
+    control: HashMap<DeviceId, AccessLevel>,
+    intercepted: HashSet<(DeviceId, MacroKeyId)>,
+    restore_on_drop: Option<LightingSnapshot>,
+}
+
+/// A handle to a session's recorded lighting state that does not keep the
+/// session alive.
+///
+/// Lets [`guard::release_all`](crate::guard::release_all), which has no
+/// `Session`, keep the record in step with the SDK calls it makes.
+#[derive(Clone, Default)]
+pub(crate) struct SessionRecord(Weak<Mutex<LightingState>>);
+
+impl SessionRecord {
+    /// Record `level` for `device_id`, or no control if `None`.
+    ///
+    /// Does nothing if the session is gone or its state is locked, so it is
+    /// safe to call from a panic hook.
+    pub(crate) fn set_control(&self, device_id: &DeviceId, level: Option<AccessLevel>) {
+        self.with(|lighting| match level {
+            Some(level) => lighting.control.insert(*device_id, level),
+            None => lighting.control.remove(device_id),
+        });
+    }
+
+    /// Record that `key_id` is no longer intercepted.
+    pub(crate) fn forget_intercept(&self, device_id: &DeviceId, key_id: MacroKeyId) {
+        self.with(|lighting| lighting.intercepted.remove(&(*device_id, key_id)));
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut LightingState) -> T) {
+        let Some(lighting) = self.0.upgrade() else {
+            return;
+        };
+        let mut lighting = match lighting.try_lock() {
+            Ok(lighting) => lighting,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        f(&mut lighting);
+    }
+}
+
+/// Whether a `Session` exists in this process.
+static SESSION_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// The session handed out by [`Session::shared`].
+///
+/// Points at the shared session from when it is created until it has
+/// finished disconnecting, so `shared()` can tell a session that is still
+/// tearing down from no session at all.
+static SHARED_SESSION: Mutex<Weak<Session>> = Mutex::new(Weak::new());
+
+/// Signalled when the shared session has finished disconnecting.
+static SHARED_RELEASED: Condvar = Condvar::new();
+
+/// Marks the process as having a session until dropped.
+struct SessionClaim(());
+
+impl SessionClaim {
+    fn acquire() -> Result<Self> {
+        SESSION_ACTIVE
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| Self(()))
+            .map_err(|_| ErrorContext::new(Operation::Connect).error(SdkError::AlreadyConnected))
+    }
+}
+
+impl Drop for SessionClaim {
+    fn drop(&mut self) {
+        SESSION_ACTIVE.store(false, Ordering::Release);
+    }
+}
+
+// `Session` is handed to many threads by `shared()`.  Every field is `Sync`,
+// with the `!Sync` receivers behind a `Mutex`, so this needs no `unsafe impl`;
+// the SDK functions themselves are documented as callable from any thread.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+    let _ = assert_send_sync::<Session>;
+};
 
 impl Session {
+    /// Configure a session to connect with
+    /// [`SessionBuilder::connect`].
+    pub fn builder() -> SessionBuilder {
+        SessionBuilder::new()
+    }
+
     /// Initiate a connection to iCUE.
     ///
     /// This registers the session-state callback and calls `CorsairConnect`.
     /// Use [`wait_for_connection`](Self::wait_for_connection) afterwards to
     /// block until the session reaches the `Connected` state.
+    ///
+    /// Returns [`SdkError::AlreadyConnected`] if another `Session` is alive
+    /// in this process, including one from [`shared`](Self::shared).
+    ///
+    /// With the `dynamic` feature, this first loads the SDK library, returning
+    /// [`SdkError::LibraryNotFound`] or [`SdkError::SymbolMissing`] if it
+    /// cannot; see [`dynamic`](crate::dynamic).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, err(level = "warn"))
+    )]
     pub fn connect() -> Result<Self> {
+        let claim = SessionClaim::acquire()?;
+
+        #[cfg(feature = "dynamic")]
+        crate::dynamic::load()?;
+
         let (tx, rx) = mpsc::channel();
         callback::install_session_sender(tx);
 
         // SAFETY: We pass a valid function pointer.  The context pointer is null
         // because the trampoline reads from the process-wide static instead of
         // dereferencing the context (see `session_state_trampoline`).
-        error::check(unsafe {
-            ffi::CorsairConnect(Some(callback::session_state_trampoline), ptr::null_mut())
+        ErrorContext::new(Operation::Connect).check(unsafe {
+            sdk::CorsairConnect(Some(callback::session_state_trampoline), ptr::null_mut())
         })?;
 
-        Ok(Self { state_rx: rx })
+        Ok(Self {
+            state_rx: Mutex::new(rx),
+            details: Mutex::new(None),
+            events: Mutex::new(None),
+            retry_policy: Mutex::new(None),
+            lighting: Arc::default(),
+            claim: Some(claim),
+            shared: false,
+        })
+    }
+
+    /// The process-wide shared session, connecting it if no handle to it is
+    /// alive.
+    ///
+    /// Every caller gets a handle to the same session, which disconnects
+    /// when the last handle is dropped.  A new session is connected with the
+    /// [`SessionBuilder`] defaults and is ready to use when returned.
+    ///
+    /// If the last handle was just dropped and the session is still
+    /// disconnecting, this waits for it to finish and then connects again.
+    ///
+    /// Returns [`SdkError::AlreadyConnected`] if the process already has a
+    /// session that was not created here.
+    pub fn shared() -> Result<Arc<Session>> {
+        Self::shared_with(|| SessionBuilder::new().connect())
+    }
+
+    fn shared_with(connect: impl FnOnce() -> Result<Session>) -> Result<Arc<Session>> {
+        let mut shared = SHARED_SESSION.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(session) = shared.upgrade() {
+                return Ok(session);
+            }
+            if shared.ptr_eq(&Weak::new()) {
+                break;
+            }
+            // The last handle is gone but `Drop` has not released the
+            // session yet.
+            shared = SHARED_RELEASED
+                .wait(shared)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        let mut session = connect()?;
+        session.shared = true;
+        let session = Arc::new(session);
+        *shared = Arc::downgrade(&session);
+        Ok(session)
     }
 
     /// Block until the session state becomes `Connected` or the timeout
@@ -159,133 +507,310 @@ impl Session {
     /// `Connected` state change.
     ///
     /// Returns `Err(SdkError::NotConnected)` on timeout or if the session
-    /// enters a terminal error state (refused, lost).
+    /// enters a terminal error state (refused, lost), and
+    /// `Err(SdkError::IncompatibleProtocol)` if the server fails
+    /// [`SessionDetails::check_server`] against
+    /// [`MIN_SERVER_VERSION`](SessionDetails::MIN_SERVER_VERSION).
+    ///
+    /// Threads sharing a session may call this concurrently.  They wait in
+    /// turn, and each returns as soon as the session is connected.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(timeout = ?timeout), err(level = "warn")))]
     pub fn wait_for_connection(&self, timeout: Duration) -> Result<SessionDetails> {
+        let state_rx = self.state_rx.lock().unwrap_or_else(|e| e.into_inner());
+        // Another thread may have seen the session connect while this one
+        // waited for the lock.
+        if let Some(details) = *self.details.lock().unwrap() {
+            if self.state() == SessionState::Connected {
+                return Ok(details);
+            }
+        }
+        let not_connected =
+            || ErrorContext::new(Operation::WaitForConnection).error(SdkError::NotConnected);
         let deadline = std::time::Instant::now() + timeout;
         loop {
             let remaining = deadline.saturating_duration_since(std::time::Instant::now());
             if remaining.is_zero() {
-                return Err(SdkError::NotConnected);
+                return Err(not_connected());
             }
-            match self.state_rx.recv_timeout(remaining) {
+            match state_rx.recv_timeout(remaining) {
                 Ok(change) => {
                     let state = SessionState::from_ffi(change.state);
                     match state {
                         SessionState::Connected => {
-                            return Ok(SessionDetails::from_ffi(&change.details));
+                            let details = SessionDetails::from_ffi(&change.details);
+                            // Only a compatible server's details are cached,
+                            // so `capabilities()` stays `None` otherwise.
+                            details.check_server(SessionDetails::MIN_SERVER_VERSION)?;
+                            *self.details.lock().unwrap() = Some(details);
+                            return Ok(details);
                         }
                         SessionState::Connecting => continue,
-                        _ => return Err(SdkError::NotConnected),
+                        _ => return Err(not_connected()),
                     }
                 }
-                Err(mpsc::RecvTimeoutError::Timeout) => return Err(SdkError::NotConnected),
-                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(SdkError::NotConnected),
+                Err(_) => return Err(not_connected()),
             }
         }
     }
 
+    // ---- Retries ----------------------------------------------------------
+
+    /// Retry failed calls on this session according to `policy`.
+    ///
+    /// Pass `None` to turn retries off again, which is the default.  See the
+    /// [`retry`] module for which calls are retried.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(enabled = policy.is_some())))]
+    pub fn set_retry_policy(&self, policy: Option<RetryPolicy>) {
+        *self.retry_policy.lock().unwrap() = policy.map(Arc::new);
+    }
+
+    /// The retry policy installed with
+    /// [`set_retry_policy`](Self::set_retry_policy).
+    pub fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry_policy.lock().unwrap().as_deref().cloned()
+    }
+
+    /// Run `f` with `policy` replacing the session's retry policy for every
+    /// call `f` makes on the current thread.
+    ///
+    /// ```no_run
+    /// # use cue_sdk::retry::RetryPolicy;
+    /// # let session = cue_sdk::connect().unwrap();
+    /// # let device_id = session.get_devices(cue_sdk::DeviceType::ALL).unwrap()[0].id;
+    /// // Fail fast for this call even if the session retries by default.
+    /// let positions = session.with_retry(&RetryPolicy::none(), |s| s.get_led_positions(&device_id));
+    /// ```
+    pub fn with_retry<R>(&self, policy: &RetryPolicy, f: impl FnOnce(&Self) -> R) -> R {
+        retry::with_override(Arc::new(policy.clone()), || f(self))
+    }
+
+    fn retrying<T>(&self, mut call: impl FnMut() -> Result<T>) -> Result<T> {
+        let policy =
+            retry::current_override().or_else(|| self.retry_policy.lock().unwrap().clone());
+        match policy {
+            Some(policy) => policy.run(call),
+            None => call(),
+        }
+    }
+
+    // ---- Session ----------------------------------------------------------
+
+    /// The most recent session state reported by the SDK.
+    pub fn state(&self) -> SessionState {
+        SessionState::from_ffi(callback::current_session_state())
+    }
+
     /// Get the current session details (client/server/host versions).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err(level = "debug"))
+    )]
     pub fn details(&self) -> Result<SessionDetails> {
-        let mut raw = MaybeUninit::<ffi::CorsairSessionDetails>::uninit();
-        // SAFETY: We pass a valid pointer to uninitialised memory that the SDK
-        // will write into.  On success, all fields are initialised.
-        error::check(unsafe { ffi::CorsairGetSessionDetails(raw.as_mut_ptr()) })?;
-        // SAFETY: `check` returned `Ok`, so the SDK has fully initialised `raw`.
-        Ok(SessionDetails::from_ffi(unsafe { &raw.assume_init() }))
+        self.retrying(|| {
+            let mut raw = MaybeUninit::<ffi::CorsairSessionDetails>::uninit();
+            // SAFETY: We pass a valid pointer to uninitialised memory that the SDK
+            // will write into.  On success, all fields are initialised.
+            ErrorContext::new(Operation::Details)
+                .check(unsafe { sdk::CorsairGetSessionDetails(raw.as_mut_ptr()) })?;
+            // SAFETY: `check` returned `Ok`, so the SDK has fully initialised `raw`.
+            Ok(SessionDetails::from_ffi(unsafe { &raw.assume_init() }))
+        })
+    }
+
+    /// The features of the server this session connected to.
+    ///
+    /// `None` until [`wait_for_connection`](Self::wait_for_connection) has
+    /// seen the session connect to a compatible server.
+    pub fn capabilities(&self) -> Option<ServerCapabilities> {
+        self.details.lock().unwrap().map(|d| d.capabilities())
     }
 
     // ---- Devices ----------------------------------------------------------
 
     /// Enumerate connected devices matching the given type filter.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(filter = ?filter), err(level = "debug")))]
     pub fn get_devices(&self, filter: DeviceType) -> Result<Vec<DeviceInfo>> {
-        let ffi_filter = ffi::CorsairDeviceFilter {
-            deviceTypeMask: filter.bits() as c_int,
-        };
-        let mut buf = [MaybeUninit::<ffi::CorsairDeviceInfo>::uninit();
-            ffi::CORSAIR_DEVICE_COUNT_MAX as usize];
-        let mut count: c_int = 0;
-
-        // SAFETY: `buf` is a stack-allocated array large enough for the SDK's
-        // maximum device count.  `count` receives the actual number written.
-        error::check(unsafe {
-            ffi::CorsairGetDevices(
-                &ffi_filter,
-                buf.len() as c_int,
-                buf.as_mut_ptr().cast(),
-                &mut count,
-            )
-        })?;
+        self.retrying(|| {
+            let ffi_filter = ffi::CorsairDeviceFilter {
+                deviceTypeMask: filter.bits() as c_int,
+            };
+            let mut buf = [MaybeUninit::<ffi::CorsairDeviceInfo>::uninit();
+                ffi::CORSAIR_DEVICE_COUNT_MAX as usize];
+            let mut count: c_int = 0;
 
-        let devices = (0..count as usize)
-            // SAFETY: The SDK has initialised exactly `count` elements.
-            .map(|i| DeviceInfo::from_ffi(unsafe { buf[i].assume_init_ref() }))
-            .collect();
-        Ok(devices)
+            // SAFETY: `buf` is a stack-allocated array large enough for the SDK's
+            // maximum device count.  `count` receives the actual number written.
+            ErrorContext::new(Operation::GetDevices).check(unsafe {
+                sdk::CorsairGetDevices(
+                    &ffi_filter,
+                    buf.len() as c_int,
+                    buf.as_mut_ptr().cast(),
+                    &mut count,
+                )
+            })?;
+
+            let ctx = ErrorContext::new(Operation::GetDevices);
+            let count = validate::count(count, buf.len()).map_err(|m| ctx.malformed(m))?;
+            buf[..count]
+                .iter()
+                // SAFETY: The SDK has initialised the first `count` elements.
+                .map(|info| DeviceInfo::from_ffi(unsafe { info.assume_init_ref() }))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|m| ctx.malformed(m))
+        })
+    }
+
+    /// Bind a [`Device`] handle for a device.
+    pub fn device(&self, device_id: &DeviceId) -> Result<Device<'_>> {
+        Device::new(self, device_id)
+    }
+
+    /// Bind [`Device`] handles for every connected device matching the
+    /// given type filter.
+    ///
+    /// A device whose LED positions cannot be read, e.g. because it
+    /// disconnected during the call, is left out rather than failing the
+    /// whole call.  Only a failure to list the devices is returned.
+    pub fn devices(&self, filter: DeviceType) -> Result<Vec<Device<'_>>> {
+        Ok(self
+            .get_devices(filter)?
+            .into_iter()
+            .filter_map(|info| {
+                let _id = info.id;
+                Device::with_info(self, info)
+                    .inspect_err(|_e| {
+                        trace::event!(warn, device = %_id, error = %_e, "skipping device");
+                    })
+                    .ok()
+            })
+            .collect())
     }
 
     /// Get detailed information about a specific device.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(device = %device_id), err(level = "debug")))]
     pub fn get_device_info(&self, device_id: &DeviceId) -> Result<DeviceInfo> {
-        let mut raw = MaybeUninit::<ffi::CorsairDeviceInfo>::uninit();
-        // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
-        // `raw` is valid uninitialised memory for the SDK to write into.
-        error::check(unsafe { ffi::CorsairGetDeviceInfo(device_id.as_ptr(), raw.as_mut_ptr()) })?;
-        // SAFETY: `check` returned `Ok`, so the SDK has fully initialised `raw`.
-        Ok(DeviceInfo::from_ffi(unsafe { raw.assume_init_ref() }))
+        self.retrying(|| {
+            let mut raw = MaybeUninit::<ffi::CorsairDeviceInfo>::uninit();
+            // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
+            // `raw` is valid uninitialised memory for the SDK to write into.
+            ErrorContext::new(Operation::GetDeviceInfo)
+                .device(device_id)
+                .check(unsafe {
+                    sdk::CorsairGetDeviceInfo(device_id.as_ptr(), raw.as_mut_ptr())
+                })?;
+            // SAFETY: `check` returned `Ok`, so the SDK has fully initialised `raw`.
+            DeviceInfo::from_ffi(unsafe { raw.assume_init_ref() }).map_err(|m| {
+                ErrorContext::new(Operation::GetDeviceInfo)
+                    .device(device_id)
+                    .malformed(m)
+            })
+        })
+    }
+
+    /// List the devices attached to one channel of a DIY controller.
+    ///
+    /// `channel_index` is zero-based and must be below the device's
+    /// [`channel_count`](DeviceInfo::channel_count).  Devices are returned in
+    /// the order the SDK numbers their LEDs.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(device = %device_id, channel = channel_index), err(level = "debug")))]
+    pub fn get_channel_devices(
+        &self,
+        device_id: &DeviceId,
+        channel_index: u32,
+    ) -> Result<Vec<ChannelDevice>> {
+        let counts = self.read_device_property(
+            device_id,
+            PropertyId::ChannelDeviceLedCountArray,
+            channel_index,
+        )?;
+        let types = self.read_device_property(
+            device_id,
+            PropertyId::ChannelDeviceTypeArray,
+            channel_index,
+        )?;
+        let ctx = ErrorContext::new(Operation::GetChannelDevices).device(device_id);
+        match (counts, types) {
+            (PropertyValue::Int32Array(counts), PropertyValue::Int32Array(types)) => {
+                ChannelDevice::from_ffi_arrays(&counts, &types).map_err(|m| ctx.malformed(m))
+            }
+            _ => Err(ctx.error(SdkError::InvalidOperation)),
+        }
     }
 
     // ---- LEDs -------------------------------------------------------------
 
     /// Get the positions of all LEDs on a device.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(device = %device_id), err(level = "debug")))]
     pub fn get_led_positions(&self, device_id: &DeviceId) -> Result<Vec<LedPosition>> {
-        let mut buf = [MaybeUninit::<ffi::CorsairLedPosition>::uninit();
-            ffi::CORSAIR_DEVICE_LEDCOUNT_MAX as usize];
-        let mut count: c_int = 0;
-
-        // SAFETY: `buf` is large enough for the maximum LED count.
-        // `count` receives the actual number of positions written.
-        error::check(unsafe {
-            ffi::CorsairGetLedPositions(
-                device_id.as_ptr(),
-                buf.len() as c_int,
-                buf.as_mut_ptr().cast(),
-                &mut count,
-            )
-        })?;
+        self.retrying(|| {
+            let mut buf = [MaybeUninit::<ffi::CorsairLedPosition>::uninit();
+                ffi::CORSAIR_DEVICE_LEDCOUNT_MAX as usize];
+            let mut count: c_int = 0;
 
-        let positions = (0..count as usize)
-            // SAFETY: The SDK has initialised exactly `count` elements.
-            .map(|i| LedPosition::from_ffi(unsafe { buf[i].assume_init_ref() }))
-            .collect();
-        Ok(positions)
+            // SAFETY: `buf` is large enough for the maximum LED count.
+            // `count` receives the actual number of positions written.
+            ErrorContext::new(Operation::GetLedPositions)
+                .device(device_id)
+                .check(unsafe {
+                    sdk::CorsairGetLedPositions(
+                        device_id.as_ptr(),
+                        buf.len() as c_int,
+                        buf.as_mut_ptr().cast(),
+                        &mut count,
+                    )
+                })?;
+
+            let count = validate::count(count, buf.len()).map_err(|m| {
+                ErrorContext::new(Operation::GetLedPositions)
+                    .device(device_id)
+                    .malformed(m)
+            })?;
+            let positions = buf[..count]
+                .iter()
+                // SAFETY: The SDK has initialised the first `count` elements.
+                .map(|pos| LedPosition::from_ffi(unsafe { pos.assume_init_ref() }))
+                .collect();
+            Ok(positions)
+        })
     }
 
     /// Set LED colors on a device immediately.
     ///
     /// `colors` must be a slice of [`LedColor`] with the LED LUIDs set
     /// correctly for the target device.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(device = %device_id, leds = colors.len()), err(level = "debug")))]
     pub fn set_led_colors(&self, device_id: &DeviceId, colors: &[LedColor]) -> Result<()> {
-        // SAFETY: `LedColor` is `#[repr(C)]` and layout-identical to
-        // `CorsairLedColor` (verified by compile-time assertions in led.rs),
-        // so the pointer cast is valid.  `colors` is a valid slice.
-        error::check(unsafe {
-            ffi::CorsairSetLedColors(
-                device_id.as_ptr(),
-                colors.len() as c_int,
-                colors.as_ptr().cast(),
-            )
+        self.retrying(|| {
+            // SAFETY: `LedColor` is `#[repr(C)]` and layout-identical to
+            // `CorsairLedColor` (verified by compile-time assertions in led.rs),
+            // so the pointer cast is valid.  `colors` is a valid slice.
+            ErrorContext::new(Operation::SetLedColors)
+                .device(device_id)
+                .check(unsafe {
+                    sdk::CorsairSetLedColors(
+                        device_id.as_ptr(),
+                        colors.len() as c_int,
+                        colors.as_ptr().cast(),
+                    )
+                })
         })
     }
 
     /// Buffer LED colors for later flushing with
     /// [`flush_led_colors`](Self::flush_led_colors).
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(device = %device_id, leds = colors.len()), err(level = "debug")))]
     pub fn set_led_colors_buffer(&self, device_id: &DeviceId, colors: &[LedColor]) -> Result<()> {
-        // SAFETY: Same layout guarantee as `set_led_colors`.
-        error::check(unsafe {
-            ffi::CorsairSetLedColorsBuffer(
-                device_id.as_ptr(),
-                colors.len() as c_int,
-                colors.as_ptr().cast(),
-            )
+        self.retrying(|| {
+            // SAFETY: Same layout guarantee as `set_led_colors`.
+            ErrorContext::new(Operation::SetLedColorsBuffer)
+                .device(device_id)
+                .check(unsafe {
+                    sdk::CorsairSetLedColorsBuffer(
+                        device_id.as_ptr(),
+                        colors.len() as c_int,
+                        colors.as_ptr().cast(),
+                    )
+                })
         })
     }
 
@@ -293,70 +818,217 @@ impl Session {
     ///
     /// This is a synchronous wrapper around `CorsairSetLedColorsFlushBufferAsync`:
     /// it blocks until the SDK signals completion.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, err(level = "debug"))
+    )]
     pub fn flush_led_colors(&self) -> Result<()> {
-        let (sender, rx) = callback::flush_channel();
-        let ctx = callback::sender_as_context(&sender);
-
-        // SAFETY: We pass a valid trampoline and a context pointer to a pinned
-        // sender.  `sender` stays alive on this stack frame until `rx.recv()`
-        // returns, which happens after the SDK invokes the callback.
-        error::check(unsafe {
-            ffi::CorsairSetLedColorsFlushBufferAsync(Some(callback::flush_trampoline), ctx)
-        })?;
+        self.start_flush_led_colors()?.wait()
+    }
 
-        // Wait for the async callback to fire.
-        match rx.recv() {
-            Ok(code) => error::check(code),
-            Err(_) => Err(SdkError::NotConnected),
-        }
+    /// Flush all buffered LED color changes, giving up after `timeout`.
+    ///
+    /// Returns `Err(SdkError::Timeout)` if the SDK does not signal completion
+    /// in time, e.g. because iCUE stopped responding.  A callback arriving
+    /// after the timeout is discarded safely.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(timeout = ?timeout), err(level = "debug")))]
+    pub fn flush_led_colors_timeout(&self, timeout: Duration) -> Result<()> {
+        self.start_flush_led_colors()?.wait_timeout(timeout)
+    }
+
+    /// Start flushing all buffered LED color changes without waiting for the
+    /// SDK to finish.
+    ///
+    /// Poll the returned [`PendingFlush`] with
+    /// [`try_result`](PendingFlush::try_result), or block on it with
+    /// [`wait`](PendingFlush::wait).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, err(level = "debug"))
+    )]
+    pub fn start_flush_led_colors(&self) -> Result<PendingFlush> {
+        self.retrying(|| {
+            let (ctx, rx) = callback::flush_channel();
+            let pending = PendingFlush::new(rx);
+
+            // SAFETY: We pass a valid trampoline and a context holding the SDK's
+            // reference to a pending flush.  The trampoline takes it back when
+            // the SDK invokes the callback; if it never does, dropping the
+            // session frees it after `CorsairDisconnect`.
+            let code = unsafe {
+                sdk::CorsairSetLedColorsFlushBufferAsync(Some(callback::flush_trampoline), ctx)
+            };
+            if let Err(e) = ErrorContext::new(Operation::FlushLedColors).check(code) {
+                // The SDK rejected the call, so it will never invoke the
+                // callback; the context is still ours to free.
+                callback::cancel_flush(ctx);
+                return Err(e);
+            }
+            Ok(pending)
+        })
     }
 
     /// Read current LED colors from a device.
     ///
     /// The `colors` slice must have the `id` field of each element pre-set to
     /// the LED LUID to query; the SDK fills in the `r`, `g`, `b`, `a` values.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(device = %device_id, leds = colors.len()), err(level = "debug")))]
     pub fn get_led_colors(&self, device_id: &DeviceId, colors: &mut [LedColor]) -> Result<()> {
-        // SAFETY: Same layout guarantee as `set_led_colors`.  The SDK reads
-        // each element's `id` and writes the colour fields in place.
-        error::check(unsafe {
-            ffi::CorsairGetLedColors(
-                device_id.as_ptr(),
-                colors.len() as c_int,
-                colors.as_mut_ptr().cast(),
-            )
+        self.retrying(|| {
+            // SAFETY: Same layout guarantee as `set_led_colors`.  The SDK reads
+            // each element's `id` and writes the colour fields in place.
+            ErrorContext::new(Operation::GetLedColors)
+                .device(device_id)
+                .check(unsafe {
+                    sdk::CorsairGetLedColors(
+                        device_id.as_ptr(),
+                        colors.len() as c_int,
+                        colors.as_mut_ptr().cast(),
+                    )
+                })
         })
     }
 
     /// Look up the LED LUID for a key name character on a keyboard device.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(device = %device_id, key = key_name), err(level = "debug")))]
     pub fn get_led_luid_for_key_name(&self, device_id: &DeviceId, key_name: c_char) -> Result<u32> {
-        let mut luid: ffi::CorsairLedLuid = 0;
-        // SAFETY: `luid` is a valid output pointer.
-        error::check(unsafe {
-            ffi::CorsairGetLedLuidForKeyName(device_id.as_ptr(), key_name, &mut luid)
-        })?;
-        Ok(luid)
+        self.retrying(|| {
+            let mut luid: ffi::CorsairLedLuid = 0;
+            // SAFETY: `luid` is a valid output pointer.
+            ErrorContext::new(Operation::GetLedLuidForKeyName)
+                .device(device_id)
+                .check(unsafe {
+                    sdk::CorsairGetLedLuidForKeyName(device_id.as_ptr(), key_name, &mut luid)
+                })?;
+            Ok(luid)
+        })
     }
 
+    /// The layer priority of a client that has not set one.
+    pub const DEFAULT_LAYER_PRIORITY: u32 = 127;
+
     /// Set the layer priority for this client (0–255).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, fields(priority = priority), err(level = "warn"))
+    )]
     pub fn set_layer_priority(&self, priority: u32) -> Result<()> {
-        // SAFETY: No pointer arguments; pure value call.
-        error::check(unsafe { ffi::CorsairSetLayerPriority(priority) })
+        self.retrying(|| {
+            // SAFETY: No pointer arguments; pure value call.
+            ErrorContext::new(Operation::SetLayerPriority)
+                .check(unsafe { sdk::CorsairSetLayerPriority(priority) })
+        })?;
+        self.lighting.lock().unwrap().layer_priority = Some(priority);
+        Ok(())
+    }
+
+    /// The layer priority last set by this session, if any.
+    pub fn layer_priority(&self) -> Option<u32> {
+        self.lighting.lock().unwrap().layer_priority
+    }
+
+    /// Return the layer priority to
+    /// [`DEFAULT_LAYER_PRIORITY`](Self::DEFAULT_LAYER_PRIORITY), as if this
+    /// session had never set one.
+    pub fn reset_layer_priority(&self) -> Result<()> {
+        self.set_layer_priority(Self::DEFAULT_LAYER_PRIORITY)?;
+        self.lighting.lock().unwrap().layer_priority = None;
+        Ok(())
     }
 
     // ---- Access control ---------------------------------------------------
 
     /// Request exclusive control of a device.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(device = %device_id, level = ?level), err(level = "warn")))]
     pub fn request_control(&self, device_id: &DeviceId, level: AccessLevel) -> Result<()> {
-        // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
-        error::check(unsafe {
-            ffi::CorsairRequestControl(device_id.as_ptr(), level as ffi::CorsairAccessLevel)
-        })
+        self.retrying(|| {
+            // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
+            ErrorContext::new(Operation::RequestControl)
+                .device(device_id)
+                .check(unsafe {
+                    sdk::CorsairRequestControl(device_id.as_ptr(), level as ffi::CorsairAccessLevel)
+                })
+        })?;
+        let mut lighting = self.lighting.lock().unwrap();
+        lighting.control.insert(*device_id, level);
+        Ok(())
     }
 
     /// Release exclusive control of a device.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(device = %device_id), err(level = "warn")))]
     pub fn release_control(&self, device_id: &DeviceId) -> Result<()> {
-        // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
-        error::check(unsafe { ffi::CorsairReleaseControl(device_id.as_ptr()) })
+        self.retrying(|| {
+            // SAFETY: `device_id.as_ptr()` is a valid null-terminated C string.
+            ErrorContext::new(Operation::ReleaseControl)
+                .device(device_id)
+                .check(unsafe { sdk::CorsairReleaseControl(device_id.as_ptr()) })
+        })?;
+        self.lighting.lock().unwrap().control.remove(device_id);
+        Ok(())
+    }
+
+    /// The access level this session holds for a device, or `None` if it
+    /// has not requested exclusive control.
+    pub fn access_level(&self, device_id: &DeviceId) -> Option<AccessLevel> {
+        self.lighting
+            .lock()
+            .unwrap()
+            .control
+            .get(device_id)
+            .copied()
+    }
+
+    /// A handle to the lighting state recorded by this session.
+    pub(crate) fn record(&self) -> SessionRecord {
+        SessionRecord(Arc::downgrade(&self.lighting))
+    }
+
+    /// Capture the LED colors of the given devices, together with the layer
+    /// priority and control state set through this session.
+    ///
+    /// See the [`snapshot`](crate::snapshot) module.
+    pub fn snapshot(&self, device_ids: &[DeviceId]) -> Result<LightingSnapshot> {
+        let infos = device_ids
+            .iter()
+            .map(|id| self.get_device_info(id))
+            .collect::<Result<Vec<_>>>()?;
+        LightingSnapshot::capture_devices(self, &infos)
+    }
+
+    /// Reapply a snapshot taken with [`snapshot`](Self::snapshot).
+    ///
+    /// See [`LightingSnapshot::restore`].
+    pub fn restore(&self, snapshot: &LightingSnapshot) -> Result<()> {
+        snapshot.restore(self)
+    }
+
+    /// Restore `snapshot` when this session is dropped, before disconnecting.
+    ///
+    /// Pass `None` to cancel.  Errors during the restore are ignored.
+    pub fn restore_on_drop(&self, snapshot: Option<LightingSnapshot>) {
+        self.lighting.lock().unwrap().restore_on_drop = snapshot;
+    }
+
+    /// Request exclusive control of a device, released when the returned
+    /// guard is dropped.
+    ///
+    /// Guards for the same device nest; see the [`guard`](crate::guard)
+    /// module.
+    pub fn acquire_control(
+        &self,
+        device_id: &DeviceId,
+        level: AccessLevel,
+    ) -> Result<ControlGuard<'_>> {
+        ControlGuard::acquire(self, device_id, level)
+    }
+
+    /// Intercept a macro key until the returned guard is dropped.
+    pub fn intercept_key(
+        &self,
+        device_id: &DeviceId,
+        key_id: MacroKeyId,
+    ) -> Result<KeyInterceptGuard<'_>> {
+        KeyInterceptGuard::acquire(self, device_id, key_id)
     }
 
     // ---- Events -----------------------------------------------------------
@@ -364,11 +1036,27 @@ impl Session {
     /// Subscribe to SDK events (device connect/disconnect, key events).
     ///
     /// Returns an [`EventSubscription`] which unsubscribes on drop.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, err(level = "warn"))
+    )]
     pub fn subscribe_for_events(&self) -> Result<EventSubscription> {
         let (sender, rx) = callback::event_channel();
         EventSubscription::new(sender, rx)
     }
 
+    /// Take the subscription made by a [`SessionBuilder`] with
+    /// [`subscribe_for_events`](SessionBuilder::subscribe_for_events).
+    ///
+    /// Returns `None` if there is none or it has already been taken.
+    pub fn take_event_subscription(&self) -> Option<EventSubscription> {
+        self.events.lock().unwrap().take()
+    }
+
+    pub(crate) fn set_event_subscription(&self, subscription: EventSubscription) {
+        *self.events.lock().unwrap() = Some(subscription);
+    }
+
     /// Subscribe to SDK events with an async receiver.
     ///
     /// Returns an [`AsyncEventSubscription`] whose [`recv`](AsyncEventSubscription::recv)
@@ -376,6 +1064,10 @@ impl Session {
     ///
     /// Requires the `async` feature.
     #[cfg(feature = "async")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, err(level = "warn"))
+    )]
     pub fn subscribe_for_events_async(&self) -> Result<AsyncEventSubscription> {
         let (sender, rx) = callback::async_event_channel();
         AsyncEventSubscription::new(sender, rx)
@@ -386,67 +1078,109 @@ impl Session {
     /// This is the async counterpart to [`flush_led_colors`](Self::flush_led_colors):
     /// it `.await`s instead of blocking.
     ///
+    /// The returned future is cancellation-safe: it may be dropped before the
+    /// SDK signals completion (for example by wrapping it in a timeout), and a
+    /// late callback is discarded.
+    ///
     /// Requires the `async` feature.
     #[cfg(feature = "async")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, err(level = "debug"))
+    )]
     pub async fn flush_led_colors_async(&self) -> Result<()> {
-        let (sender, mut rx) = callback::async_flush_channel();
-        let ctx = callback::async_sender_as_context(&sender);
-
-        // SAFETY: We pass a valid trampoline and a context pointer to a pinned
-        // sender.  `sender` stays alive in this async fn's state until
-        // `rx.recv().await` returns, which happens after the SDK invokes the
-        // callback.
-        error::check(unsafe {
-            ffi::CorsairSetLedColorsFlushBufferAsync(Some(callback::async_flush_trampoline), ctx)
-        })?;
+        let (ctx, mut rx) = callback::async_flush_channel();
+
+        // SAFETY: We pass a valid trampoline and a context holding the SDK's
+        // reference to a pending flush.  The trampoline takes it back when the
+        // SDK invokes the callback, so nothing in this future's state is
+        // referenced by the SDK.
+        let code = unsafe {
+            sdk::CorsairSetLedColorsFlushBufferAsync(Some(callback::flush_trampoline), ctx)
+        };
+        if let Err(e) = ErrorContext::new(Operation::FlushLedColors).check(code) {
+            // The SDK rejected the call, so it will never invoke the callback;
+            // the context is still ours to free.
+            callback::cancel_flush(ctx);
+            return Err(e);
+        }
 
         match rx.recv().await {
-            Some(code) => error::check(code),
-            None => Err(SdkError::NotConnected),
+            Some(code) => ErrorContext::new(Operation::FlushLedColors).check(code),
+            None => Err(ErrorContext::new(Operation::FlushLedColors).error(SdkError::NotConnected)),
         }
     }
 
     /// Configure whether a macro key event should be intercepted.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "info", skip_all, fields(device = %device_id, key = ?key_id, intercepted = is_intercepted), err(level = "warn")))]
     pub fn configure_key_event(
         &self,
         device_id: &DeviceId,
         key_id: MacroKeyId,
         is_intercepted: bool,
     ) -> Result<()> {
-        let config = ffi::CorsairKeyEventConfiguration {
-            keyId: key_id as ffi::CorsairMacroKeyId,
-            isIntercepted: is_intercepted,
-        };
-        // SAFETY: `config` is a valid stack-allocated struct.
-        error::check(unsafe { ffi::CorsairConfigureKeyEvent(device_id.as_ptr(), &config) })
+        self.retrying(|| {
+            let config = ffi::CorsairKeyEventConfiguration {
+                keyId: key_id.to_ffi(),
+                isIntercepted: is_intercepted,
+            };
+            // SAFETY: `config` is a valid stack-allocated struct.
+            ErrorContext::new(Operation::ConfigureKeyEvent)
+                .device(device_id)
+                .check(unsafe { sdk::CorsairConfigureKeyEvent(device_id.as_ptr(), &config) })
+        })?;
+        let mut lighting = self.lighting.lock().unwrap();
+        if is_intercepted {
+            lighting.intercepted.insert((*device_id, key_id));
+        } else {
+            lighting.intercepted.remove(&(*device_id, key_id));
+        }
+        Ok(())
+    }
+
+    /// Whether this session has intercepted `key_id` with
+    /// [`configure_key_event`](Self::configure_key_event), directly or
+    /// through a [`KeyInterceptGuard`].
+    pub fn is_key_intercepted(&self, device_id: &DeviceId, key_id: MacroKeyId) -> bool {
+        self.lighting
+            .lock()
+            .unwrap()
+            .intercepted
+            .contains(&(*device_id, key_id))
     }
 
     // ---- Properties -------------------------------------------------------
 
     /// Get metadata about a device property.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(device = %device_id, property = ?property, index = index), err(level = "debug")))]
     pub fn get_device_property_info(
         &self,
         device_id: &DeviceId,
         property: PropertyId,
         index: u32,
     ) -> Result<PropertyInfo> {
-        let mut data_type: ffi::CorsairDataType = 0;
-        let mut flags: u32 = 0;
-
-        // SAFETY: Output pointers are valid stack-allocated values.
-        error::check(unsafe {
-            ffi::CorsairGetDevicePropertyInfo(
-                device_id.as_ptr(),
-                property.to_ffi(),
-                index,
-                &mut data_type,
-                &mut flags,
-            )
-        })?;
+        self.retrying(|| {
+            let mut data_type: ffi::CorsairDataType = 0;
+            let mut flags: u32 = 0;
 
-        Ok(PropertyInfo {
-            data_type: DataType::from_ffi(data_type).unwrap_or(DataType::Int32), // fallback for unknown types
-            flags: PropertyFlags::from_bits_truncate(flags),
+            // SAFETY: Output pointers are valid stack-allocated values.
+            ErrorContext::new(Operation::GetDevicePropertyInfo)
+                .device(device_id)
+                .property(property)
+                .check(unsafe {
+                    sdk::CorsairGetDevicePropertyInfo(
+                        device_id.as_ptr(),
+                        property.to_ffi(),
+                        index,
+                        &mut data_type,
+                        &mut flags,
+                    )
+                })?;
+
+            Ok(PropertyInfo {
+                data_type: DataType::from_ffi(data_type),
+                flags: PropertyFlags::from_bits_truncate(flags),
+            })
         })
     }
 
@@ -454,31 +1188,104 @@ impl Session {
     ///
     /// The SDK-allocated memory is freed immediately after the value is copied
     /// into an owned [`PropertyValue`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(device = %device_id, property = ?property, index = index), err(level = "debug")))]
     pub fn read_device_property(
         &self,
         device_id: &DeviceId,
         property: PropertyId,
         index: u32,
     ) -> Result<PropertyValue> {
-        let mut prop = MaybeUninit::<ffi::CorsairProperty>::zeroed();
-
-        // SAFETY: `prop` points to zeroed memory suitable for the SDK to write
-        // into.  On success all fields are initialised.
-        error::check(unsafe {
-            ffi::CorsairReadDeviceProperty(
-                device_id.as_ptr(),
-                property.to_ffi(),
-                index,
-                prop.as_mut_ptr(),
-            )
-        })?;
+        self.retrying(|| {
+            let mut prop = MaybeUninit::<ffi::CorsairProperty>::zeroed();
 
-        // SAFETY: `check` returned `Ok`, so the SDK has fully initialised `prop`.
-        let mut prop = unsafe { prop.assume_init() };
-        // SAFETY: The property was just initialised by the SDK and its `type_`
-        // field matches the union variant.  `from_ffi_and_free` copies the data
-        // out and calls `CorsairFreeProperty` to release SDK memory.
-        unsafe { PropertyValue::from_ffi_and_free(&mut prop) }.ok_or(SdkError::InvalidOperation)
+            // SAFETY: `prop` points to zeroed memory suitable for the SDK to write
+            // into.  On success all fields are initialised.
+            ErrorContext::new(Operation::ReadDeviceProperty)
+                .device(device_id)
+                .property(property)
+                .check(unsafe {
+                    sdk::CorsairReadDeviceProperty(
+                        device_id.as_ptr(),
+                        property.to_ffi(),
+                        index,
+                        prop.as_mut_ptr(),
+                    )
+                })?;
+
+            // SAFETY: `check` returned `Ok`, so the SDK has fully initialised `prop`.
+            let mut prop = unsafe { prop.assume_init() };
+            // SAFETY: The property was just initialised by the SDK and its `type_`
+            // field matches the union variant.  `from_ffi_and_free` copies the data
+            // out and calls `CorsairFreeProperty` to release SDK memory.
+            unsafe { PropertyValue::from_ffi_and_free(&mut prop) }.map_err(|kind| {
+                ErrorContext::new(Operation::ReadDeviceProperty)
+                    .device(device_id)
+                    .property(property)
+                    .error(kind)
+            })
+        })
+    }
+
+    /// Read a device property as a specific type.
+    ///
+    /// Returns [`SdkError::TypeMismatch`] if the property holds a value of a
+    /// different type.
+    ///
+    /// ```no_run
+    /// # use cue_sdk::PropertyId;
+    /// # let session = cue_sdk::connect().unwrap();
+    /// # let device_id = session.get_devices(cue_sdk::DeviceType::HEADSET).unwrap()[0].id;
+    /// let muted = !session.read::<bool>(&device_id, PropertyId::MicEnabled, 0).unwrap();
+    /// ```
+    pub fn read<T: FromPropertyValue>(
+        &self,
+        device_id: &DeviceId,
+        property: PropertyId,
+        index: u32,
+    ) -> Result<T> {
+        let value = self.read_device_property(device_id, property, index)?;
+        let actual = value.data_type();
+        T::from_property_value(value).ok_or_else(|| {
+            ErrorContext::new(Operation::ReadDeviceProperty)
+                .device(device_id)
+                .property(property)
+                .error(SdkError::TypeMismatch {
+                    expected: T::DATA_TYPE.unwrap_or(actual),
+                    actual,
+                })
+        })
+    }
+
+    /// Write a property value of any type to a device.
+    ///
+    /// Returns `SdkError::InvalidArguments` without calling the SDK if a
+    /// string contains a null byte.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(device = %device_id, property = ?property, index = index, data_type = ?value.data_type()), err(level = "debug")))]
+    pub fn write_device_property(
+        &self,
+        device_id: &DeviceId,
+        property: PropertyId,
+        index: u32,
+        value: &PropertyValue,
+    ) -> Result<()> {
+        let ctx = ErrorContext::new(Operation::WriteDeviceProperty)
+            .device(device_id)
+            .property(property);
+        let prop = FfiProperty::new(value).ok_or_else(|| ctx.error(SdkError::InvalidArguments))?;
+        self.retrying(|| {
+            // SAFETY: `prop` has a `type_` matching its active union variant,
+            // and every pointer in it refers to memory owned by `prop` or
+            // `value`, both of which outlive the call.  The SDK only reads
+            // from the property.
+            ctx.check(unsafe {
+                sdk::CorsairWriteDeviceProperty(
+                    device_id.as_ptr(),
+                    property.to_ffi(),
+                    index,
+                    prop.as_ptr(),
+                )
+            })
+        })
     }
 
     /// Write a boolean property to a device.
@@ -489,12 +1296,7 @@ impl Session {
         index: u32,
         value: bool,
     ) -> Result<()> {
-        let prop = crate::property::make_bool_property(value);
-        // SAFETY: `prop` is a valid stack-allocated struct with matching
-        // `type_` and `value` fields.
-        error::check(unsafe {
-            ffi::CorsairWriteDeviceProperty(device_id.as_ptr(), property.to_ffi(), index, &prop)
-        })
+        self.write_device_property(device_id, property, index, &PropertyValue::Boolean(value))
     }
 
     /// Write an integer property to a device.
@@ -505,11 +1307,7 @@ impl Session {
         index: u32,
         value: i32,
     ) -> Result<()> {
-        let prop = crate::property::make_int32_property(value);
-        // SAFETY: Same as `write_device_property_bool`.
-        error::check(unsafe {
-            ffi::CorsairWriteDeviceProperty(device_id.as_ptr(), property.to_ffi(), index, &prop)
-        })
+        self.write_device_property(device_id, property, index, &PropertyValue::Int32(value))
     }
 
     /// Write a float property to a device.
@@ -520,25 +1318,194 @@ impl Session {
         index: u32,
         value: f64,
     ) -> Result<()> {
-        let prop = crate::property::make_float64_property(value);
-        // SAFETY: Same as `write_device_property_bool`.
-        error::check(unsafe {
-            ffi::CorsairWriteDeviceProperty(device_id.as_ptr(), property.to_ffi(), index, &prop)
-        })
+        self.write_device_property(device_id, property, index, &PropertyValue::Float64(value))
     }
 }
 
 impl Drop for Session {
     fn drop(&mut self) {
+        let restore = self
+            .lighting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .restore_on_drop
+            .take();
+        if let Some(snapshot) = restore {
+            let _ = snapshot.restore(self);
+        }
+        // Unsubscribe while still connected.
+        drop(
+            self.events
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(),
+        );
+
         // Clear the static sender *first* so the SDK's background thread
         // cannot send into a half-dropped channel (fixes macOS SIGBUS, #18).
         callback::clear_session_sender();
+        trace::event!(info, "disconnecting from iCUE");
 
         // SAFETY: `CorsairDisconnect` is safe to call at any time; it is a
         // no-op if not connected.  We ignore the return value because we
         // cannot propagate errors from `Drop`.
         unsafe {
-            let _ = ffi::CorsairDisconnect();
+            let _ = sdk::CorsairDisconnect();
+        }
+        // Disconnected, so no flush callback can fire any more; free the
+        // contexts of flushes that never completed.
+        callback::release_pending_flushes();
+        drop(self.claim.take());
+
+        // Only the shared session takes this lock: `shared_with` holds it
+        // while dropping a session that failed to connect.
+        if self.shared {
+            let mut shared = SHARED_SESSION.lock().unwrap_or_else(|e| e.into_inner());
+            *shared = Weak::new();
+            SHARED_RELEASED.notify_all();
+        }
+    }
+}
+
+// With the `dynamic` feature and no SDK library loaded, every SDK call
+// returns `NotConnected` without calling into iCUE, so these tests can build
+// sessions directly.
+#[cfg(all(test, feature = "dynamic"))]
+mod tests {
+    use std::thread;
+
+    use core::ffi::c_void;
+
+    use super::*;
+    use crate::retry::Backoff;
+
+    /// Serialises the tests, which all need the process's one session.
+    static SESSIONS: Mutex<()> = Mutex::new(());
+
+    /// A session that skips `CorsairConnect`.
+    fn offline() -> Result<Session> {
+        offline_with(mpsc::channel().1)
+    }
+
+    /// A session that skips `CorsairConnect` and receives state changes from
+    /// the test instead of the SDK.
+    fn offline_with(rx: mpsc::Receiver<SessionStateChange>) -> Result<Session> {
+        let claim = SessionClaim::acquire()?;
+        Ok(Session {
+            state_rx: Mutex::new(rx),
+            details: Mutex::new(None),
+            events: Mutex::new(None),
+            retry_policy: Mutex::new(None),
+            lighting: Arc::default(),
+            claim: Some(claim),
+            shared: false,
+        })
+    }
+
+    fn raw_version(major: i32, minor: i32, patch: i32) -> ffi::CorsairVersion {
+        ffi::CorsairVersion {
+            major,
+            minor,
+            patch,
         }
     }
+
+    #[test]
+    fn incompatible_servers_are_not_cached() {
+        let _lock = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let (tx, rx) = mpsc::channel();
+        let session = offline_with(rx).unwrap();
+        tx.send(SessionStateChange {
+            state: ffi::CorsairSessionState_CSS_Connected,
+            details: ffi::CorsairSessionDetails {
+                clientVersion: raw_version(4, 0, 48),
+                serverVersion: raw_version(3, 0, 171),
+                serverHostVersion: raw_version(5, 9, 105),
+            },
+        })
+        .unwrap();
+
+        let err = session
+            .wait_for_connection(Duration::from_secs(1))
+            .unwrap_err();
+        assert_eq!(err.kind(), SdkError::IncompatibleProtocol);
+        assert_eq!(session.capabilities(), None);
+    }
+
+    #[test]
+    fn shared_session_can_be_dropped_and_reacquired_concurrently() {
+        let _lock = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    for _ in 0..200 {
+                        let session = Session::shared_with(offline).expect("shared session");
+                        assert!(session.shared);
+                        // Make teardown slow enough for other threads to call
+                        // `shared_with` while it runs: restoring retries the
+                        // failing SDK calls.
+                        session.set_retry_policy(Some(
+                            RetryPolicy::default()
+                                .backoff(Backoff::Fixed(Duration::from_micros(200))),
+                        ));
+                        session.restore_on_drop(Some(LightingSnapshot {
+                            layer_priority: Some(1),
+                            devices: Vec::new(),
+                        }));
+                        drop(session);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // The last session has been released, so a plain one can connect.
+        assert!(SHARED_SESSION.lock().unwrap().upgrade().is_none());
+        let session = offline().expect("no session is alive");
+        assert_eq!(
+            Session::shared_with(offline).err().map(|e| e.kind()),
+            Some(SdkError::AlreadyConnected)
+        );
+        drop(session);
+    }
+
+    /// A flush the SDK accepted, with the context it was handed.
+    fn accepted_flush() -> (*mut c_void, PendingFlush) {
+        let (ctx, rx) = callback::flush_channel();
+        (ctx, PendingFlush::new(rx))
+    }
+
+    #[test]
+    fn flush_callbacks_complete_their_flush_once() {
+        let _lock = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let (ctx, mut pending) = accepted_flush();
+        assert_eq!(pending.try_result(), None);
+
+        // SAFETY: `ctx` came from `flush_channel`.
+        unsafe { callback::flush_trampoline(ctx, ffi::CorsairError_CE_Success) };
+        assert_eq!(pending.wait(), Ok(()));
+        assert!(pending.latency().is_some());
+
+        // The context was freed by the first call, so a repeat is ignored.
+        // SAFETY: The trampoline only compares `ctx` with pending flushes.
+        unsafe { callback::flush_trampoline(ctx, ffi::CorsairError_CE_Success) };
+    }
+
+    #[test]
+    fn dropping_the_session_frees_flushes_that_never_completed() {
+        let _lock = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        let session = offline().unwrap();
+        let (ctx, mut pending) = accepted_flush();
+        let waiter = thread::spawn(move || pending.wait());
+
+        drop(session);
+        let err = waiter.join().unwrap().unwrap_err();
+        assert_eq!(err.kind(), SdkError::NotConnected);
+
+        // A callback arriving after the disconnect finds nothing to free.
+        // SAFETY: The trampoline only compares `ctx` with pending flushes.
+        unsafe { callback::flush_trampoline(ctx, ffi::CorsairError_CE_Success) };
+    }
 }
@@ -0,0 +1,230 @@
+//! Capturing and restoring the lighting state set by this client.
+//!
+//! A [`LightingSnapshot`] records the colors of every LED on a set of
+//! devices, together with the client's layer priority and which devices it
+//! holds exclusive control of.  Restoring it puts all of that back, so a
+//! program can temporarily take over the lighting and return it exactly as
+//! it found it.
+//!
+//! Devices are matched by serial number on restore, so a snapshot still
+//! applies after a device was replugged and received a new [`DeviceId`].
+//! With the `serde` feature, snapshots can be saved and loaded.
+//!
+//! ```no_run
+//! # use cue_sdk::DeviceType;
+//! # let session = cue_sdk::connect().unwrap();
+//! let ids: Vec<_> = session.get_devices(DeviceType::ALL).unwrap().iter().map(|d| d.id).collect();
+//! let before = session.snapshot(&ids).unwrap();
+//! // ... change colors, priority and control ...
+//! session.restore(&before).unwrap();
+//!
+//! // Or restore automatically when the session is dropped.
+//! session.restore_on_drop(Some(before));
+//! ```
+//!
+//! [`LightingSnapshot::restore_steps`] lists what a restore would change
+//! without calling the SDK.
+
+use crate::device::{DeviceId, DeviceInfo, DeviceType};
+use crate::error::Result;
+use crate::led::LedColor;
+use crate::session::{AccessLevel, Session};
+
+// ---------------------------------------------------------------------------
+// DeviceSnapshot
+// ---------------------------------------------------------------------------
+
+/// The lighting state of one device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSnapshot {
+    /// The device id at capture time.
+    pub device_id: DeviceId,
+    /// The device serial number, used to find the device on restore.
+    pub serial: String,
+    /// The access level this client held, or `None` for shared access.
+    pub access_level: Option<AccessLevel>,
+    /// The color of every LED.
+    pub colors: Vec<LedColor>,
+}
+
+impl DeviceSnapshot {
+    /// Capture the lighting state of one device.
+    pub fn capture(session: &Session, info: &DeviceInfo) -> Result<Self> {
+        let mut colors: Vec<LedColor> = session
+            .get_led_positions(&info.id)?
+            .iter()
+            .map(|pos| LedColor::new(pos.id, 0, 0, 0, 0))
+            .collect();
+        session.get_led_colors(&info.id, &mut colors)?;
+        Ok(Self {
+            device_id: info.id,
+            serial: info.serial.clone(),
+            access_level: session.access_level(&info.id),
+            colors,
+        })
+    }
+
+    /// The steps that restore this device's access level and colors.
+    ///
+    /// `device_id` is the device's current id, which may differ from the one
+    /// it had when captured, and `held` the access level currently held.
+    fn restore_steps<'a>(
+        &'a self,
+        device_id: DeviceId,
+        held: Option<AccessLevel>,
+        steps: &mut Vec<RestoreStep<'a>>,
+    ) {
+        if self.access_level != held {
+            steps.push(match self.access_level {
+                Some(level) => RestoreStep::RequestControl { device_id, level },
+                None => RestoreStep::ReleaseControl { device_id },
+            });
+        }
+        if !self.colors.is_empty() {
+            steps.push(RestoreStep::SetColors {
+                device_id,
+                colors: &self.colors,
+            });
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RestoreStep
+// ---------------------------------------------------------------------------
+
+/// One change made by [`LightingSnapshot::restore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreStep<'a> {
+    /// Set the layer priority recorded in the snapshot.
+    SetLayerPriority(u32),
+    /// Return the layer priority to the SDK default, because the snapshot
+    /// was taken before the session set one.
+    ResetLayerPriority,
+    /// Request the access level the session held at capture time.
+    RequestControl {
+        device_id: DeviceId,
+        level: AccessLevel,
+    },
+    /// Release control the session took after the capture.
+    ReleaseControl { device_id: DeviceId },
+    /// Set the captured LED colors.
+    SetColors {
+        device_id: DeviceId,
+        colors: &'a [LedColor],
+    },
+}
+
+impl RestoreStep<'_> {
+    fn apply(&self, session: &Session) -> Result<()> {
+        match *self {
+            Self::SetLayerPriority(priority) => session.set_layer_priority(priority),
+            Self::ResetLayerPriority => session.reset_layer_priority(),
+            Self::RequestControl { device_id, level } => session.request_control(&device_id, level),
+            Self::ReleaseControl { device_id } => session.release_control(&device_id),
+            Self::SetColors { device_id, colors } => session.set_led_colors(&device_id, colors),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// LightingSnapshot
+// ---------------------------------------------------------------------------
+
+/// The lighting state of this client across devices.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LightingSnapshot {
+    /// The layer priority set by this client, if it set one.
+    pub layer_priority: Option<u32>,
+    /// Per-device state.
+    pub devices: Vec<DeviceSnapshot>,
+}
+
+impl LightingSnapshot {
+    /// Capture the lighting state of every connected device.
+    ///
+    /// See also [`Session::snapshot`] for a chosen set of devices.
+    pub fn capture(session: &Session) -> Result<Self> {
+        let infos = session.get_devices(DeviceType::ALL)?;
+        Self::capture_devices(session, &infos)
+    }
+
+    /// Capture the lighting state of the given devices.
+    pub fn capture_devices(session: &Session, devices: &[DeviceInfo]) -> Result<Self> {
+        let devices = devices
+            .iter()
+            .map(|info| DeviceSnapshot::capture(session, info))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            layer_priority: session.layer_priority(),
+            devices,
+        })
+    }
+
+    /// Restore the layer priority, access levels and LED colors.
+    ///
+    /// A layer priority or exclusive control the session set after the
+    /// capture is undone.  Devices that are not connected are skipped.  Every
+    /// step is attempted even if an earlier one fails; the first error is
+    /// returned.
+    pub fn restore(&self, session: &Session) -> Result<()> {
+        let (connected, mut result) = match session.get_devices(DeviceType::ALL) {
+            Ok(connected) => (connected, Ok(())),
+            Err(e) => (Vec::new(), Err(e)),
+        };
+        let steps = self.restore_steps(session.layer_priority(), &connected, |id| {
+            session.access_level(id)
+        });
+        for step in steps {
+            let applied = step.apply(session);
+            if result.is_ok() {
+                result = applied;
+            }
+        }
+        result
+    }
+
+    /// The steps [`restore`](Self::restore) takes, given the layer priority
+    /// the session has set, the connected devices and the access level the
+    /// session holds for each device.
+    ///
+    /// Devices are matched by serial number, or by id if the snapshot has no
+    /// serial for them.  Control already held at the captured level is not
+    /// requested again.
+    pub fn restore_steps(
+        &self,
+        layer_priority: Option<u32>,
+        connected: &[DeviceInfo],
+        access_level: impl Fn(&DeviceId) -> Option<AccessLevel>,
+    ) -> Vec<RestoreStep<'_>> {
+        let mut steps = Vec::new();
+        match (self.layer_priority, layer_priority) {
+            (Some(priority), _) => steps.push(RestoreStep::SetLayerPriority(priority)),
+            (None, Some(_)) => steps.push(RestoreStep::ResetLayerPriority),
+            (None, None) => {}
+        }
+        for device in &self.devices {
+            if let Some(info) = find_device(connected, device) {
+                device.restore_steps(info.id, access_level(&info.id), &mut steps);
+            }
+        }
+        steps
+    }
+
+    /// The snapshot of a device, looked up by serial number.
+    pub fn device(&self, serial: &str) -> Option<&DeviceSnapshot> {
+        self.devices.iter().find(|d| d.serial == serial)
+    }
+}
+
+/// Find the connected device a snapshot belongs to: by serial number if it
+/// has one, otherwise by id.
+fn find_device<'a>(connected: &'a [DeviceInfo], device: &DeviceSnapshot) -> Option<&'a DeviceInfo> {
+    if device.serial.is_empty() {
+        connected.iter().find(|info| info.id == device.device_id)
+    } else {
+        connected.iter().find(|info| info.serial == device.serial)
+    }
+}
@@ -0,0 +1,22 @@
+//! Internal logging macro backed by the optional `tracing` feature.
+//!
+//! Levels are chosen so that production logs stay quiet: per-frame LED calls
+//! and raw return codes are `TRACE`, device and property queries `DEBUG`,
+//! session lifecycle and control changes `INFO`.  Failed lifecycle and
+//! control calls are reported at `WARN`, other failures at `DEBUG`.
+
+/// Emit a `tracing` event at the given level, or nothing if the `tracing`
+/// feature is disabled.
+///
+/// Arguments are only evaluated when the feature is enabled, so they must
+/// not have side effects the caller relies on.
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        {
+            ::tracing::$level!($($arg)+);
+        }
+    }};
+}
+
+pub(crate) use event;
@@ -0,0 +1,315 @@
+//! Internal checks for data returned by the iCUE SDK.
+//!
+//! Counts, pointers and fixed-size strings written by the SDK are checked
+//! here before they are used to index a buffer or build a slice, so a
+//! misbehaving SDK produces [`SdkError::MalformedResponse`] instead of a
+//! panic or an out-of-bounds read.
+//!
+//! [`SdkError::MalformedResponse`]: crate::SdkError::MalformedResponse
+
+use core::ffi::{c_char, c_int, c_uint};
+
+use crate::error::Malformed;
+
+/// The largest array the SDK returns in a property.  Property arrays hold
+/// property ids, macro keys and per-channel devices, so a larger count means
+/// the response is corrupt.
+pub(crate) const MAX_ARRAY_LEN: c_uint = 1 << 16;
+
+/// Check an element count the SDK wrote for a buffer of `capacity` items.
+pub(crate) fn count(count: c_int, capacity: usize) -> Result<usize, Malformed> {
+    let len = usize::try_from(count).map_err(|_| Malformed::NegativeCount(count))?;
+    if len > capacity {
+        return Err(Malformed::CountTooLarge {
+            count: len as u64,
+            max: capacity as u64,
+        });
+    }
+    Ok(len)
+}
+
+/// Decode a fixed-size C string, which must contain a terminator.
+///
+/// Invalid UTF-8 is replaced, as the SDK does not promise an encoding.
+pub(crate) fn c_string(chars: &[c_char]) -> Result<String, Malformed> {
+    let len = terminated_len(chars)?;
+    let bytes: Vec<u8> = chars[..len].iter().map(|&c| c as u8).collect();
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// The length of a fixed-size C string, not counting its terminator.
+pub(crate) fn terminated_len(chars: &[c_char]) -> Result<usize, Malformed> {
+    chars
+        .iter()
+        .position(|&c| c == 0)
+        .ok_or(Malformed::UnterminatedString)
+}
+
+/// View an SDK array as a slice.
+///
+/// An empty array may have a null `items` pointer.
+///
+/// # Safety
+///
+/// If `items` is non-null and `count` is at most [`MAX_ARRAY_LEN`], it must
+/// point to `count` initialised values that outlive `'a`.
+pub(crate) unsafe fn array<'a, T>(items: *const T, count: c_uint) -> Result<&'a [T], Malformed> {
+    if count == 0 {
+        return Ok(&[]);
+    }
+    if items.is_null() {
+        return Err(Malformed::NullPointer);
+    }
+    if count > MAX_ARRAY_LEN {
+        return Err(Malformed::CountTooLarge {
+            count: count.into(),
+            max: MAX_ARRAY_LEN.into(),
+        });
+    }
+    // SAFETY: `items` is non-null and the caller guarantees it points to
+    // `count` values.
+    Ok(unsafe { std::slice::from_raw_parts(items, count as usize) })
+}
+
+// These tests feed crafted FFI structs to the conversions and do not call
+// into the iCUE SDK.
+#[cfg(test)]
+mod tests {
+    use core::ffi::c_char;
+    use std::ptr;
+
+    use cue_sdk_sys as ffi;
+
+    use super::*;
+    use crate::device::{ChannelDevice, ChannelDeviceType, DeviceId, DeviceInfo};
+    use crate::error::SdkError;
+    use crate::event::{Event, MacroKeyId};
+    use crate::property::PropertyValue;
+
+    fn c_chars<const N: usize>(s: &str) -> [c_char; N] {
+        let mut raw = [0; N];
+        for (dst, b) in raw.iter_mut().zip(s.bytes()) {
+            *dst = b as c_char;
+        }
+        raw
+    }
+
+    fn device_info() -> ffi::CorsairDeviceInfo {
+        ffi::CorsairDeviceInfo {
+            type_: ffi::CorsairDeviceType_CDT_Keyboard,
+            id: c_chars("{keyboard}"),
+            serial: c_chars("ABC123"),
+            model: c_chars("K70"),
+            ledCount: 120,
+            channelCount: 0,
+        }
+    }
+
+    fn int32_array(items: *mut i32, count: u32) -> ffi::CorsairProperty {
+        ffi::CorsairProperty {
+            type_: ffi::CorsairDataType_CT_Int32_Array,
+            value: ffi::CorsairDataValue {
+                int32_array: ffi::CorsairDataType_Int32Array { items, count },
+            },
+        }
+    }
+
+    #[test]
+    fn counts_are_checked_against_the_buffer() {
+        assert_eq!(count(3, 64), Ok(3));
+        assert_eq!(count(64, 64), Ok(64));
+        assert_eq!(count(-1, 64), Err(Malformed::NegativeCount(-1)));
+        assert_eq!(
+            count(65, 64),
+            Err(Malformed::CountTooLarge { count: 65, max: 64 })
+        );
+    }
+
+    #[test]
+    fn device_info_requires_terminated_strings() {
+        let info = DeviceInfo::from_ffi(&device_info()).unwrap();
+        assert_eq!(info.serial, "ABC123");
+        assert_eq!(info.id.to_string(), "{keyboard}");
+
+        let mut raw = device_info();
+        raw.serial = [b'x' as c_char; 128];
+        assert_eq!(
+            DeviceInfo::from_ffi(&raw).unwrap_err(),
+            Malformed::UnterminatedString
+        );
+
+        let mut raw = device_info();
+        raw.id = [b'x' as c_char; 128];
+        assert_eq!(
+            DeviceInfo::from_ffi(&raw).unwrap_err(),
+            Malformed::UnterminatedString
+        );
+        assert!(DeviceId::from_ffi([b'x' as c_char; 128]).is_err());
+    }
+
+    #[test]
+    fn device_info_rejects_bad_counts() {
+        let mut raw = device_info();
+        raw.ledCount = -5;
+        assert_eq!(
+            DeviceInfo::from_ffi(&raw).unwrap_err(),
+            Malformed::NegativeCount(-5)
+        );
+
+        let mut raw = device_info();
+        raw.ledCount = ffi::CORSAIR_DEVICE_LEDCOUNT_MAX as i32 + 1;
+        assert!(matches!(
+            DeviceInfo::from_ffi(&raw).unwrap_err(),
+            Malformed::CountTooLarge { .. }
+        ));
+
+        let mut raw = device_info();
+        raw.channelCount = -1;
+        assert_eq!(
+            DeviceInfo::from_ffi(&raw).unwrap_err(),
+            Malformed::NegativeCount(-1)
+        );
+    }
+
+    #[test]
+    fn property_arrays_are_validated() {
+        let mut items = [4, 5, 6];
+        let prop = int32_array(items.as_mut_ptr(), 3);
+        // SAFETY: `items` holds 3 values and outlives the call.
+        let value = unsafe { PropertyValue::from_ffi(&prop) };
+        assert_eq!(value, Ok(PropertyValue::Int32Array(vec![4, 5, 6])));
+
+        let prop = int32_array(ptr::null_mut(), 0);
+        // SAFETY: An empty array is never dereferenced.
+        let value = unsafe { PropertyValue::from_ffi(&prop) };
+        assert_eq!(value, Ok(PropertyValue::Int32Array(Vec::new())));
+
+        let prop = int32_array(ptr::null_mut(), 3);
+        // SAFETY: A null pointer is rejected before it is dereferenced.
+        let value = unsafe { PropertyValue::from_ffi(&prop) };
+        assert_eq!(
+            value,
+            Err(SdkError::MalformedResponse(Malformed::NullPointer))
+        );
+
+        let prop = int32_array(items.as_mut_ptr(), MAX_ARRAY_LEN + 1);
+        // SAFETY: An oversized count is rejected before the slice is built.
+        let value = unsafe { PropertyValue::from_ffi(&prop) };
+        assert_eq!(
+            value,
+            Err(SdkError::MalformedResponse(Malformed::CountTooLarge {
+                count: u64::from(MAX_ARRAY_LEN) + 1,
+                max: u64::from(MAX_ARRAY_LEN),
+            }))
+        );
+    }
+
+    #[test]
+    fn string_arrays_treat_null_entries_as_empty() {
+        let mut hello = c_chars::<6>("hello");
+        let mut items = [hello.as_mut_ptr(), ptr::null_mut()];
+        let prop = ffi::CorsairProperty {
+            type_: ffi::CorsairDataType_CT_String_Array,
+            value: ffi::CorsairDataValue {
+                string_array: ffi::CorsairDataType_StringArray {
+                    items: items.as_mut_ptr(),
+                    count: 2,
+                },
+            },
+        };
+        // SAFETY: `items` holds 2 pointers, each null or a C string.
+        let value = unsafe { PropertyValue::from_ffi(&prop) };
+        assert_eq!(
+            value,
+            Ok(PropertyValue::StringArray(vec![
+                "hello".into(),
+                String::new()
+            ]))
+        );
+    }
+
+    #[test]
+    fn unknown_data_types_cannot_be_read() {
+        let prop = ffi::CorsairProperty {
+            type_: 99,
+            value: ffi::CorsairDataValue { int32: 0 },
+        };
+        // SAFETY: The value is never read for an unknown type.
+        let value = unsafe { PropertyValue::from_ffi(&prop) };
+        assert_eq!(value, Err(SdkError::InvalidOperation));
+    }
+
+    #[test]
+    fn events_reject_null_and_unterminated_payloads() {
+        let null_key = ffi::CorsairEvent {
+            id: ffi::CorsairEventId_CEI_KeyEvent,
+            event_union: ffi::CorsairEventUnion {
+                keyEvent: ptr::null(),
+            },
+        };
+        assert_eq!(
+            Event::from_ffi(&null_key).unwrap_err(),
+            Malformed::NullPointer
+        );
+
+        let key = ffi::CorsairKeyEvent {
+            deviceId: c_chars("{keyboard}"),
+            keyId: 42,
+            isPressed: true,
+        };
+        let event = ffi::CorsairEvent {
+            id: ffi::CorsairEventId_CEI_KeyEvent,
+            event_union: ffi::CorsairEventUnion { keyEvent: &key },
+        };
+        assert!(matches!(
+            Event::from_ffi(&event),
+            Ok(Event::KeyEvent {
+                key_id: MacroKeyId::Unknown(raw),
+                is_pressed: true,
+                ..
+            }) if raw.get() == 42
+        ));
+
+        let unterminated = ffi::CorsairDeviceConnectionStatusChangedEvent {
+            deviceId: [b'x' as c_char; 128],
+            isConnected: true,
+        };
+        let event = ffi::CorsairEvent {
+            id: ffi::CorsairEventId_CEI_DeviceConnectionStatusChangedEvent,
+            event_union: ffi::CorsairEventUnion {
+                deviceConnectionStatusChangedEvent: &unterminated,
+            },
+        };
+        assert_eq!(
+            Event::from_ffi(&event).unwrap_err(),
+            Malformed::UnterminatedString
+        );
+
+        let unknown = ffi::CorsairEvent {
+            id: 7,
+            event_union: ffi::CorsairEventUnion {
+                keyEvent: ptr::null(),
+            },
+        };
+        assert!(matches!(Event::from_ffi(&unknown), Ok(Event::Unknown(7))));
+    }
+
+    #[test]
+    fn channel_device_arrays_must_match() {
+        let devices = ChannelDevice::from_ffi_arrays(&[4, 8], &[1, 7]).unwrap();
+        assert_eq!(devices[1].device_type, ChannelDeviceType::Strip);
+        assert_eq!(devices[1].led_count, 8);
+
+        assert_eq!(
+            ChannelDevice::from_ffi_arrays(&[4, 8], &[1]),
+            Err(Malformed::LengthMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            ChannelDevice::from_ffi_arrays(&[-4], &[1]),
+            Err(Malformed::NegativeCount(-4))
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Polling device properties for changes.
+//!
+//! The SDK has no events for property changes such as the microphone being
+//! muted or the equalizer preset being switched.  A [`PropertyWatcher`]
+//! reads a set of properties, each at its own interval, and reports a
+//! [`PropertyEvent`] whenever a value differs from the previous reading.
+//!
+//! When a read fails, typically because the device was disconnected, the
+//! watch is retried with a [`Backoff`] instead of its normal interval until
+//! it succeeds again.
+//!
+//! Events are returned from [`poll`](PropertyWatcher::poll) and sent to
+//! every channel from [`subscribe`](PropertyWatcher::subscribe) and, with
+//! the `async` feature, [`subscribe_async`](PropertyWatcher::subscribe_async).
+//!
+//! ```no_run
+//! # use std::ops::ControlFlow;
+//! # use std::time::Duration;
+//! # use cue_sdk::{DeviceType, PropertyId};
+//! # use cue_sdk::watcher::PropertyWatcher;
+//! # let session = cue_sdk::connect().unwrap();
+//! let headset = &session.get_devices(DeviceType::HEADSET).unwrap()[0];
+//! let mut watcher = PropertyWatcher::new();
+//! watcher.watch(&headset.id, PropertyId::MicEnabled, 0, Duration::from_millis(250));
+//! watcher.watch(&headset.id, PropertyId::BatteryLevel, 0, Duration::from_secs(60));
+//!
+//! watcher.run(&session, |event| {
+//!     println!("{event:?}");
+//!     ControlFlow::Continue(())
+//! });
+//! ```
+
+use std::ops::ControlFlow;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::device::DeviceId;
+use crate::error::{Error, Result};
+use crate::gesture::{Clock, SystemClock};
+use crate::property::{PropertyId, PropertyValue};
+use crate::retry::Backoff;
+use crate::session::Session;
+use crate::trace;
+
+// ---------------------------------------------------------------------------
+// WatchKey / PropertyEvent
+// ---------------------------------------------------------------------------
+
+/// Identifies one watched property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchKey {
+    pub device_id: DeviceId,
+    pub property: PropertyId,
+    pub index: u32,
+}
+
+/// A change observed by a [`PropertyWatcher`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyEvent {
+    /// The property was read and differs from the previous reading.  `old`
+    /// is `None` for the first reading.
+    Changed {
+        key: WatchKey,
+        old: Option<PropertyValue>,
+        new: PropertyValue,
+    },
+    /// The property could not be read.  Reported once; the next successful
+    /// read is reported as `Changed` even if the value is unchanged.
+    Unavailable { key: WatchKey, error: Error },
+}
+
+impl PropertyEvent {
+    /// The property the event is about.
+    pub fn key(&self) -> &WatchKey {
+        match self {
+            Self::Changed { key, .. } | Self::Unavailable { key, .. } => key,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PropertyWatcher
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+struct Watch {
+    key: WatchKey,
+    interval: Duration,
+    due: Instant,
+    failures: u32,
+    last: Option<PropertyValue>,
+}
+
+/// Polls device properties and reports changes.
+#[derive(Debug)]
+pub struct PropertyWatcher<C: Clock = SystemClock> {
+    clock: C,
+    backoff: Backoff,
+    watches: Vec<Watch>,
+    listeners: Vec<mpsc::Sender<PropertyEvent>>,
+    #[cfg(feature = "async")]
+    async_listeners: Vec<tokio::sync::mpsc::UnboundedSender<PropertyEvent>>,
+}
+
+impl Default for PropertyWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PropertyWatcher {
+    /// Create a watcher using the system clock.
+    ///
+    /// Failed reads are retried after 1 s, doubling up to 30 s.
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl<C: Clock> PropertyWatcher<C> {
+    /// Create a watcher that reads time from `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            clock,
+            backoff: Backoff::Exponential {
+                initial: Duration::from_secs(1),
+                max: Duration::from_secs(30),
+                factor: 2.0,
+            },
+            watches: Vec::new(),
+            listeners: Vec::new(),
+            #[cfg(feature = "async")]
+            async_listeners: Vec::new(),
+        }
+    }
+
+    /// Wait according to `backoff` between reads of a property that failed.
+    ///
+    /// A watch is never read more often than its own interval.
+    pub fn set_backoff(&mut self, backoff: Backoff) {
+        self.backoff = backoff;
+    }
+
+    /// Read `property` at `index` of a device every `interval`.
+    ///
+    /// The first read happens on the next poll.  Watching a property that is
+    /// already watched changes its interval.
+    pub fn watch(
+        &mut self,
+        device_id: &DeviceId,
+        property: PropertyId,
+        index: u32,
+        interval: Duration,
+    ) -> WatchKey {
+        let key = WatchKey {
+            device_id: *device_id,
+            property,
+            index,
+        };
+        let now = self.clock.now();
+        match self.watches.iter_mut().find(|w| w.key == key) {
+            Some(watch) => {
+                watch.interval = interval;
+                watch.due = watch.due.min(now + interval);
+            }
+            None => self.watches.push(Watch {
+                key,
+                interval,
+                due: now,
+                failures: 0,
+                last: None,
+            }),
+        }
+        key
+    }
+
+    /// Stop watching a property.  Returns `false` if it was not watched.
+    pub fn unwatch(&mut self, key: &WatchKey) -> bool {
+        let before = self.watches.len();
+        self.watches.retain(|w| w.key != *key);
+        self.watches.len() != before
+    }
+
+    /// Stop watching every property of a device.
+    pub fn unwatch_device(&mut self, device_id: &DeviceId) {
+        self.watches.retain(|w| w.key.device_id != *device_id);
+    }
+
+    /// The last value read for a property.
+    pub fn value(&self, key: &WatchKey) -> Option<&PropertyValue> {
+        self.watches.iter().find(|w| w.key == *key)?.last.as_ref()
+    }
+
+    /// Iterate over the watched properties.
+    pub fn keys(&self) -> impl Iterator<Item = &WatchKey> + '_ {
+        self.watches.iter().map(|w| &w.key)
+    }
+
+    /// Receive every future [`PropertyEvent`] on a channel.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<PropertyEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.listeners.push(tx);
+        rx
+    }
+
+    /// Receive every future [`PropertyEvent`] on an async channel.
+    ///
+    /// Requires the `async` feature.
+    #[cfg(feature = "async")]
+    pub fn subscribe_async(&mut self) -> tokio::sync::mpsc::UnboundedReceiver<PropertyEvent> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.async_listeners.push(tx);
+        rx
+    }
+
+    /// When the next watch is due, or `None` if nothing is watched.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.watches.iter().map(|w| w.due).min()
+    }
+
+    /// Read every watch that is due from the SDK.
+    pub fn poll(&mut self, session: &Session) -> Vec<PropertyEvent> {
+        self.poll_with(|device_id, property, index| {
+            session.read_device_property(device_id, property, index)
+        })
+    }
+
+    /// Read every watch that is due with `read`, e.g. from a cache or a test
+    /// double.
+    pub fn poll_with(
+        &mut self,
+        mut read: impl FnMut(&DeviceId, PropertyId, u32) -> Result<PropertyValue>,
+    ) -> Vec<PropertyEvent> {
+        let now = self.clock.now();
+        let mut events = Vec::new();
+        for watch in self.watches.iter_mut().filter(|w| w.due <= now) {
+            let key = watch.key;
+            match read(&key.device_id, key.property, key.index) {
+                Ok(value) => {
+                    watch.due = now + watch.interval;
+                    let recovered = watch.failures > 0;
+                    watch.failures = 0;
+                    if recovered || watch.last.as_ref() != Some(&value) {
+                        events.push(PropertyEvent::Changed {
+                            key,
+                            old: watch.last.replace(value.clone()),
+                            new: value,
+                        });
+                    }
+                }
+                Err(error) => {
+                    watch.failures += 1;
+                    let delay = self.backoff.delay(watch.failures).max(watch.interval);
+                    watch.due = now + delay;
+                    trace::event!(
+                        debug,
+                        device = %key.device_id,
+                        property = ?key.property,
+                        error = %error,
+                        retry_ms = delay.as_millis() as u64,
+                        "watched property unavailable"
+                    );
+                    if watch.failures == 1 {
+                        events.push(PropertyEvent::Unavailable { key, error });
+                    }
+                }
+            }
+        }
+        self.emit(&events);
+        events
+    }
+
+    /// Poll until `on_event` returns [`ControlFlow::Break`], sleeping until
+    /// the next watch is due in between.
+    ///
+    /// Returns immediately if nothing is watched.
+    pub fn run(
+        &mut self,
+        session: &Session,
+        mut on_event: impl FnMut(&PropertyEvent) -> ControlFlow<()>,
+    ) {
+        while let Some(due) = self.next_due() {
+            let wait = due.saturating_duration_since(self.clock.now());
+            if !wait.is_zero() {
+                std::thread::sleep(wait);
+            }
+            for event in self.poll(session) {
+                if on_event(&event).is_break() {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn emit(&mut self, events: &[PropertyEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        self.listeners
+            .retain(|tx| events.iter().all(|event| tx.send(event.clone()).is_ok()));
+        #[cfg(feature = "async")]
+        self.async_listeners
+            .retain(|tx| events.iter().all(|event| tx.send(event.clone()).is_ok()));
+    }
+}
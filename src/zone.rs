@@ -0,0 +1,656 @@
+//! Named LED zones and device selectors.
+//!
+//! A [`Zone`] is a set of LED LUIDs stored as sorted, disjoint ranges, so
+//! whole LED groups (such as a DIY channel or the keyboard logo) cost no more
+//! than a single key.  [`Zones`] maps names to zones and ships with built-in
+//! keyboard zones.  A [`Selector`] such as `keyboard:F1..F12`, `mouse:*` or
+//! `channel:1/fan:2` picks LEDs on any device it matches, and is resolved
+//! against a [`DeviceLayout`].
+//!
+//! # Zone specs
+//!
+//! Zone definitions and the LED part of a selector share one syntax: a
+//! comma-separated list of items, where each item is
+//!
+//! - a keyboard key name from [`led::keyboard`](crate::led::keyboard)
+//!   (`W`, `ESCAPE`, `1`), or a raw LUID (`42`, `0x40001`);
+//! - an inclusive range of either (`F1..F12`);
+//! - a zone reference (`@wasd`);
+//! - `*` for every LED.
+//!
+//! Items prefixed with `!` are removed from the result; a spec made only of
+//! removals starts from every LED.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::device::{ChannelDevice, ChannelDeviceType, DeviceInfo, DeviceType};
+use crate::error::Result;
+use crate::led::{keyboard, LedGroup, LedPosition};
+use crate::session::Session;
+
+// ---------------------------------------------------------------------------
+// ZoneError
+// ---------------------------------------------------------------------------
+
+/// Errors produced while parsing zone specs, zone configs and selectors.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ZoneError {
+    #[error("unknown key name `{0}`")]
+    UnknownKey(String),
+    #[error("unknown zone `{0}`")]
+    UnknownZone(String),
+    #[error("unknown selector target `{0}`")]
+    UnknownTarget(String),
+    #[error("invalid range `{0}`")]
+    InvalidRange(String),
+    #[error("syntax error: {0}")]
+    Syntax(String),
+}
+
+// ---------------------------------------------------------------------------
+// Zone
+// ---------------------------------------------------------------------------
+
+/// A set of LED LUIDs.
+///
+/// Zones are device-independent; use [`resolve`](Self::resolve) to pick the
+/// LEDs of a particular device that fall inside the zone.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Zone {
+    // Sorted, disjoint, non-adjacent inclusive ranges.
+    ranges: Vec<(u32, u32)>,
+}
+
+/// Names of the zones returned by [`Zone::builtin`].
+pub const BUILTIN_ZONES: &[&str] = &[
+    "wasd",
+    "f_row",
+    "number_row",
+    "arrows",
+    "navigation",
+    "numpad",
+    "media",
+    "modifiers",
+    "logo",
+    "g_keys",
+];
+
+impl Zone {
+    /// An empty zone.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A zone containing every possible LUID.
+    pub fn all() -> Self {
+        Self::range(0, u32::MAX)
+    }
+
+    /// A zone containing every LUID from `first` to `last` inclusive.
+    ///
+    /// The result is empty if `first > last`.
+    pub fn range(first: u32, last: u32) -> Self {
+        if first > last {
+            return Self::new();
+        }
+        Self {
+            ranges: vec![(first, last)],
+        }
+    }
+
+    /// A zone containing every LED of an LED group.
+    pub fn group(group: LedGroup) -> Self {
+        let (first, last) = group.luid_range();
+        Self::range(first, last)
+    }
+
+    /// A zone containing exactly the given LUIDs.
+    pub fn from_leds(leds: impl IntoIterator<Item = u32>) -> Self {
+        Self::from_ranges(leds.into_iter().map(|luid| (luid, luid)).collect())
+    }
+
+    /// Look up one of the built-in keyboard zones listed in [`BUILTIN_ZONES`].
+    pub fn builtin(name: &str) -> Option<Self> {
+        use keyboard as k;
+        let zone = match name {
+            "wasd" => Self::from_leds([k::W, k::A, k::S, k::D]),
+            "f_row" => Self::range(k::F1, k::F12),
+            "number_row" => Self::range(k::KEY_1, k::KEY_0),
+            "arrows" => Self::range(k::UP_ARROW, k::RIGHT_ARROW),
+            "navigation" => Self::range(k::INSERT, k::PAGE_DOWN),
+            "numpad" => Self::range(k::NUM_LOCK, k::KEYPAD_PERIOD_DELETE),
+            "media" => Self::range(k::MUTE, k::SCAN_NEXT_TRACK)
+                .union(&Self::from_leds([k::VOLUME_UP, k::VOLUME_DOWN])),
+            "modifiers" => Self::from_leds([
+                k::LEFT_SHIFT,
+                k::RIGHT_SHIFT,
+                k::LEFT_CTRL,
+                k::RIGHT_CTRL,
+                k::LEFT_ALT,
+                k::RIGHT_ALT,
+                k::LEFT_GUI,
+                k::RIGHT_GUI,
+                k::FN,
+            ]),
+            "logo" => Self::group(LedGroup::KeyboardOem),
+            "g_keys" => Self::group(LedGroup::KeyboardGKeys),
+            _ => return None,
+        };
+        Some(zone)
+    }
+
+    /// Whether the zone contains no LUIDs.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Whether `luid` is part of the zone.
+    pub fn contains(&self, luid: u32) -> bool {
+        let idx = self.ranges.partition_point(|&(_, last)| last < luid);
+        self.ranges
+            .get(idx)
+            .is_some_and(|&(first, _)| first <= luid)
+    }
+
+    /// The inclusive LUID ranges making up this zone, in ascending order.
+    pub fn ranges(&self) -> &[(u32, u32)] {
+        &self.ranges
+    }
+
+    /// LUIDs present in either zone.
+    pub fn union(&self, other: &Zone) -> Zone {
+        let mut ranges = self.ranges.clone();
+        ranges.extend_from_slice(&other.ranges);
+        Self::from_ranges(ranges)
+    }
+
+    /// LUIDs present in both zones.
+    pub fn intersection(&self, other: &Zone) -> Zone {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a0, a1) = self.ranges[i];
+            let (b0, b1) = other.ranges[j];
+            let (lo, hi) = (a0.max(b0), a1.min(b1));
+            if lo <= hi {
+                ranges.push((lo, hi));
+            }
+            if a1 < b1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Zone { ranges }
+    }
+
+    /// LUIDs present in this zone but not in `other`.
+    pub fn difference(&self, other: &Zone) -> Zone {
+        let mut ranges = Vec::new();
+        for &(first, last) in &self.ranges {
+            let mut lo = first;
+            let mut done = false;
+            for &(b0, b1) in &other.ranges {
+                if b1 < lo || b0 > last {
+                    continue;
+                }
+                if b0 > lo {
+                    ranges.push((lo, b0 - 1));
+                }
+                if b1 >= last {
+                    done = true;
+                    break;
+                }
+                lo = b1 + 1;
+            }
+            if !done {
+                ranges.push((lo, last));
+            }
+        }
+        Zone { ranges }
+    }
+
+    /// Pick the LEDs of a device that fall inside this zone.
+    ///
+    /// The LUIDs are returned in the order of `positions`.
+    pub fn resolve(&self, positions: &[LedPosition]) -> Vec<u32> {
+        positions
+            .iter()
+            .map(|pos| pos.id)
+            .filter(|&luid| self.contains(luid))
+            .collect()
+    }
+
+    fn from_ranges(mut ranges: Vec<(u32, u32)>) -> Self {
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (first, last) in ranges {
+            match merged.last_mut() {
+                Some(prev) if first <= prev.1.saturating_add(1) => prev.1 = prev.1.max(last),
+                _ => merged.push((first, last)),
+            }
+        }
+        Self { ranges: merged }
+    }
+}
+
+impl FromIterator<u32> for Zone {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        Self::from_leds(iter)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Zone specs
+// ---------------------------------------------------------------------------
+
+/// A parsed, not yet evaluated zone spec (see the module docs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ZoneSpec {
+    items: Vec<(bool, Term)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    All,
+    Range(u32, u32),
+    Zone(String),
+}
+
+impl ZoneSpec {
+    fn parse(spec: &str) -> std::result::Result<Self, ZoneError> {
+        let mut items = Vec::new();
+        for raw in spec.split(',') {
+            let item = raw.trim();
+            if item.is_empty() {
+                return Err(ZoneError::Syntax(format!("empty item in `{spec}`")));
+            }
+            let (exclude, item) = match item.strip_prefix('!') {
+                Some(rest) => (true, rest.trim()),
+                None => (false, item),
+            };
+            let term = if item == "*" {
+                Term::All
+            } else if let Some(name) = item.strip_prefix('@') {
+                Term::Zone(name.to_owned())
+            } else if let Some((first, last)) = item.split_once("..") {
+                let (first, last) = (parse_luid(first.trim())?, parse_luid(last.trim())?);
+                if first > last {
+                    return Err(ZoneError::InvalidRange(item.to_owned()));
+                }
+                Term::Range(first, last)
+            } else {
+                let luid = parse_luid(item)?;
+                Term::Range(luid, luid)
+            };
+            items.push((exclude, term));
+        }
+        Ok(Self { items })
+    }
+
+    fn build(&self, zones: &Zones) -> std::result::Result<Zone, ZoneError> {
+        let only_exclusions = self.items.iter().all(|(exclude, _)| *exclude);
+        let mut zone = if only_exclusions {
+            Zone::all()
+        } else {
+            Zone::new()
+        };
+        for (exclude, term) in &self.items {
+            let part = match term {
+                Term::All => Zone::all(),
+                Term::Range(first, last) => Zone::range(*first, *last),
+                Term::Zone(name) => zones
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| ZoneError::UnknownZone(name.clone()))?,
+            };
+            zone = if *exclude {
+                zone.difference(&part)
+            } else {
+                zone.union(&part)
+            };
+        }
+        Ok(zone)
+    }
+}
+
+fn parse_luid(s: &str) -> std::result::Result<u32, ZoneError> {
+    let number = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        // Bare digits are key names (`1` is `KEY_1`), so only multi-digit
+        // decimal numbers are treated as raw LUIDs.
+        None if s.len() > 1 => s.parse().ok(),
+        None => None,
+    };
+    number
+        .or_else(|| keyboard::from_name(s))
+        .ok_or_else(|| ZoneError::UnknownKey(s.to_owned()))
+}
+
+// ---------------------------------------------------------------------------
+// Zones
+// ---------------------------------------------------------------------------
+
+/// A collection of named zones.
+#[derive(Debug, Clone, Default)]
+pub struct Zones {
+    zones: HashMap<String, Zone>,
+}
+
+impl Zones {
+    /// An empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A collection pre-populated with every built-in zone.
+    pub fn builtin() -> Self {
+        let zones = BUILTIN_ZONES
+            .iter()
+            .filter_map(|&name| Some((name.to_owned(), Zone::builtin(name)?)))
+            .collect();
+        Self { zones }
+    }
+
+    /// Look up a zone by name.
+    pub fn get(&self, name: &str) -> Option<&Zone> {
+        self.zones.get(name)
+    }
+
+    /// Add or replace a zone, returning the previous one with that name.
+    pub fn insert(&mut self, name: impl Into<String>, zone: Zone) -> Option<Zone> {
+        self.zones.insert(name.into(), zone)
+    }
+
+    /// Remove a zone by name.
+    pub fn remove(&mut self, name: &str) -> Option<Zone> {
+        self.zones.remove(name)
+    }
+
+    /// Iterate over the names of all zones, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.zones.keys().map(String::as_str)
+    }
+
+    /// Evaluate a zone spec such as `F1..F12, !F6, @wasd` against the zones
+    /// in this collection.
+    pub fn parse_zone(&self, spec: &str) -> std::result::Result<Zone, ZoneError> {
+        ZoneSpec::parse(spec)?.build(self)
+    }
+
+    /// Load user-defined zones from a config string.
+    ///
+    /// Each non-empty line has the form `name = spec`; text after `#` is a
+    /// comment.  Definitions are applied in order, so a zone may reference
+    /// any zone defined before it.  On error, zones from earlier lines are
+    /// kept.
+    ///
+    /// ```
+    /// # use cue_sdk::zone::Zones;
+    /// let mut zones = Zones::builtin();
+    /// zones.load("movement = @wasd, SPACE\nfkeys = F1..F12, !F6").unwrap();
+    /// assert!(zones.get("movement").unwrap().contains(cue_sdk::led::keyboard::SPACE));
+    /// ```
+    pub fn load(&mut self, config: &str) -> std::result::Result<(), ZoneError> {
+        for (lineno, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, spec) = line.split_once('=').ok_or_else(|| {
+                ZoneError::Syntax(format!("line {}: expected `name = spec`", lineno + 1))
+            })?;
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(ZoneError::Syntax(format!(
+                    "line {}: invalid zone name `{name}`",
+                    lineno + 1
+                )));
+            }
+            let zone = self.parse_zone(spec.trim())?;
+            self.insert(name, zone);
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// DeviceLayout
+// ---------------------------------------------------------------------------
+
+/// Everything needed to resolve a [`Selector`] against one device.
+#[derive(Debug, Clone)]
+pub struct DeviceLayout {
+    /// The device itself.
+    pub info: DeviceInfo,
+    /// Positions of every LED on the device.
+    pub positions: Vec<LedPosition>,
+    /// Devices attached to each DIY channel, indexed by zero-based channel.
+    pub channels: Vec<Vec<ChannelDevice>>,
+}
+
+impl DeviceLayout {
+    /// Query the LED positions and channel devices of `info` from the SDK.
+    ///
+    /// Channels whose attached devices cannot be read are left empty.
+    pub fn query(session: &Session, info: &DeviceInfo) -> Result<Self> {
+        let positions = session.get_led_positions(&info.id)?;
+        let channels = (0..info.channel_count.max(0) as u32)
+            .map(|index| {
+                session
+                    .get_channel_devices(&info.id, index)
+                    .unwrap_or_default()
+            })
+            .collect();
+        Ok(Self {
+            info: info.clone(),
+            positions,
+            channels,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Selector
+// ---------------------------------------------------------------------------
+
+/// Device type names accepted as selector targets.
+const TARGETS: &[(&str, DeviceType)] = &[
+    ("keyboard", DeviceType::KEYBOARD),
+    ("mouse", DeviceType::MOUSE),
+    ("mousemat", DeviceType::MOUSEMAT),
+    ("headset", DeviceType::HEADSET),
+    ("headset_stand", DeviceType::HEADSET_STAND),
+    ("fan_led_controller", DeviceType::FAN_LED_CONTROLLER),
+    ("led_controller", DeviceType::LED_CONTROLLER),
+    ("memory_module", DeviceType::MEMORY_MODULE),
+    ("cooler", DeviceType::COOLER),
+    ("motherboard", DeviceType::MOTHERBOARD),
+    ("graphics_card", DeviceType::GRAPHICS_CARD),
+    ("touchbar", DeviceType::TOUCHBAR),
+    ("game_controller", DeviceType::GAME_CONTROLLER),
+    ("*", DeviceType::ALL),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelKind {
+    Any,
+    Fan,
+    Strip,
+    Pump,
+}
+
+impl ChannelKind {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "device" => Some(Self::Any),
+            "fan" => Some(Self::Fan),
+            "strip" => Some(Self::Strip),
+            "pump" => Some(Self::Pump),
+            _ => None,
+        }
+    }
+
+    fn matches(self, ty: ChannelDeviceType) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Fan => ty.is_fan(),
+            Self::Strip => ty == ChannelDeviceType::Strip,
+            Self::Pump => ty == ChannelDeviceType::Pump,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Type(DeviceType, ZoneSpec),
+    Channel {
+        channel: u32,
+        device: Option<(ChannelKind, u32)>,
+    },
+}
+
+/// A textual description of LEDs on matching devices.
+///
+/// | Selector | Meaning |
+/// |----------|---------|
+/// | `keyboard:F1..F12` | The F-row on every keyboard |
+/// | `keyboard:@wasd, SPACE` | A zone plus a key |
+/// | `mouse:*` or `mouse` | Every LED on every mouse |
+/// | `*:!@logo` | Every LED on every device except keyboard logos |
+/// | `channel:1` | Every LED on DIY channel 1 |
+/// | `channel:1/fan:2` | The second fan on DIY channel 1 |
+///
+/// Channel devices may be picked by `fan`, `strip`, `pump` or `device` (any
+/// kind); the number counts only devices of that kind, starting at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    source: String,
+    target: Target,
+}
+
+impl Selector {
+    /// Whether this selector can pick LEDs on the given device.
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        match &self.target {
+            Target::Type(ty, _) => *ty == DeviceType::ALL || device.device_type.intersects(*ty),
+            Target::Channel { channel, .. } => *channel as i32 <= device.channel_count,
+        }
+    }
+
+    /// Resolve the selector against a device using the built-in zones.
+    ///
+    /// Returns an empty list if the selector does not match the device.
+    pub fn resolve(&self, layout: &DeviceLayout) -> std::result::Result<Vec<u32>, ZoneError> {
+        self.resolve_in(&Zones::builtin(), layout)
+    }
+
+    /// Resolve the selector against a device, looking up `@zone` references
+    /// in `zones`.
+    ///
+    /// LUIDs are returned in the order of `layout.positions`.  Returns an
+    /// empty list if the selector does not match the device.
+    pub fn resolve_in(
+        &self,
+        zones: &Zones,
+        layout: &DeviceLayout,
+    ) -> std::result::Result<Vec<u32>, ZoneError> {
+        if !self.matches(&layout.info) {
+            return Ok(Vec::new());
+        }
+        match &self.target {
+            Target::Type(_, spec) => Ok(spec.build(zones)?.resolve(&layout.positions)),
+            Target::Channel { channel, device } => {
+                let Some(group) = LedGroup::diy_channel(*channel) else {
+                    return Ok(Vec::new());
+                };
+                let mut leds = Zone::group(group).resolve(&layout.positions);
+                leds.sort_unstable();
+                let Some((kind, ordinal)) = device else {
+                    return Ok(leds);
+                };
+                let attached = layout
+                    .channels
+                    .get(*channel as usize - 1)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let mut offset = 0usize;
+                let mut seen = 0;
+                for dev in attached {
+                    let count = dev.led_count as usize;
+                    if kind.matches(dev.device_type) {
+                        seen += 1;
+                        if seen == *ordinal {
+                            let end = (offset + count).min(leds.len());
+                            return Ok(leds.get(offset..end).unwrap_or_default().to_vec());
+                        }
+                    }
+                    offset += count;
+                }
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = ZoneError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let source = s.trim();
+        let (target, rest) = match source.split_once(':') {
+            Some((target, rest)) => (target.trim(), Some(rest.trim())),
+            None => (source, None),
+        };
+
+        let target = if target == "channel" {
+            let rest = rest.ok_or_else(|| ZoneError::Syntax("missing channel number".into()))?;
+            let (channel, device) = match rest.split_once('/') {
+                Some((channel, device)) => (channel, Some(device)),
+                None => (rest, None),
+            };
+            let channel = parse_ordinal(channel)?;
+            let device = device
+                .map(|device| {
+                    let (kind, ordinal) = device.split_once(':').ok_or_else(|| {
+                        ZoneError::Syntax(format!("expected `kind:n`, got `{device}`"))
+                    })?;
+                    let kind = ChannelKind::parse(kind.trim())
+                        .ok_or_else(|| ZoneError::UnknownTarget(kind.trim().to_owned()))?;
+                    Ok((kind, parse_ordinal(ordinal)?))
+                })
+                .transpose()?;
+            Target::Channel { channel, device }
+        } else {
+            let ty = TARGETS
+                .iter()
+                .find(|(name, _)| *name == target)
+                .map(|&(_, ty)| ty)
+                .ok_or_else(|| ZoneError::UnknownTarget(target.to_owned()))?;
+            Target::Type(ty, ZoneSpec::parse(rest.unwrap_or("*"))?)
+        };
+
+        Ok(Self {
+            source: source.to_owned(),
+            target,
+        })
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_ordinal(s: &str) -> std::result::Result<u32, ZoneError> {
+    match s.trim().parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(ZoneError::Syntax(format!(
+            "expected a number starting at 1, got `{}`",
+            s.trim()
+        ))),
+    }
+}
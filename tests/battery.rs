@@ -0,0 +1,70 @@
+//! Battery threshold tracking and indicator colors.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::battery::{BatteryEvent, BatteryIndicator, BatteryMonitor};
+use cue_sdk::zone::Zone;
+use cue_sdk::{DeviceId, Rgba};
+
+#[test]
+fn thresholds_fire_once_until_rearmed() {
+    let device_id: DeviceId = "{headset}".parse().unwrap();
+    let mut monitor = BatteryMonitor::new();
+    let events = monitor.subscribe();
+    let alerts = |monitor: &mut BatteryMonitor, level| {
+        monitor
+            .record(&device_id, level)
+            .into_iter()
+            .filter(|e| !matches!(e, BatteryEvent::Changed { .. }))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(alerts(&mut monitor, 50), []);
+    assert!(monitor.record(&device_id, 50).is_empty());
+    assert_eq!(
+        alerts(&mut monitor, 20),
+        [BatteryEvent::Low {
+            device_id,
+            level: 20
+        }]
+    );
+    assert_eq!(alerts(&mut monitor, 15), []);
+    assert_eq!(
+        alerts(&mut monitor, 5),
+        [BatteryEvent::Critical {
+            device_id,
+            level: 5
+        }]
+    );
+    assert_eq!(alerts(&mut monitor, 60), []);
+    assert_eq!(
+        alerts(&mut monitor, 100),
+        [BatteryEvent::ChargingComplete { device_id }]
+    );
+    assert_eq!(
+        alerts(&mut monitor, 19),
+        [BatteryEvent::Low {
+            device_id,
+            level: 19
+        }]
+    );
+    assert_eq!(monitor.level(&device_id), Some(19));
+
+    assert_eq!(
+        events.try_recv().unwrap(),
+        BatteryEvent::Changed {
+            device_id,
+            previous: None,
+            level: 50
+        }
+    );
+    assert_eq!(events.try_iter().count(), 10);
+}
+
+#[test]
+fn indicator_fades_between_colors() {
+    let indicator = BatteryIndicator::new(Zone::new()).colors(Rgba::BLACK, Rgba::rgb(200, 100, 0));
+    assert_eq!(indicator.color(0), Rgba::BLACK);
+    assert_eq!(indicator.color(50), Rgba::rgb(100, 50, 0));
+    assert_eq!(indicator.color(150), Rgba::rgb(200, 100, 0));
+}
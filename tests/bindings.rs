@@ -0,0 +1,97 @@
+//! Bindings config parsing and gesture matching.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::bindings::{Action, BindingError, Bindings};
+use cue_sdk::gesture::Gesture;
+use cue_sdk::MacroKeyId::{Key1, Key2, Key3};
+use cue_sdk::{DeviceInfo, DeviceType, PropertyId};
+
+fn device(device_type: DeviceType) -> DeviceInfo {
+    DeviceInfo {
+        device_type,
+        id: "{device}".parse().unwrap(),
+        serial: "SERIAL".into(),
+        model: "Model".into(),
+        led_count: 0,
+        channel_count: 0,
+    }
+}
+
+const CONFIG: &str = "\
+# selector  gesture      = action
+keyboard    tap(G1)      = exec echo '#1'
+keyboard    long_press(G2) = profile gaming   # comment
+headset     tap(G1)      = toggle mic_enabled
+*           chord(G1+G2) = emit panic
+keyboard    sequence(G1,G1,G3) = emit combo
+keyboard    hold_repeat(G3) = emit volume
+";
+
+#[test]
+fn parse_and_match() {
+    let bindings = Bindings::parse(CONFIG).unwrap();
+    assert_eq!(bindings.len(), 6);
+
+    let keyboard = device(DeviceType::KEYBOARD);
+    let headset = device(DeviceType::HEADSET);
+    let actions = |device, gesture| {
+        bindings
+            .actions(device, &gesture)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        actions(&keyboard, Gesture::Tap(Key1)),
+        [Action::Exec("echo '#1'".into())]
+    );
+    assert_eq!(
+        actions(&headset, Gesture::Tap(Key1)),
+        [Action::Toggle(PropertyId::MicEnabled)]
+    );
+    assert_eq!(
+        actions(&keyboard, Gesture::LongPress(Key2)),
+        [Action::Profile("gaming".into())]
+    );
+    assert_eq!(
+        actions(&headset, Gesture::Chord(vec![Key1, Key2])),
+        [Action::Emit("panic".into())]
+    );
+    assert_eq!(
+        actions(
+            &keyboard,
+            Gesture::HoldRepeat {
+                key: Key3,
+                count: 4
+            }
+        ),
+        [Action::Emit("volume".into())]
+    );
+    assert!(actions(&headset, Gesture::LongPress(Key2)).is_empty());
+
+    assert_eq!(bindings.keys(&keyboard), [Key1, Key2, Key3]);
+    assert_eq!(bindings.keys(&headset), [Key1, Key2]);
+}
+
+#[test]
+fn parse_errors_name_the_line() {
+    let err =
+        Bindings::parse("keyboard tap(G1) = emit a\nkeyboard tap(G1, G2) = emit b").unwrap_err();
+    assert!(matches!(err, BindingError::Syntax { line: 2, .. }), "{err}");
+
+    for bad in [
+        "keyboard tap(G21) = emit a",
+        "keyboard swipe(G1) = emit a",
+        "keyboard chord(G1) = emit a",
+        "keyboard tap(G1) = toggle volume",
+        "keyboard tap(G1) = launch rockets",
+        "keyboard tap(G1)",
+    ] {
+        assert!(Bindings::parse(bad).is_err(), "{bad}");
+    }
+    assert!(matches!(
+        Bindings::parse("toaster tap(G1) = emit a"),
+        Err(BindingError::Selector { line: 1, .. })
+    ));
+}
@@ -0,0 +1,58 @@
+//! Connecting through `SessionBuilder`.
+//!
+//! These tests do not need the iCUE SDK; with the `dynamic` feature and no
+//! library installed, every connection attempt fails at the first step.
+
+#![cfg(feature = "dynamic")]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cue_sdk::retry::{Backoff, RetryPolicy};
+use cue_sdk::{dynamic, AccessLevel, DeviceType, Operation, SdkError, Session};
+
+// One test, because the search paths are process-wide.
+#[test]
+fn failed_connection_names_the_step_and_attempts() {
+    dynamic::set_search_paths([std::env::temp_dir().join("cue-sdk-no-such-dir")]);
+
+    let retries = Arc::new(AtomicU32::new(0));
+    let counter = Arc::clone(&retries);
+    let policy = RetryPolicy::default()
+        .max_attempts(3)
+        .backoff(Backoff::None)
+        .retry_on(&[SdkError::LibraryNotFound])
+        .on_retry(move |ev| {
+            assert_eq!(ev.operation(), Some(Operation::Connect));
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+    let err = Session::builder()
+        .timeout(Duration::from_millis(10))
+        .reconnect(policy)
+        .layer_priority(200)
+        .request_control(DeviceType::KEYBOARD, AccessLevel::ExclusiveLightingControl)
+        .subscribe_for_events(true)
+        .connect()
+        .err()
+        .expect("no SDK library is installed");
+    assert_eq!(err.kind(), SdkError::LibraryNotFound);
+    assert_eq!(err.operation(), Some(Operation::Connect));
+    assert_eq!(retries.load(Ordering::SeqCst), 2);
+    let detail = err.detail().unwrap();
+    assert!(detail.starts_with("tried "), "{detail}");
+    assert!(detail.ends_with("; gave up after 3 attempt(s)"), "{detail}");
+    assert!(err.to_string().starts_with("connect failed: "), "{err}");
+
+    // Errors the policy does not retry end the first attempt.
+    let err = Session::builder()
+        .reconnect(RetryPolicy::default().max_attempts(3))
+        .connect()
+        .err()
+        .unwrap();
+    assert!(err
+        .detail()
+        .unwrap()
+        .ends_with("gave up after 1 attempt(s)"));
+}
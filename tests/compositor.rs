@@ -0,0 +1,99 @@
+//! Layer blending and stacking.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::compositor::{BlendMode, Compositor, Layer};
+use cue_sdk::led::keyboard;
+use cue_sdk::zone::Zone;
+use cue_sdk::Rgba;
+
+#[test]
+fn blend_modes() {
+    let below = Rgba::rgb(128, 64, 255);
+    let above = Rgba::rgb(128, 255, 0);
+    assert_eq!(BlendMode::Normal.blend(below, above, 1.0), above);
+    assert_eq!(
+        BlendMode::Add.blend(below, above, 1.0),
+        Rgba::rgb(255, 255, 255)
+    );
+    assert_eq!(
+        BlendMode::Multiply.blend(below, above, 1.0),
+        Rgba::rgb(64, 64, 0)
+    );
+    assert_eq!(
+        BlendMode::Screen.blend(below, above, 1.0),
+        Rgba::rgb(192, 255, 255)
+    );
+    assert_eq!(
+        BlendMode::Overlay.blend(below, above, 1.0),
+        Rgba::rgb(128, 128, 255)
+    );
+    assert_eq!(
+        BlendMode::Max.blend(below, above, 1.0),
+        Rgba::rgb(128, 255, 255)
+    );
+
+    // Opacity and alpha both scale the effect of the layer.
+    assert_eq!(
+        BlendMode::Normal.blend(Rgba::BLACK, Rgba::WHITE, 0.5),
+        Rgba::rgb(128, 128, 128)
+    );
+    assert_eq!(
+        BlendMode::Normal.blend(Rgba::BLACK, Rgba::new(255, 255, 255, 0), 1.0),
+        Rgba::BLACK
+    );
+}
+
+#[test]
+fn layers_stack_and_can_be_removed_at_runtime() {
+    let mut compositor = Compositor::new();
+    compositor.push(Layer::new("base").with_fill(Rgba::rgb(0, 0, 100)));
+    let keys = compositor.push(Layer::new("keys"));
+    compositor
+        .layer_mut(keys)
+        .unwrap()
+        .fill_zone(&Zone::builtin("wasd").unwrap(), Rgba::rgb(200, 0, 0));
+    let dim = compositor.push(
+        Layer::new("dim")
+            .with_mode(BlendMode::Multiply)
+            .with_fill(Rgba::rgb(128, 128, 128)),
+    );
+
+    assert_eq!(compositor.color(keyboard::W), Rgba::rgb(100, 0, 0));
+    assert_eq!(compositor.color(keyboard::Q), Rgba::rgb(0, 0, 50));
+
+    compositor.remove(dim).unwrap();
+    assert_eq!(compositor.color(keyboard::W), Rgba::rgb(200, 0, 0));
+
+    compositor.move_to(keys, 0);
+    assert_eq!(compositor.color(keyboard::W), Rgba::rgb(0, 0, 100));
+    assert_eq!(
+        compositor.find("base"),
+        compositor.layers().nth(1).map(|(id, _)| id)
+    );
+}
+
+#[test]
+fn refilling_a_zone_replaces_its_color() {
+    let (red, green, blue) = (
+        Rgba::rgb(255, 0, 0),
+        Rgba::rgb(0, 255, 0),
+        Rgba::rgb(0, 0, 255),
+    );
+    let left = Zone::range(1, 10);
+    let right = Zone::range(6, 20);
+    let mut layer = Layer::new("zones");
+
+    layer.fill_zone(&left, red);
+    layer.fill_zone(&right, green);
+    assert_eq!(layer.get(8), Some(green));
+
+    // The refilled zone is now the most recent one.
+    layer.fill_zone(&left, blue);
+    assert_eq!(layer.get(8), Some(blue));
+    assert_eq!(layer.get(15), Some(green));
+
+    layer.fill_zone(&Zone::range(1, 10), red);
+    assert_eq!(layer.get(1), Some(red));
+    assert_eq!(layer.get(8), Some(red));
+}
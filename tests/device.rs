@@ -0,0 +1,74 @@
+//! LUID checks on `Device` handles.
+//!
+//! Like `smoke.rs`, this needs the SDK library but not iCUE: the device is
+//! built from a registry entry, so no device has to be connected.  Built
+//! with the `dynamic` feature and no library installed, it does nothing.
+
+use cue_sdk::registry::{Capabilities, DeviceEntry};
+use cue_sdk::{
+    Device, DeviceId, DeviceInfo, DeviceType, LedColor, LedPosition, Operation, Rgba, SdkError,
+};
+
+fn entry(device_id: DeviceId) -> DeviceEntry {
+    DeviceEntry {
+        info: DeviceInfo {
+            device_type: DeviceType::MOUSE,
+            id: device_id,
+            serial: "SN1".to_owned(),
+            model: "Test mouse".to_owned(),
+            led_count: 2,
+            channel_count: 0,
+        },
+        positions: vec![
+            LedPosition {
+                id: 10,
+                cx: 0.0,
+                cy: 0.0,
+            },
+            LedPosition {
+                id: 11,
+                cx: 1.0,
+                cy: 0.0,
+            },
+        ],
+        capabilities: Capabilities::default(),
+        connected: true,
+    }
+}
+
+// One test, because only one session can exist per process.
+#[test]
+fn luids_outside_the_device_are_rejected_before_the_sdk() {
+    let session = match cue_sdk::connect() {
+        Ok(session) => session,
+        Err(err) if err.kind() == SdkError::LibraryNotFound => return,
+        Err(err) => panic!("connect() should succeed even without iCUE: {err}"),
+    };
+    let device_id: DeviceId = "{mouse}".parse().unwrap();
+    let device = Device::from_entry(&session, &entry(device_id));
+    assert!(device.contains(10) && device.contains(11));
+    assert!(!device.contains(12));
+
+    let red = Rgba::rgb(255, 0, 0);
+    let mut colors = vec![LedColor::with_color(10, red), LedColor::with_color(12, red)];
+    let rejected = [
+        (device.set_colors(&colors), Operation::SetLedColors),
+        (device.buffer_colors(&colors), Operation::SetLedColorsBuffer),
+        (device.read_colors(&mut colors), Operation::GetLedColors),
+    ];
+    for (result, operation) in rejected {
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), SdkError::InvalidArguments);
+        assert_eq!(err.operation(), Some(operation));
+        assert_eq!(err.device_id(), Some(&device_id));
+        // Rejected by the handle, not returned by the SDK.
+        assert_eq!(err.code(), None);
+    }
+
+    // LUIDs of the device are passed on; without iCUE the SDK then fails.
+    let err = device
+        .set_colors(&[LedColor::with_color(11, red)])
+        .unwrap_err();
+    assert_eq!(err.kind(), SdkError::NotConnected);
+    assert!(err.code().is_some());
+}
@@ -0,0 +1,39 @@
+//! Runtime loading of the SDK library.
+//!
+//! These tests do not need the iCUE SDK; they check how a missing or
+//! incomplete library is reported.
+
+#![cfg(feature = "dynamic")]
+
+use cue_sdk::{dynamic, Operation, SdkError};
+
+// One test, because the search paths are process-wide.
+#[test]
+fn missing_library_is_reported_from_connect() {
+    let dir = std::env::temp_dir().join("cue-sdk-no-such-dir");
+    dynamic::set_search_paths([dir.join("iCUESDK.dll")]);
+    assert_eq!(dynamic::search_paths(), vec![dir.join("iCUESDK.dll")]);
+
+    let err = cue_sdk::connect()
+        .err()
+        .expect("no SDK library is installed");
+    assert_eq!(err.kind(), SdkError::LibraryNotFound);
+    assert_eq!(err.operation(), Some(Operation::Connect));
+    let detail = err.detail().unwrap();
+    assert!(detail.contains("cue-sdk-no-such-dir"), "{detail}");
+    assert!(detail.contains(dynamic::LIBRARY_NAME), "{detail}");
+    assert!(!dynamic::is_loaded());
+
+    // A library that exists but is not the SDK.
+    #[cfg(target_os = "linux")]
+    {
+        dynamic::set_search_paths(["libm.so.6"]);
+        let err = dynamic::load().unwrap_err();
+        assert_eq!(err.kind(), SdkError::SymbolMissing("CorsairConnect"));
+        assert!(
+            err.to_string().contains("no symbol `CorsairConnect`"),
+            "{err}"
+        );
+        assert!(!dynamic::is_loaded());
+    }
+}
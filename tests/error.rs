@@ -0,0 +1,98 @@
+//! Error kinds and their formatting.
+//!
+//! Errors without context are pure Rust.  The remaining test connects like
+//! `smoke.rs` and needs the SDK library but not iCUE; built with the
+//! `dynamic` feature and no library installed, it checks the connect error
+//! instead.
+
+use cue_sdk::{DeviceId, Error, Malformed, Operation, PropertyId, SdkError};
+
+#[test]
+fn errors_without_context_format_as_their_kind() {
+    let err = Error::from(SdkError::Timeout);
+    assert_eq!(err.kind(), SdkError::Timeout);
+    assert_eq!(err, SdkError::Timeout);
+    assert_ne!(err, SdkError::NotConnected);
+    assert_eq!(err.to_string(), "operation timed out");
+    assert_eq!(err.operation(), None);
+    assert_eq!(err.device_id(), None);
+    assert_eq!(err.property(), None);
+    assert_eq!(err.code(), None);
+    assert_eq!(err.session_state(), None);
+    assert_eq!(err.detail(), None);
+
+    let err = Error::from(SdkError::from(Malformed::CountTooLarge {
+        count: 9,
+        max: 4,
+    }));
+    assert_eq!(
+        err.kind(),
+        SdkError::MalformedResponse(Malformed::CountTooLarge { count: 9, max: 4 })
+    );
+    assert_eq!(
+        err.to_string(),
+        "malformed SDK response: count 9 exceeds the maximum of 4"
+    );
+    assert_eq!(
+        Error::from(SdkError::SymbolMissing("CorsairConnect")).to_string(),
+        "iCUE SDK library has no symbol `CorsairConnect`"
+    );
+    assert_eq!(
+        Error::from(SdkError::Unknown(99)).to_string(),
+        "unknown SDK error code: 99"
+    );
+}
+
+// One test, because only one session can exist per process.
+#[test]
+fn sdk_errors_name_the_call_and_its_target() {
+    let session = match cue_sdk::connect() {
+        Ok(session) => session,
+        // Built with `dynamic` and no SDK library installed.
+        Err(err) if err.kind() == SdkError::LibraryNotFound => {
+            assert_eq!(err.operation(), Some(Operation::Connect));
+            assert_eq!(err.device_id(), None);
+            assert_eq!(err.code(), None);
+            let text = err.to_string();
+            assert!(
+                text.starts_with("connect failed: iCUE SDK library not found: tried "),
+                "{text}"
+            );
+            let state = err.session_state().unwrap();
+            assert!(text.ends_with(&format!(" (session {state:?})")), "{text}");
+            return;
+        }
+        Err(err) => panic!("connect() should succeed even without iCUE: {err}"),
+    };
+    let device_id: DeviceId = "{no-such-device}".parse().unwrap();
+
+    // Without iCUE every call fails with the SDK's `CE_NotConnected`.
+    let err = session.get_device_info(&device_id).unwrap_err();
+    assert_eq!(err.kind(), SdkError::NotConnected);
+    assert_eq!(err.operation(), Some(Operation::GetDeviceInfo));
+    assert_eq!(err.device_id(), Some(&device_id));
+    assert_eq!(err.property(), None);
+    let code = err.code().expect("the error came from the SDK");
+    let state = err.session_state().unwrap();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "get_device_info failed: not connected to iCUE \
+             (device {device_id}, code {code}, session {state:?})"
+        )
+    );
+
+    let err = session
+        .read_device_property(&device_id, PropertyId::BatteryLevel, 0)
+        .unwrap_err();
+    assert_eq!(err.kind(), SdkError::NotConnected);
+    assert_eq!(err.property(), Some(PropertyId::BatteryLevel));
+    let state = err.session_state().unwrap();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "read_device_property failed: not connected to iCUE \
+             (device {device_id}, property BatteryLevel, code {code}, session {state:?})"
+        )
+    );
+}
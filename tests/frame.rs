@@ -0,0 +1,120 @@
+//! Dense LED frames and their dirty tracking.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::{DeviceId, LedFrame, LedPosition, Rgba};
+
+const RED: Rgba = Rgba::rgb(255, 0, 0);
+
+/// A clean frame of `len` LEDs with LUIDs 100, 101, ...
+fn frame(len: u32) -> LedFrame {
+    let positions: Vec<LedPosition> = (0..len)
+        .map(|i| LedPosition {
+            id: 100 + i,
+            cx: f64::from(i),
+            cy: 0.0,
+        })
+        .collect();
+    let mut frame = LedFrame::new("{keyboard}".parse::<DeviceId>().unwrap(), &positions);
+    frame.clear_dirty();
+    frame
+}
+
+/// The LUID ranges of the slices that would be sent.
+fn dirty_runs(frame: &mut LedFrame) -> Vec<(u32, u32)> {
+    frame
+        .dirty_slices()
+        .map(|s| (s[0].id, s[s.len() - 1].id))
+        .collect()
+}
+
+#[test]
+fn new_frames_are_black_and_fully_dirty() {
+    let positions = [
+        LedPosition {
+            id: 7,
+            cx: 0.0,
+            cy: 0.0,
+        },
+        LedPosition {
+            id: 3,
+            cx: 1.0,
+            cy: 0.0,
+        },
+    ];
+    let mut frame = LedFrame::new("{mouse}".parse().unwrap(), &positions);
+    assert_eq!(frame.len(), 2);
+    assert_eq!(frame.get(7), Some(Rgba::BLACK));
+    assert_eq!(frame.dirty_count(), 2);
+    assert_eq!(dirty_runs(&mut frame), vec![(7, 3)]);
+    assert_eq!(frame.luids().collect::<Vec<_>>(), vec![7, 3]);
+}
+
+#[test]
+fn unknown_luids_are_rejected() {
+    let mut frame = frame(4);
+    assert_eq!(frame.index_of(102), Some(2));
+    assert_eq!(frame.index_of(99), None);
+    assert_eq!(frame.index_of(104), None);
+    assert!(!frame.contains(1));
+    assert_eq!(frame.get(1), None);
+
+    assert!(!frame.set(1, RED));
+    frame.fill_leds([1, 2, 103], RED);
+    assert_eq!(frame.dirty_count(), 1);
+    assert_eq!(frame.get(103), Some(RED));
+}
+
+#[test]
+fn only_changed_colors_are_dirty() {
+    let mut frame = frame(4);
+    assert!(frame.set(101, Rgba::BLACK));
+    assert!(!frame.is_dirty());
+
+    assert!(frame.set(101, RED));
+    assert!(frame.set(101, RED));
+    assert_eq!(frame.dirty_count(), 1);
+    assert_eq!(frame.get(101), Some(RED));
+
+    frame.fill(RED);
+    assert_eq!(frame.dirty_count(), 4);
+    frame.clear_dirty();
+    frame.fill(RED);
+    assert!(!frame.is_dirty());
+    assert_eq!(
+        frame.colors().iter().filter(|c| c.color() == RED).count(),
+        4
+    );
+
+    frame.mark_all_dirty();
+    assert_eq!(frame.dirty_count(), 4);
+}
+
+#[test]
+fn contiguous_changes_are_sent_as_runs() {
+    let mut frame = frame(10);
+    for luid in [101, 102, 103, 106, 109] {
+        frame.set(luid, RED);
+    }
+    assert_eq!(
+        dirty_runs(&mut frame),
+        vec![(101, 103), (106, 106), (109, 109)]
+    );
+    // Taking the slices does not clean the frame.
+    assert_eq!(frame.dirty_count(), 5);
+}
+
+#[test]
+fn scattered_changes_are_coalesced_past_the_run_limit() {
+    let mut frame = frame(40);
+    // Eight separate runs are sent separately.
+    for i in 0..8 {
+        frame.set(101 + 2 * i, RED);
+    }
+    assert_eq!(dirty_runs(&mut frame).len(), 8);
+
+    // A ninth run makes one slice from the first to the last dirty LED.
+    frame.set(130, RED);
+    assert_eq!(dirty_runs(&mut frame), vec![(101, 130)]);
+    assert_eq!(frame.dirty_slices().next().unwrap().len(), 30);
+}
@@ -0,0 +1,149 @@
+//! Gesture recognition driven by a manual clock.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use std::time::Duration;
+
+use cue_sdk::gesture::{Gesture, GestureConfig, GestureRecognizer, ManualClock};
+use cue_sdk::MacroKeyId::{self, Key1, Key2, Key3};
+use cue_sdk::{DeviceId, Event};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+struct Keys {
+    clock: ManualClock,
+    gestures: GestureRecognizer<ManualClock>,
+    device_id: DeviceId,
+}
+
+impl Keys {
+    fn new(config: GestureConfig) -> Self {
+        let clock = ManualClock::new();
+        let mut gestures = GestureRecognizer::with_clock(clock.clone());
+        gestures.set_default_config(config);
+        Self {
+            clock,
+            gestures,
+            device_id: "{keyboard}".parse().unwrap(),
+        }
+    }
+
+    fn edge(&mut self, key_id: MacroKeyId, is_pressed: bool) -> Vec<Gesture> {
+        let event = Event::KeyEvent {
+            device_id: self.device_id,
+            key_id,
+            is_pressed,
+        };
+        self.gestures
+            .handle_event(&event)
+            .into_iter()
+            .map(|e| e.gesture)
+            .collect()
+    }
+
+    fn wait(&mut self, by: Duration) -> Vec<Gesture> {
+        self.clock.advance(by);
+        self.gestures
+            .poll()
+            .into_iter()
+            .map(|e| e.gesture)
+            .collect()
+    }
+}
+
+#[test]
+fn tap_double_tap_and_long_press() {
+    let mut keys = Keys::new(GestureConfig::default());
+
+    keys.edge(Key1, true);
+    keys.wait(ms(100));
+    assert_eq!(keys.edge(Key1, false), []);
+    assert_eq!(keys.wait(ms(400)), [Gesture::Tap(Key1)]);
+
+    keys.edge(Key1, true);
+    keys.wait(ms(50));
+    keys.edge(Key1, false);
+    keys.wait(ms(100));
+    keys.edge(Key1, true);
+    keys.wait(ms(50));
+    assert_eq!(keys.edge(Key1, false), [Gesture::DoubleTap(Key1)]);
+    assert_eq!(keys.gestures.next_deadline(), None);
+
+    keys.edge(Key2, true);
+    assert_eq!(keys.wait(ms(999)), []);
+    assert_eq!(keys.wait(ms(1)), [Gesture::LongPress(Key2)]);
+    assert_eq!(keys.edge(Key2, false), []);
+}
+
+#[test]
+fn hold_repeat() {
+    let mut keys = Keys::new(GestureConfig::default().hold_repeat(ms(500), ms(100)));
+    keys.edge(Key1, true);
+    assert_eq!(keys.wait(ms(499)), []);
+    assert_eq!(
+        keys.wait(ms(201)),
+        [
+            Gesture::HoldRepeat {
+                key: Key1,
+                count: 1
+            },
+            Gesture::HoldRepeat {
+                key: Key1,
+                count: 2
+            },
+            Gesture::HoldRepeat {
+                key: Key1,
+                count: 3
+            },
+        ]
+    );
+}
+
+#[test]
+fn chords_and_sequences() {
+    let mut keys = Keys::new(
+        GestureConfig::default()
+            .chord(&[Key1, Key2])
+            .sequence(&[Key3, Key3, Key1])
+            .double_tap(Duration::ZERO),
+    );
+
+    keys.edge(Key2, true);
+    keys.wait(ms(20));
+    assert_eq!(keys.edge(Key1, true), [Gesture::Chord(vec![Key1, Key2])]);
+    keys.edge(Key1, false);
+    assert_eq!(keys.edge(Key2, false), []);
+
+    // Too far apart to be a chord.
+    keys.wait(ms(2000));
+    keys.edge(Key1, true);
+    keys.wait(ms(100));
+    assert_eq!(keys.edge(Key2, true), []);
+
+    keys.edge(Key1, false);
+    keys.edge(Key2, false);
+
+    let press = |keys: &mut Keys, key, gap| {
+        keys.wait(gap);
+        let mut seen = keys.edge(key, true);
+        keys.wait(ms(10));
+        seen.extend(keys.edge(key, false));
+        seen
+    };
+    press(&mut keys, Key3, ms(100));
+    press(&mut keys, Key3, ms(100));
+    assert_eq!(
+        press(&mut keys, Key1, ms(100)),
+        [
+            Gesture::Sequence(vec![Key3, Key3, Key1]),
+            Gesture::Tap(Key1)
+        ]
+    );
+
+    // A gap longer than the sequence timeout starts over.
+    press(&mut keys, Key3, ms(100));
+    press(&mut keys, Key3, ms(100));
+    assert_eq!(press(&mut keys, Key1, ms(1500)), [Gesture::Tap(Key1)]);
+}
@@ -0,0 +1,82 @@
+//! Typed property value extraction.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::property::DataType;
+use cue_sdk::{DeviceType, FromPropertyValue, MacroKeyId, PropertyId, PropertyValue, SdkError};
+
+#[test]
+fn typed_extraction_checks_the_data_type() {
+    let value = PropertyValue::Int32Array(vec![1, 2]);
+    assert_eq!(value.data_type(), DataType::Int32Array);
+    assert_eq!(<Vec<i32>>::DATA_TYPE, Some(DataType::Int32Array));
+    assert_eq!(
+        <Vec<i32>>::from_property_value(value.clone()),
+        Some(vec![1, 2])
+    );
+    assert_eq!(i32::from_property_value(value.clone()), None);
+    assert_eq!(
+        PropertyValue::from_property_value(value.clone()),
+        Some(value)
+    );
+
+    let err = SdkError::TypeMismatch {
+        expected: DataType::Boolean,
+        actual: DataType::Int32,
+    };
+    assert_eq!(
+        err.to_string(),
+        "property type mismatch: expected Boolean, got Int32"
+    );
+}
+
+#[test]
+fn property_names_round_trip() {
+    for property in PropertyId::ALL {
+        assert_eq!(PropertyId::from_name(property.name()), Some(property));
+    }
+    assert_eq!(PropertyId::from_name("mic_muted"), None);
+}
+
+#[test]
+fn unknown_ids_keep_their_raw_value() {
+    let property = PropertyId::from_raw(77);
+    assert!(matches!(property, PropertyId::Unknown(raw) if raw.get() == 77));
+    assert_eq!(property.raw(), 77);
+    assert_eq!(property.name(), "unknown");
+    assert_eq!(property.to_string(), "unknown(77)");
+    assert_eq!(PropertyId::MicEnabled.to_string(), "mic_enabled");
+    assert!(!PropertyId::ALL.contains(&property));
+
+    assert_eq!(MacroKeyId::Key5.number(), 5);
+    assert_eq!(MacroKeyId::from_number(42).number(), 42);
+
+    let device_type = DeviceType::from_bits_retain(0x8000 | DeviceType::MOUSE.bits());
+    assert!(device_type.contains(DeviceType::MOUSE));
+    assert_eq!(device_type.unknown_bits(), 0x8000);
+    assert_eq!(DeviceType::KEYBOARD.unknown_bits(), 0);
+}
+
+#[test]
+fn raw_ids_of_known_values_are_not_unknown() {
+    for property in PropertyId::ALL {
+        assert_eq!(PropertyId::from_raw(property.raw()), property);
+    }
+    for key in MacroKeyId::ALL {
+        assert_eq!(MacroKeyId::from_number(key.number()), key);
+    }
+    assert_eq!(MacroKeyId::from_number(1), MacroKeyId::Key1);
+    assert!(matches!(MacroKeyId::from_number(21), MacroKeyId::Unknown(raw) if raw.get() == 21));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn known_ids_do_not_deserialize_as_unknown() {
+    let unknown = PropertyId::from_raw(77);
+    let json = serde_json::to_string(&unknown).unwrap();
+    assert_eq!(json, r#"{"Unknown":77}"#);
+    assert_eq!(serde_json::from_str::<PropertyId>(&json).unwrap(), unknown);
+
+    let known = format!(r#"{{"Unknown":{}}}"#, PropertyId::BatteryLevel.raw());
+    assert!(serde_json::from_str::<PropertyId>(&known).is_err());
+}
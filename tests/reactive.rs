@@ -0,0 +1,84 @@
+//! Key-press effects stepped with explicit timestamps.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use std::time::{Duration, Instant};
+
+use cue_sdk::compositor::Layer;
+use cue_sdk::reactive::{Decay, Effect, KeyMap, ReactiveEffects};
+use cue_sdk::{DeviceId, Event, LedGroup, LedPosition, MacroKeyId, Rgba};
+
+const RED: Rgba = Rgba::rgb(255, 0, 0);
+
+fn g(n: u16) -> u32 {
+    LedGroup::KeyboardGKeys.luid(n)
+}
+
+/// Three G keys in a row, one unit apart.
+fn positions() -> Vec<LedPosition> {
+    (1..=3)
+        .map(|n| LedPosition {
+            id: g(n),
+            cx: f64::from(n),
+            cy: 0.0,
+        })
+        .collect()
+}
+
+fn device() -> DeviceId {
+    "{keyboard}".parse().unwrap()
+}
+
+#[test]
+fn ripple_expands_and_fades() {
+    let mut effects = ReactiveEffects::new(device(), &positions()).with_effect(Effect::Ripple {
+        color: RED,
+        speed: 10.0,
+        width: 0.5,
+        decay: Decay::Linear(Duration::from_millis(400)),
+    });
+    let start = Instant::now();
+    assert!(effects.press(MacroKeyId::Key1, start));
+
+    assert_eq!(effects.color(g(1), start), Some(RED));
+    assert_eq!(effects.color(g(2), start), None);
+
+    // After 100 ms the ring has reached G2 and lost a quarter of its alpha.
+    let later = start + Duration::from_millis(100);
+    assert_eq!(effects.color(g(1), later), None);
+    assert_eq!(effects.color(g(2), later), Some(Rgba::new(255, 0, 0, 191)));
+
+    let mut layer = Layer::new("ripple");
+    effects.render(start + Duration::from_millis(500), &mut layer);
+    assert_eq!(effects.active_count(start), 0);
+    assert_eq!(layer.get(g(1)), None);
+}
+
+#[test]
+fn events_for_other_devices_and_unmapped_keys_are_ignored() {
+    let mut keys = KeyMap::new();
+    keys.insert(MacroKeyId::Key2, g(3));
+    let mut effects = ReactiveEffects::new(device(), &positions())
+        .with_keys(keys)
+        .with_effect(Effect::Toggle {
+            on: RED,
+            off: Some(Rgba::BLACK),
+        });
+    let now = Instant::now();
+    let press = |device_id, key_id| Event::KeyEvent {
+        device_id,
+        key_id,
+        is_pressed: true,
+    };
+
+    assert!(!effects.handle_event(&press("{mouse}".parse().unwrap(), MacroKeyId::Key2), now));
+    assert!(!effects.handle_event(&press(device(), MacroKeyId::Key1), now));
+    assert_eq!(effects.color(g(3), now), Some(Rgba::BLACK));
+
+    assert!(effects.handle_event(&press(device(), MacroKeyId::Key2), now));
+    assert!(effects.is_toggled(MacroKeyId::Key2));
+    assert_eq!(effects.color(g(3), now), Some(RED));
+
+    effects.press(MacroKeyId::Key2, now);
+    assert_eq!(effects.color(g(3), now), Some(Rgba::BLACK));
+}
@@ -0,0 +1,139 @@
+//! Property report diffing and serialisation.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::report::{DeviceReport, PropertyReading, PropertyReport, ReportChange};
+use cue_sdk::{DeviceId, PropertyId, PropertyValue};
+
+fn reading(property: PropertyId, index: u32, value: PropertyValue) -> PropertyReading {
+    PropertyReading {
+        property,
+        index,
+        value: Ok(value),
+    }
+}
+
+fn device(serial: &str, properties: Vec<PropertyReading>) -> DeviceReport {
+    DeviceReport {
+        device_id: format!("{{{serial}}}").parse::<DeviceId>().unwrap(),
+        serial: serial.into(),
+        model: "Test Device".into(),
+        led_count: 10,
+        channel_count: 2,
+        properties,
+    }
+}
+
+fn working() -> PropertyReport {
+    PropertyReport {
+        devices: vec![
+            device(
+                "HEADSET",
+                vec![
+                    reading(PropertyId::MicEnabled, 0, PropertyValue::Boolean(true)),
+                    reading(PropertyId::BatteryLevel, 0, PropertyValue::Int32(80)),
+                ],
+            ),
+            device(
+                "HUB",
+                vec![
+                    reading(PropertyId::ChannelLedCount, 0, PropertyValue::Int32(16)),
+                    reading(PropertyId::ChannelLedCount, 1, PropertyValue::Int32(8)),
+                ],
+            ),
+        ],
+    }
+}
+
+#[test]
+fn identical_reports_have_no_changes() {
+    assert!(working().diff(&working()).is_empty());
+}
+
+#[test]
+fn diff_reports_every_kind_of_change() {
+    let before = working();
+    let mut after = working();
+    after.devices[0].properties[1] = PropertyReading {
+        property: PropertyId::BatteryLevel,
+        index: 0,
+        value: Err("device not connected".into()),
+    };
+    after.devices[0].properties.push(reading(
+        PropertyId::SidetoneEnabled,
+        0,
+        PropertyValue::Boolean(false),
+    ));
+    after.devices[1].properties.remove(1);
+    after.devices[1].serial = "HUB2".into();
+    after.devices.push(device("MOUSE", Vec::new()));
+
+    let changes = before.diff(&after);
+    assert_eq!(
+        changes,
+        vec![
+            ReportChange::PropertyChanged {
+                serial: "HEADSET".into(),
+                property: PropertyId::BatteryLevel,
+                index: 0,
+                old: Ok(PropertyValue::Int32(80)),
+                new: Err("device not connected".into()),
+            },
+            ReportChange::PropertyAdded {
+                serial: "HEADSET".into(),
+                property: PropertyId::SidetoneEnabled,
+                index: 0,
+                new: Ok(PropertyValue::Boolean(false)),
+            },
+            ReportChange::DeviceRemoved {
+                serial: "HUB".into(),
+                model: "Test Device".into(),
+            },
+            ReportChange::DeviceAdded {
+                serial: "HUB2".into(),
+                model: "Test Device".into(),
+            },
+            ReportChange::DeviceAdded {
+                serial: "MOUSE".into(),
+                model: "Test Device".into(),
+            },
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "~ HEADSET battery_level[0]: Int32(80) -> <device not connected>"
+    );
+}
+
+#[test]
+fn indexed_properties_are_compared_per_index() {
+    let before = working();
+    let mut after = working();
+    after.devices[1].properties[1].value = Ok(PropertyValue::Int32(9));
+    after.devices[1].properties.remove(0);
+
+    let changes = before.diff(&after);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(
+        changes[0].to_string(),
+        "- HUB channel_led_count[0] = Int32(16)"
+    );
+    assert_eq!(
+        changes[1].to_string(),
+        "~ HUB channel_led_count[1]: Int32(8) -> Int32(9)"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn report_round_trips_through_json() {
+    let report = working();
+    let json = serde_json::to_string(&report).unwrap();
+    let back: PropertyReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, report);
+    assert!(back
+        .device("HUB")
+        .unwrap()
+        .get(PropertyId::ChannelLedCount, 1)
+        .is_some());
+}
@@ -0,0 +1,73 @@
+//! Retry policy behaviour.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cue_sdk::retry::{Backoff, RetryPolicy};
+use cue_sdk::{Result, SdkError};
+
+#[test]
+fn retries_transient_errors_until_success() {
+    let retries = Arc::new(AtomicU32::new(0));
+    let seen = retries.clone();
+    let policy = RetryPolicy::default()
+        .max_attempts(5)
+        .backoff(Backoff::None)
+        .on_retry(move |ev| {
+            assert_eq!(ev.error.kind(), SdkError::DeviceNotFound);
+            seen.fetch_add(1, Ordering::Relaxed);
+        });
+
+    let mut calls = 0;
+    let result: Result<u32> = policy.run(|| {
+        calls += 1;
+        if calls < 3 {
+            Err(SdkError::DeviceNotFound.into())
+        } else {
+            Ok(calls)
+        }
+    });
+
+    assert_eq!(result, Ok(3));
+    assert_eq!(retries.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn stops_on_non_retryable_error_and_attempt_limit() {
+    let policy = RetryPolicy::default()
+        .max_attempts(3)
+        .backoff(Backoff::None);
+
+    let mut calls = 0;
+    let result: Result<()> = policy.run(|| {
+        calls += 1;
+        Err(SdkError::InvalidArguments.into())
+    });
+    assert_eq!(result.unwrap_err(), SdkError::InvalidArguments);
+    assert_eq!(calls, 1);
+
+    let mut calls = 0;
+    let result: Result<()> = policy.run(|| {
+        calls += 1;
+        Err(SdkError::NotConnected.into())
+    });
+    assert_eq!(result.unwrap_err(), SdkError::NotConnected);
+    assert_eq!(calls, 3);
+}
+
+#[test]
+fn exponential_backoff_is_capped() {
+    let backoff = Backoff::Exponential {
+        initial: Duration::from_millis(10),
+        max: Duration::from_millis(50),
+        factor: 2.0,
+    };
+    assert_eq!(backoff.delay(1), Duration::from_millis(10));
+    assert_eq!(backoff.delay(2), Duration::from_millis(20));
+    assert_eq!(backoff.delay(3), Duration::from_millis(40));
+    assert_eq!(backoff.delay(4), Duration::from_millis(50));
+    assert_eq!(backoff.delay(100), Duration::from_millis(50));
+}
@@ -0,0 +1,32 @@
+//! The one-session-per-process guard.
+//!
+//! These tests do not need the iCUE SDK; with the `dynamic` feature and no
+//! library installed, connecting fails after the guard is taken, which must
+//! release it again.
+
+#![cfg(feature = "dynamic")]
+
+use cue_sdk::{dynamic, Operation, SdkError, Session};
+
+// One test, because the guard and search paths are process-wide.
+#[test]
+fn failed_connections_release_the_session() {
+    dynamic::set_search_paths([std::env::temp_dir().join("cue-sdk-no-such-dir")]);
+
+    for _ in 0..2 {
+        let err = Session::connect().err().unwrap();
+        assert_eq!(err.kind(), SdkError::LibraryNotFound);
+        assert_eq!(err.operation(), Some(Operation::Connect));
+    }
+
+    // A failed shared session is not cached; the next call tries again.
+    for _ in 0..2 {
+        let err = Session::shared().err().unwrap();
+        assert_eq!(err.kind(), SdkError::LibraryNotFound);
+    }
+    assert!(Session::connect().is_err_and(|e| e.kind() == SdkError::LibraryNotFound));
+    assert_eq!(
+        SdkError::AlreadyConnected.to_string(),
+        "a session is already connected in this process"
+    );
+}
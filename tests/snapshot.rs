@@ -0,0 +1,149 @@
+//! Lighting snapshot restore planning and serialisation.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use std::collections::HashMap;
+
+use cue_sdk::snapshot::{DeviceSnapshot, LightingSnapshot, RestoreStep};
+use cue_sdk::{AccessLevel, DeviceId, DeviceInfo, DeviceType, LedColor};
+
+fn id(s: &str) -> DeviceId {
+    s.parse().unwrap()
+}
+
+fn info(device_id: &str, serial: &str) -> DeviceInfo {
+    DeviceInfo {
+        device_type: DeviceType::KEYBOARD,
+        id: id(device_id),
+        serial: serial.into(),
+        model: "K70".into(),
+        led_count: 1,
+        channel_count: 0,
+    }
+}
+
+fn device(device_id: &str, serial: &str, access_level: Option<AccessLevel>) -> DeviceSnapshot {
+    DeviceSnapshot {
+        device_id: id(device_id),
+        serial: serial.into(),
+        access_level,
+        colors: vec![LedColor::rgb(1, 255, 0, 0)],
+    }
+}
+
+#[test]
+fn devices_are_matched_by_serial_then_id() {
+    let snapshot = LightingSnapshot {
+        layer_priority: None,
+        devices: vec![
+            device("{old-keyboard}", "ABC123", None),
+            device("{no-serial}", "", None),
+            device("{unplugged}", "GONE", None),
+        ],
+    };
+    // The keyboard was replugged and has a new id.
+    let connected = [info("{new-keyboard}", "ABC123"), info("{no-serial}", "")];
+
+    let steps = snapshot.restore_steps(None, &connected, |_| None);
+    let colors = &snapshot.devices[0].colors[..];
+    assert_eq!(
+        steps,
+        vec![
+            RestoreStep::SetColors {
+                device_id: id("{new-keyboard}"),
+                colors,
+            },
+            RestoreStep::SetColors {
+                device_id: id("{no-serial}"),
+                colors,
+            },
+        ]
+    );
+}
+
+#[test]
+fn control_is_changed_only_where_it_differs() {
+    let exclusive = AccessLevel::ExclusiveLightingControl;
+    let mut snapshot = LightingSnapshot {
+        layer_priority: None,
+        devices: vec![
+            device("{a}", "A", Some(exclusive)),
+            device("{b}", "B", Some(exclusive)),
+            device("{c}", "C", None),
+            device("{d}", "D", None),
+        ],
+    };
+    for d in &mut snapshot.devices {
+        d.colors.clear();
+    }
+    let connected = [
+        info("{a}", "A"),
+        info("{b}", "B"),
+        info("{c}", "C"),
+        info("{d}", "D"),
+    ];
+    // Now: `a` released, `b` still held, `c` taken after the capture.
+    let held = HashMap::from([(id("{b}"), exclusive), (id("{c}"), exclusive)]);
+
+    let steps = snapshot.restore_steps(None, &connected, |id| held.get(id).copied());
+    assert_eq!(
+        steps,
+        vec![
+            RestoreStep::RequestControl {
+                device_id: id("{a}"),
+                level: exclusive,
+            },
+            RestoreStep::ReleaseControl {
+                device_id: id("{c}"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn layer_priority_set_after_capture_is_reset() {
+    let before = LightingSnapshot::default();
+    assert_eq!(
+        before.restore_steps(Some(200), &[], |_| None),
+        vec![RestoreStep::ResetLayerPriority]
+    );
+    assert!(before.restore_steps(None, &[], |_| None).is_empty());
+
+    let with_priority = LightingSnapshot {
+        layer_priority: Some(50),
+        devices: Vec::new(),
+    };
+    assert_eq!(
+        with_priority.restore_steps(Some(200), &[], |_| None),
+        vec![RestoreStep::SetLayerPriority(50)]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn snapshot_round_trips_through_json() {
+    let snapshot = LightingSnapshot {
+        layer_priority: Some(200),
+        devices: vec![DeviceSnapshot {
+            device_id: "{4b2c-keyboard}".parse::<DeviceId>().unwrap(),
+            serial: "ABC123".into(),
+            access_level: Some(AccessLevel::ExclusiveLightingControl),
+            colors: vec![
+                LedColor::rgb(1, 255, 0, 0),
+                LedColor::new(2, 0, 0, 255, 128),
+            ],
+        }],
+    };
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert!(json.contains("\"{4b2c-keyboard}\""));
+    let back: LightingSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, snapshot);
+    assert_eq!(back.device("ABC123").unwrap().colors.len(), 2);
+}
+
+#[test]
+fn device_id_rejects_oversized_strings() {
+    assert!("x".repeat(127).parse::<DeviceId>().is_ok());
+    assert!("x".repeat(128).parse::<DeviceId>().is_err());
+}
@@ -0,0 +1,153 @@
+//! Logging through the optional `tracing` feature.
+//!
+//! The event tests are pure Rust and use the retry loop, which logs each
+//! retry.  Without the feature, logging must compile to nothing and leave
+//! the behaviour unchanged.
+//!
+//! The span test connects like `smoke.rs` and needs the SDK library but not
+//! iCUE; built with the `dynamic` feature and no library installed, it does
+//! nothing.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use cue_sdk::retry::{Backoff, RetryPolicy};
+use cue_sdk::{Result, SdkError};
+
+/// Run a call that fails twice with a retryable error, returning the result
+/// and the number of retry hooks that ran.
+fn retry_twice() -> (Result<u32>, u32) {
+    let retries = Arc::new(AtomicU32::new(0));
+    let seen = retries.clone();
+    let policy = RetryPolicy::default()
+        .max_attempts(3)
+        .backoff(Backoff::None)
+        .on_retry(move |_| {
+            seen.fetch_add(1, Ordering::Relaxed);
+        });
+    let mut calls = 0;
+    let result = policy.run(|| {
+        calls += 1;
+        if calls < 3 {
+            Err(SdkError::Timeout.into())
+        } else {
+            Ok(calls)
+        }
+    });
+    (result, retries.load(Ordering::Relaxed))
+}
+
+#[cfg(not(feature = "tracing"))]
+#[test]
+fn logging_is_a_no_op_without_the_feature() {
+    assert_eq!(retry_twice(), (Ok(3), 2));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn retries_are_logged_with_the_feature() {
+    use std::sync::atomic::AtomicUsize;
+
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    /// Counts `DEBUG` events from this crate.
+    struct Counter(Arc<AtomicUsize>);
+
+    impl Subscriber for Counter {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let meta = event.metadata();
+            if meta.target().starts_with("cue_sdk") && *meta.level() == Level::DEBUG {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let events = Arc::new(AtomicUsize::new(0));
+    let result = tracing::subscriber::with_default(Counter(events.clone()), retry_twice);
+    assert_eq!(result, (Ok(3), 2));
+    assert_eq!(events.load(Ordering::Relaxed), 2);
+
+    // Without a subscriber the events go nowhere.
+    assert_eq!(retry_twice(), (Ok(3), 2));
+    assert_eq!(events.load(Ordering::Relaxed), 2);
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn spans_record_the_call_arguments() {
+    use std::fmt;
+    use std::sync::Mutex;
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use cue_sdk::{DeviceId, PropertyId};
+
+    type Fields = Arc<Mutex<Vec<String>>>;
+
+    /// Records the fields of every span as `span.field=value`.
+    struct SpanFields(Fields);
+
+    struct Visitor<'a>(&'a str, &'a mut Vec<String>);
+
+    impl Visit for Visitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.1
+                .push(format!("{}.{}={value:?}", self.0, field.name()));
+        }
+    }
+
+    impl Subscriber for SpanFields {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = self.0.lock().unwrap();
+            span.record(&mut Visitor(span.metadata().name(), &mut fields));
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let session = match cue_sdk::connect() {
+        Ok(session) => session,
+        Err(err) if err.kind() == SdkError::LibraryNotFound => return,
+        Err(err) => panic!("connect() should succeed even without iCUE: {err}"),
+    };
+    let device_id: DeviceId = "{no-such-device}".parse().unwrap();
+
+    let fields = Fields::default();
+    tracing::subscriber::with_default(SpanFields(fields.clone()), || {
+        // Without iCUE these fail, but their spans are still recorded.
+        let _ = session.set_layer_priority(200);
+        let _ = session.read_device_property(&device_id, PropertyId::BatteryLevel, 3);
+        let _ = session.get_device_property_info(&device_id, PropertyId::BatteryLevel, 4);
+    });
+    let fields = fields.lock().unwrap();
+    for expected in [
+        "set_layer_priority.priority=200",
+        "read_device_property.index=3",
+        "get_device_property_info.index=4",
+    ] {
+        assert!(
+            fields.iter().any(|f| f == expected),
+            "{expected} in {fields:?}"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Version ordering, parsing and server capability checks.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::capabilities::Feature;
+use cue_sdk::{SdkError, ServerCapabilities, SessionDetails, Version};
+
+fn details(client: Version, server: Version) -> SessionDetails {
+    SessionDetails {
+        client_version: client,
+        server_version: server,
+        server_host_version: Version::new(5, 9, 105),
+    }
+}
+
+#[test]
+fn versions_order_by_major_minor_patch() {
+    assert!(Version::new(4, 0, 0) < Version::new(4, 0, 1));
+    assert!(Version::new(4, 0, 9) < Version::new(4, 1, 0));
+    assert!(Version::new(4, 9, 9) < Version::new(5, 0, 0));
+    assert_eq!(
+        Version::new(4, 2, 0).max(Version::new(4, 10, 0)),
+        Version::new(4, 10, 0)
+    );
+}
+
+#[test]
+fn versions_parse_with_or_without_patch() {
+    assert_eq!("4.0.48".parse(), Ok(Version::new(4, 0, 48)));
+    assert_eq!(" 4.1 ".parse(), Ok(Version::new(4, 1, 0)));
+    let v = Version::new(5, 9, 105);
+    assert_eq!(v.to_string().parse(), Ok(v));
+
+    for bad in ["", "4", "4.x", "4.0.0.0", "4..0", "a.b.c"] {
+        assert_eq!(
+            bad.parse::<Version>(),
+            Err(SdkError::InvalidArguments),
+            "{bad:?}"
+        );
+    }
+}
+
+#[test]
+fn compatible_servers_pass_the_check() {
+    let d = details(Version::new(4, 0, 48), Version::new(4, 0, 48));
+    assert!(d.check_server(SessionDetails::MIN_SERVER_VERSION).is_ok());
+
+    let d = details(Version::new(4, 0, 48), Version::new(4, 2, 0));
+    assert!(d.check_server(Version::new(4, 1, 0)).is_ok());
+}
+
+#[test]
+fn incompatible_servers_name_every_version() {
+    let d = details(Version::new(4, 0, 48), Version::new(3, 0, 171));
+    let err = d
+        .check_server(SessionDetails::MIN_SERVER_VERSION)
+        .unwrap_err();
+    assert_eq!(err.kind(), SdkError::IncompatibleProtocol);
+    assert_eq!(
+        err.detail(),
+        Some(
+            "client 4.0.48, server 3.0.171, host 5.9.105; requires a 4.x server of at least 4.0.0"
+        )
+    );
+
+    // A newer major version speaks a different protocol.
+    let d = details(Version::new(4, 0, 48), Version::new(5, 0, 0));
+    assert_eq!(
+        d.check_server(SessionDetails::MIN_SERVER_VERSION)
+            .unwrap_err()
+            .kind(),
+        SdkError::IncompatibleProtocol
+    );
+
+    // So does a server older than the required minimum.
+    let d = details(Version::new(4, 0, 48), Version::new(4, 0, 10));
+    assert!(d.check_server(Version::new(4, 0, 20)).is_err());
+}
+
+#[test]
+fn capabilities_follow_the_server_version() {
+    let caps = details(Version::new(4, 0, 48), Version::new(4, 0, 48)).capabilities();
+    assert_eq!(caps.server_version(), Version::new(4, 0, 48));
+    for feature in Feature::ALL {
+        assert!(caps.supports(feature), "{feature:?}");
+    }
+    assert_eq!(caps.iter().count(), Feature::ALL.len());
+
+    let old = ServerCapabilities::new(Version::new(3, 0, 171));
+    assert!(!old.supports(Feature::PropertyWrite));
+    assert_eq!(old.iter().count(), 0);
+}
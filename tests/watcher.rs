@@ -0,0 +1,76 @@
+//! Property watching with a fake reader and a manual clock.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use std::time::Duration;
+
+use cue_sdk::gesture::ManualClock;
+use cue_sdk::retry::Backoff;
+use cue_sdk::watcher::{PropertyEvent, PropertyWatcher};
+use cue_sdk::{DeviceId, PropertyId, PropertyValue, SdkError};
+
+#[test]
+fn reports_changes_and_backs_off_while_unavailable() {
+    let clock = ManualClock::new();
+    let mut watcher = PropertyWatcher::with_clock(clock.clone());
+    watcher.set_backoff(Backoff::Fixed(Duration::from_secs(5)));
+    let rx = watcher.subscribe();
+
+    let device_id: DeviceId = "{headset}".parse().unwrap();
+    let mic = watcher.watch(
+        &device_id,
+        PropertyId::MicEnabled,
+        0,
+        Duration::from_secs(1),
+    );
+
+    let mut reads = 0;
+    let mut poll = |watcher: &mut PropertyWatcher<ManualClock>, value: Option<bool>| {
+        watcher.poll_with(|_, _, _| {
+            reads += 1;
+            value
+                .map(PropertyValue::Boolean)
+                .ok_or_else(|| SdkError::DeviceNotFound.into())
+        })
+    };
+
+    let events = poll(&mut watcher, Some(true));
+    assert!(matches!(
+        &events[..],
+        [PropertyEvent::Changed {
+            old: None,
+            new: PropertyValue::Boolean(true),
+            ..
+        }]
+    ));
+
+    // Not due yet, then due but unchanged.
+    assert!(poll(&mut watcher, Some(false)).is_empty());
+    clock.advance(Duration::from_secs(1));
+    assert!(poll(&mut watcher, Some(true)).is_empty());
+
+    clock.advance(Duration::from_secs(1));
+    let events = poll(&mut watcher, None);
+    assert!(
+        matches!(&events[..], [PropertyEvent::Unavailable { error, .. }] if *error == SdkError::DeviceNotFound)
+    );
+
+    // Backing off: the 1 s interval is stretched to 5 s.
+    clock.advance(Duration::from_secs(4));
+    assert!(poll(&mut watcher, None).is_empty());
+    clock.advance(Duration::from_secs(1));
+    assert!(poll(&mut watcher, None).is_empty());
+    clock.advance(Duration::from_secs(5));
+    let events = poll(&mut watcher, Some(true));
+    assert!(matches!(
+        &events[..],
+        [PropertyEvent::Changed {
+            old: Some(PropertyValue::Boolean(true)),
+            ..
+        }]
+    ));
+    assert_eq!(reads, 5);
+
+    assert_eq!(watcher.value(&mic), Some(&PropertyValue::Boolean(true)));
+    assert_eq!(rx.try_iter().count(), 3);
+}
@@ -0,0 +1,76 @@
+//! Zone set operations and selector parsing.
+//!
+//! These tests are pure Rust and do not call into the iCUE SDK.
+
+use cue_sdk::led::keyboard;
+use cue_sdk::zone::{Selector, Zone, ZoneError, Zones};
+
+#[test]
+fn zone_set_operations() {
+    let f_row = Zone::builtin("f_row").unwrap();
+    let f5_to_f8 = Zone::range(keyboard::F5, keyboard::F8);
+
+    let without = f_row.difference(&f5_to_f8);
+    assert!(without.contains(keyboard::F4));
+    assert!(!without.contains(keyboard::F5));
+    assert!(!without.contains(keyboard::F8));
+    assert!(without.contains(keyboard::F9));
+    assert_eq!(without.ranges().len(), 2);
+
+    assert_eq!(without.union(&f5_to_f8), f_row);
+    assert_eq!(f_row.intersection(&f5_to_f8), f5_to_f8);
+    assert!(without.intersection(&f5_to_f8).is_empty());
+}
+
+#[test]
+fn zones_load_config() {
+    let mut zones = Zones::builtin();
+    zones
+        .load(
+            "# movement keys\n\
+             movement = @wasd, SPACE, LEFT_SHIFT\n\
+             fkeys = F1..F12, !F6\n",
+        )
+        .unwrap();
+
+    let movement = zones.get("movement").unwrap();
+    assert!(movement.contains(keyboard::W));
+    assert!(movement.contains(keyboard::SPACE));
+    assert!(!movement.contains(keyboard::Q));
+
+    let fkeys = zones.get("fkeys").unwrap();
+    assert!(fkeys.contains(keyboard::F5));
+    assert!(!fkeys.contains(keyboard::F6));
+
+    assert_eq!(
+        zones.load("broken = @nope"),
+        Err(ZoneError::UnknownZone("nope".into()))
+    );
+}
+
+#[test]
+fn selector_parsing() {
+    for ok in [
+        "keyboard:F1..F12",
+        "keyboard:@wasd, SPACE",
+        "mouse:*",
+        "mouse",
+        "*:!@logo",
+        "channel:1",
+        "channel:1/fan:2",
+    ] {
+        let selector: Selector = ok.parse().unwrap_or_else(|e| panic!("{ok}: {e}"));
+        assert_eq!(selector.to_string(), ok);
+    }
+
+    assert_eq!(
+        "toaster:*".parse::<Selector>(),
+        Err(ZoneError::UnknownTarget("toaster".into()))
+    );
+    assert_eq!(
+        "keyboard:F12..F1".parse::<Selector>(),
+        Err(ZoneError::InvalidRange("F12..F1".into()))
+    );
+    assert!("channel:0".parse::<Selector>().is_err());
+    assert!("keyboard:NOT_A_KEY".parse::<Selector>().is_err());
+}